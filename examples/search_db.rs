@@ -1,5 +1,6 @@
 use findagrave_citation_parser::db::search_memorials_by_name;
-use findagrave_citation_parser::{process_memorial, Config, Result};
+use findagrave_citation_parser::{process_memorials, BatchOptions, Config, Result};
+use futures::StreamExt;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -11,14 +12,20 @@ async fn main() -> Result<()> {
 
     println!("Fetching and storing memorial information...");
 
-    // Fetch and store the memorials
-    for id in &memorial_ids {
-        match process_memorial(id, &config, true).await {
+    // Fetch and store the memorials, a few at a time
+    let options = BatchOptions {
+        store_to_db: true,
+        ..BatchOptions::default()
+    };
+    let inputs = memorial_ids.iter().map(|id| id.to_string());
+    let mut results = process_memorials(inputs, &config, &options);
+    while let Some(item) = results.next().await {
+        match item.result {
             Ok(memorial) => {
                 println!("Stored memorial for: {}", memorial.name);
             }
             Err(err) => {
-                eprintln!("Error fetching memorial {}: {}", id, err);
+                eprintln!("Error fetching memorial {}: {}", item.input, err);
             }
         }
     }
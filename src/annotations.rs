@@ -0,0 +1,433 @@
+use crate::db::{merged_into, open_db};
+use crate::{Error, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+/// A research note on a memorial
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Note {
+    /// Note ID
+    pub id: i64,
+    /// ID of the memorial the note is about
+    pub memorial_id: u64,
+    /// Who wrote the note
+    pub author: String,
+    /// Text of the note
+    pub body: String,
+    /// UTC time the note was added (`YYYY-MM-DD HH:MM:SS`)
+    pub created_at: String,
+}
+
+/// A tag and the number of memorials carrying it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagCount {
+    /// Tag text
+    pub tag: String,
+    /// Number of tagged memorials
+    pub count: usize,
+}
+
+/// A named group of memorials worked on together
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Project {
+    /// Project ID
+    pub id: i64,
+    /// Unique project name
+    pub name: String,
+    /// Optional description of the project
+    pub description: Option<String>,
+    /// UTC time the project was created (`YYYY-MM-DD HH:MM:SS`)
+    pub created_at: String,
+    /// Number of memorials in the project
+    pub memorial_count: usize,
+}
+
+/// Tag a memorial
+///
+/// Tags are matched ignoring case; tags the memorial already has are left
+/// alone. Returns the number of newly added tags.
+pub fn add_tags(memorial_id: u64, tags: &[String], db_path: &str) -> Result<usize> {
+    let mut conn = open_db(db_path)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    let memorial_id = stored_memorial_id(&tx, memorial_id)?;
+
+    let mut added = 0;
+    for tag in tags {
+        added += tx
+            .execute(
+                "INSERT OR IGNORE INTO tags (memorial_id, tag) VALUES (?1, ?2)",
+                params![memorial_id, label("Tag", tag)?],
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    }
+
+    tx.commit()
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(added)
+}
+
+/// Remove tags from a memorial, returning how many were removed
+pub fn remove_tags(memorial_id: u64, tags: &[String], db_path: &str) -> Result<usize> {
+    let conn = open_db(db_path)?;
+    let memorial_id = merged_into(&conn, memorial_id)?.unwrap_or(memorial_id);
+
+    let mut removed = 0;
+    for tag in tags {
+        removed += conn
+            .execute(
+                "DELETE FROM tags WHERE memorial_id = ?1 AND tag = ?2",
+                params![memorial_id, tag.trim()],
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    }
+    Ok(removed)
+}
+
+/// List the tags on a memorial, alphabetically
+pub fn memorial_tags(memorial_id: u64, db_path: &str) -> Result<Vec<String>> {
+    let conn = open_db(db_path)?;
+    let memorial_id = merged_into(&conn, memorial_id)?.unwrap_or(memorial_id);
+
+    let mut stmt = conn
+        .prepare("SELECT tag FROM tags WHERE memorial_id = ?1 ORDER BY tag")
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    let tags = stmt
+        .query_map(params![memorial_id], |row| row.get(0))
+        .and_then(Iterator::collect)
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(tags)
+}
+
+/// List every tag in use with its number of memorials, alphabetically
+pub fn list_tags(db_path: &str) -> Result<Vec<TagCount>> {
+    let conn = open_db(db_path)?;
+    let mut stmt = conn
+        .prepare("SELECT tag, COUNT(*) FROM tags GROUP BY tag ORDER BY tag")
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    let tags = stmt
+        .query_map([], |row| {
+            Ok(TagCount {
+                tag: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .and_then(Iterator::collect)
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(tags)
+}
+
+/// Add a note to a memorial, stamped with its author and the current time
+pub fn add_note(memorial_id: u64, author: &str, body: &str, db_path: &str) -> Result<Note> {
+    let conn = open_db(db_path)?;
+    let memorial_id = stored_memorial_id(&conn, memorial_id)?;
+    let author = label("Author", author)?;
+    if body.trim().is_empty() {
+        return Err(Error::InvalidInput("Note text cannot be empty".to_string()));
+    }
+
+    conn.execute(
+        "INSERT INTO notes (memorial_id, author, body) VALUES (?1, ?2, ?3)",
+        params![memorial_id, author, body.trim()],
+    )
+    .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    let id = conn.last_insert_rowid();
+    conn.query_row(
+        &format!("SELECT {} FROM notes WHERE id = ?1", NOTE_COLUMNS),
+        params![id],
+        row_to_note,
+    )
+    .map_err(|e| Error::DatabaseError(e.to_string()))
+}
+
+/// Delete a note, returning `false` if no note had that ID
+pub fn remove_note(note_id: i64, db_path: &str) -> Result<bool> {
+    let conn = open_db(db_path)?;
+    let deleted = conn
+        .execute("DELETE FROM notes WHERE id = ?1", params![note_id])
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(deleted > 0)
+}
+
+/// List notes, oldest first, for one memorial or for every memorial
+pub fn list_notes(memorial_id: Option<u64>, db_path: &str) -> Result<Vec<Note>> {
+    let conn = open_db(db_path)?;
+    let memorial_id = match memorial_id {
+        Some(id) => Some(merged_into(&conn, id)?.unwrap_or(id)),
+        None => None,
+    };
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM notes WHERE ?1 IS NULL OR memorial_id = ?1
+             ORDER BY created_at, id",
+            NOTE_COLUMNS
+        ))
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    let notes = stmt
+        .query_map(params![memorial_id], row_to_note)
+        .and_then(Iterator::collect)
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(notes)
+}
+
+/// Add memorials to a project, creating the project if needed
+///
+/// A description replaces the project's current one. Returns the number of
+/// memorials newly added.
+pub fn add_to_project(
+    project: &str,
+    memorial_ids: &[u64],
+    description: Option<&str>,
+    db_path: &str,
+) -> Result<usize> {
+    let mut conn = open_db(db_path)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    tx.execute(
+        "INSERT OR IGNORE INTO projects (name) VALUES (?1)",
+        params![label("Project name", project)?],
+    )
+    .and_then(|_| {
+        tx.execute(
+            "UPDATE projects SET description = COALESCE(?2, description) WHERE name = ?1",
+            params![project.trim(), description],
+        )
+    })
+    .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    let project_id = project_id(&tx, project)?.unwrap_or_default();
+
+    let mut added = 0;
+    for &memorial_id in memorial_ids {
+        let memorial_id = stored_memorial_id(&tx, memorial_id)?;
+        added += tx
+            .execute(
+                "INSERT OR IGNORE INTO project_memorials (project_id, memorial_id)
+                 VALUES (?1, ?2)",
+                params![project_id, memorial_id],
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    }
+
+    tx.commit()
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(added)
+}
+
+/// Remove memorials from a project, returning how many were removed
+pub fn remove_from_project(project: &str, memorial_ids: &[u64], db_path: &str) -> Result<usize> {
+    let conn = open_db(db_path)?;
+    let Some(project_id) = project_id(&conn, project)? else {
+        return Ok(0);
+    };
+
+    let mut removed = 0;
+    for &memorial_id in memorial_ids {
+        let memorial_id = merged_into(&conn, memorial_id)?.unwrap_or(memorial_id);
+        removed += conn
+            .execute(
+                "DELETE FROM project_memorials WHERE project_id = ?1 AND memorial_id = ?2",
+                params![project_id, memorial_id],
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    }
+    Ok(removed)
+}
+
+/// Delete a project; its memorials are kept
+///
+/// Returns `false` if no project had that name.
+pub fn delete_project(project: &str, db_path: &str) -> Result<bool> {
+    let conn = open_db(db_path)?;
+    let deleted = conn
+        .execute(
+            "DELETE FROM projects WHERE name = ?1",
+            params![project.trim()],
+        )
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(deleted > 0)
+}
+
+/// List every project with its number of memorials, by name
+pub fn list_projects(db_path: &str) -> Result<Vec<Project>> {
+    let conn = open_db(db_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.id, p.name, p.description, p.created_at, COUNT(pm.memorial_id)
+             FROM projects p LEFT JOIN project_memorials pm ON pm.project_id = p.id
+             GROUP BY p.id ORDER BY p.name",
+        )
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    let projects = stmt
+        .query_map([], |row| {
+            Ok(Project {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                created_at: row.get(3)?,
+                memorial_count: row.get(4)?,
+            })
+        })
+        .and_then(Iterator::collect)
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(projects)
+}
+
+/// Columns selected when loading a note, in the order `row_to_note` expects
+const NOTE_COLUMNS: &str = "id, memorial_id, author, body, created_at";
+
+fn row_to_note(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+    Ok(Note {
+        id: row.get(0)?,
+        memorial_id: row.get(1)?,
+        author: row.get(2)?,
+        body: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+/// Resolve a memorial ID through any merge and check it is stored
+fn stored_memorial_id(conn: &Connection, id: u64) -> Result<u64> {
+    let id = merged_into(conn, id)?.unwrap_or(id);
+    let exists = conn
+        .query_row("SELECT 1 FROM memorials WHERE id = ?1", params![id], |_| {
+            Ok(())
+        })
+        .optional()
+        .map_err(|e| Error::DatabaseError(e.to_string()))?
+        .is_some();
+
+    if exists {
+        Ok(id)
+    } else {
+        Err(Error::InvalidInput(format!(
+            "Memorial {} is not stored; fetch it with --save first",
+            id
+        )))
+    }
+}
+
+fn project_id(conn: &Connection, project: &str) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT id FROM projects WHERE name = ?1",
+        params![project.trim()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| Error::DatabaseError(e.to_string()))
+}
+
+/// Trim a tag, author or project name, rejecting blank ones
+fn label<'a>(what: &str, text: &'a str) -> Result<&'a str> {
+    let text = text.trim();
+    if text.is_empty() {
+        Err(Error::InvalidInput(format!("{} cannot be empty", what)))
+    } else {
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::query_memorials;
+    use crate::{Memorial, MemorialQuery, MemorialStore};
+    use tempfile::tempdir;
+
+    fn store(db_path: &str, ids: &[u64]) {
+        let mut store = MemorialStore::open(db_path).unwrap();
+        for &id in ids {
+            store
+                .store(&Memorial {
+                    id: Some(id),
+                    name: format!("Person {}", id),
+                    ..Memorial::default()
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_tags_and_projects_filter_search() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path = db_path.to_str().unwrap();
+        store(db_path, &[1, 2, 3]);
+
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            add_tags(1, &tags(&["civil-war", "Ohio"]), db_path).unwrap(),
+            2
+        );
+        assert_eq!(add_tags(2, &tags(&["Civil-War"]), db_path).unwrap(), 1);
+        assert_eq!(add_tags(1, &tags(&["ohio"]), db_path).unwrap(), 0);
+        assert!(add_tags(99, &tags(&["x"]), db_path).is_err());
+
+        assert_eq!(memorial_tags(1, db_path).unwrap(), ["civil-war", "Ohio"]);
+        assert_eq!(list_tags(db_path).unwrap()[0].count, 2);
+
+        assert_eq!(
+            add_to_project("Smith line", &[2, 3], None, db_path).unwrap(),
+            2
+        );
+        assert_eq!(list_projects(db_path).unwrap()[0].memorial_count, 2);
+
+        let ids = |query: MemorialQuery| -> Vec<u64> {
+            query_memorials(&query.sort_by(crate::SortField::Id, false), db_path)
+                .unwrap()
+                .into_iter()
+                .filter_map(|m| m.id)
+                .collect()
+        };
+        assert_eq!(ids(MemorialQuery::new().tag("CIVIL-WAR")), [1, 2]);
+        assert_eq!(ids(MemorialQuery::new().tag("civil-war").tag("ohio")), [1]);
+        assert_eq!(ids(MemorialQuery::new().project("smith line")), [2, 3]);
+        assert_eq!(
+            ids(MemorialQuery::new().tag("civil-war").project("Smith line")),
+            [2]
+        );
+
+        assert_eq!(remove_tags(1, &tags(&["OHIO"]), db_path).unwrap(), 1);
+        assert_eq!(remove_from_project("Smith line", &[3], db_path).unwrap(), 1);
+        assert!(delete_project("Smith line", db_path).unwrap());
+        assert!(list_projects(db_path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_notes_follow_merges_and_deletes() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path = db_path.to_str().unwrap();
+        store(db_path, &[1, 2]);
+
+        let note = add_note(2, "jdoe", "Census 1880 lists a son", db_path).unwrap();
+        assert_eq!(note.author, "jdoe");
+        assert!(!note.created_at.is_empty());
+        add_tags(2, &["census".to_string()], db_path).unwrap();
+        assert!(add_note(1, "jdoe", "  ", db_path).is_err());
+
+        let mut memorials = MemorialStore::open(db_path).unwrap();
+        let kept = memorials.get(1).unwrap().unwrap();
+        memorials.merge(&kept, &[2]).unwrap();
+
+        // Annotations moved to the surviving memorial and follow the old ID
+        assert_eq!(
+            list_notes(Some(1), db_path).unwrap(),
+            [Note {
+                memorial_id: 1,
+                ..note.clone()
+            }]
+        );
+        assert_eq!(list_notes(Some(2), db_path).unwrap().len(), 1);
+        assert_eq!(memorial_tags(1, db_path).unwrap(), ["census"]);
+
+        assert!(memorials.delete(1).unwrap());
+        assert!(list_notes(None, db_path).unwrap().is_empty());
+        assert!(list_tags(db_path).unwrap().is_empty());
+        assert!(!remove_note(note.id, db_path).unwrap());
+    }
+}
@@ -0,0 +1,244 @@
+use crate::{
+    check_snapshot_backend, fetch_memorial, save_memorial, Config, Error, Memorial,
+    MemorialRepository, Result,
+};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::Serialize;
+use std::future::Future;
+use std::io::BufRead;
+use std::sync::{Arc, Mutex};
+
+/// Repository shared by every memorial of a run, opened when the first is saved
+pub(crate) type SharedRepository = Arc<Mutex<Option<Box<dyn MemorialRepository + Send>>>>;
+
+/// Order in which batch results are yielded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResultOrder {
+    /// Yield results in the same order as the inputs
+    #[default]
+    Input,
+    /// Yield results as soon as each one finishes
+    Completion,
+}
+
+/// Options controlling a batch run
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Maximum number of memorials fetched at the same time
+    pub concurrency: usize,
+    /// Order in which results are yielded
+    pub order: ResultOrder,
+    /// Store each successfully parsed memorial in the database
+    pub store_to_db: bool,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            order: ResultOrder::Input,
+            store_to_db: false,
+        }
+    }
+}
+
+/// Outcome of processing a single input in a batch
+#[derive(Debug)]
+pub struct BatchItem {
+    /// Zero-based position of the input in the batch
+    pub index: usize,
+    /// URL or memorial ID as given
+    pub input: String,
+    /// Parsed memorial or the error that stopped it
+    pub result: Result<Memorial>,
+}
+
+/// Running totals for a batch run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BatchSummary {
+    /// Number of inputs processed
+    pub total: usize,
+    /// Number of inputs that were fetched and parsed
+    pub succeeded: usize,
+    /// Number of inputs that failed
+    pub failed: usize,
+}
+
+impl BatchSummary {
+    /// Add the outcome of one item to the totals
+    pub fn record(&mut self, item: &BatchItem) {
+        self.total += 1;
+        if item.result.is_ok() {
+            self.succeeded += 1;
+        } else {
+            self.failed += 1;
+        }
+    }
+}
+
+/// Read memorial URLs or IDs from a reader, one per line
+///
+/// Blank lines and lines starting with `#` are skipped.
+pub fn read_inputs<R: BufRead>(reader: R) -> Result<Vec<String>> {
+    let mut inputs = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            inputs.push(line.to_string());
+        }
+    }
+    Ok(inputs)
+}
+
+/// Process many FindAGrave URLs or memorial IDs with bounded parallelism
+///
+/// Returns a stream that yields one [`BatchItem`] per input. At most
+/// `options.concurrency` memorials are fetched at the same time, and a
+/// failure on one input does not stop the others. Memorials are saved
+/// through a single repository opened for the whole batch.
+pub fn process_memorials<'a, I>(
+    inputs: I,
+    config: &'a Config,
+    options: &BatchOptions,
+) -> BoxStream<'a, BatchItem>
+where
+    I: IntoIterator<Item = String>,
+    I::IntoIter: Send + 'a,
+{
+    process_memorials_into(inputs, config, options, SharedRepository::default())
+}
+
+/// Process a batch, saving memorials through `repository`
+pub(crate) fn process_memorials_into<'a, I>(
+    inputs: I,
+    config: &'a Config,
+    options: &BatchOptions,
+    repository: SharedRepository,
+) -> BoxStream<'a, BatchItem>
+where
+    I: IntoIterator<Item = String>,
+    I::IntoIter: Send + 'a,
+{
+    let store_to_db = options.store_to_db;
+    run_batch(inputs, options, move |input| {
+        fetch_and_save(input, config, store_to_db, Arc::clone(&repository))
+    })
+}
+
+/// Fetch and parse one input, saving it through `repository` if asked to
+///
+/// The repository is opened from `config` on first use.
+pub(crate) async fn fetch_and_save(
+    input: String,
+    config: &Config,
+    store_to_db: bool,
+    repository: SharedRepository,
+) -> Result<Memorial> {
+    check_snapshot_backend(config, store_to_db)?;
+    let (memorial, url, html) = fetch_memorial(&input, config).await?;
+    if store_to_db {
+        let mut repository = repository.lock().map_err(|e| Error::Other(e.to_string()))?;
+        if repository.is_none() {
+            *repository = Some(config.open_repository()?);
+        }
+        if let Some(repository) = repository.as_mut() {
+            save_memorial(&memorial, &url, &html, config, repository.as_mut())?;
+        }
+    }
+    Ok(memorial)
+}
+
+/// Drive `process` over every input according to the batch options
+pub(crate) fn run_batch<'a, I, F, Fut>(
+    inputs: I,
+    options: &BatchOptions,
+    process: F,
+) -> BoxStream<'a, BatchItem>
+where
+    I: IntoIterator<Item = String>,
+    I::IntoIter: Send + 'a,
+    F: Fn(String) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<Memorial>> + Send + 'a,
+{
+    let tasks = stream::iter(inputs.into_iter().enumerate()).map(move |(index, input)| {
+        let future = process(input.clone());
+        async move {
+            BatchItem {
+                index,
+                input,
+                result: future.await,
+            }
+        }
+    });
+
+    let concurrency = options.concurrency.max(1);
+    match options.order {
+        ResultOrder::Input => tasks.buffered(concurrency).boxed(),
+        ResultOrder::Completion => tasks.buffer_unordered(concurrency).boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use std::time::Duration;
+
+    async fn fake_process(input: String) -> Result<Memorial> {
+        // Later inputs finish first so ordering differences are visible
+        let delay: u64 = input
+            .parse()
+            .map_err(|_| Error::InvalidInput(input.clone()))?;
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        let mut memorial = Memorial::new();
+        memorial.name = format!("Person {}", input);
+        Ok(memorial)
+    }
+
+    #[test]
+    fn test_read_inputs_skips_blanks_and_comments() {
+        let data = "123\n\n# a comment\n  https://www.findagrave.com/memorial/456  \n";
+        let inputs = read_inputs(data.as_bytes()).unwrap();
+        assert_eq!(
+            inputs,
+            vec!["123", "https://www.findagrave.com/memorial/456"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_input_order() {
+        let inputs = vec!["30".to_string(), "bad".to_string(), "1".to_string()];
+        let options = BatchOptions::default();
+
+        let items: Vec<BatchItem> = run_batch(inputs, &options, fake_process).collect().await;
+
+        let indexes: Vec<usize> = items.iter().map(|item| item.index).collect();
+        assert_eq!(indexes, vec![0, 1, 2]);
+
+        let mut summary = BatchSummary::default();
+        items.iter().for_each(|item| summary.record(item));
+        assert_eq!(
+            summary,
+            BatchSummary {
+                total: 3,
+                succeeded: 2,
+                failed: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_completion_order() {
+        let inputs = vec!["30".to_string(), "1".to_string()];
+        let options = BatchOptions {
+            order: ResultOrder::Completion,
+            ..BatchOptions::default()
+        };
+
+        let items: Vec<BatchItem> = run_batch(inputs, &options, fake_process).collect().await;
+
+        let indexes: Vec<usize> = items.iter().map(|item| item.index).collect();
+        assert_eq!(indexes, vec![1, 0]);
+    }
+}
@@ -0,0 +1,339 @@
+use crate::db::MemorialStore;
+use crate::{Error, Memorial, MemorialQuery, Result, SortField};
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::str::FromStr;
+
+/// Site credited as the publisher of every memorial page
+const PUBLISHER: &str = "Find a Grave";
+
+/// Reference manager formats for memorial citations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BibFormat {
+    /// A classic BibTeX `@misc` entry, with the address in `howpublished`
+    BibTex,
+    /// A BibLaTeX `@online` entry with `url` and `urldate`
+    BibLatex,
+    /// An RIS record of type `ELEC`, as read by Zotero, EndNote and Mendeley
+    Ris,
+    /// A CSL-JSON array of `webpage` items, as used by Zotero and Pandoc
+    CslJson,
+}
+
+impl FromStr for BibFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bibtex" => Ok(BibFormat::BibTex),
+            "biblatex" => Ok(BibFormat::BibLatex),
+            "ris" => Ok(BibFormat::Ris),
+            "csl-json" => Ok(BibFormat::CslJson),
+            other => Err(Error::InvalidInput(format!(
+                "Unknown bibliography format: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A memorial to cite and the time its page was fetched (`YYYY-MM-DD HH:MM:SS`)
+pub type BibEntry = (Memorial, Option<String>);
+
+/// Render memorial citations in a reference manager format
+///
+/// Each entry carries the page title, its address, the access date and the
+/// memorial's maintainer (or its creator, if no maintainer is known) as
+/// editor. Entries are separated by blank lines; CSL-JSON entries are items
+/// of a single JSON array.
+pub fn to_bibliography(entries: &[BibEntry], format: BibFormat) -> Result<String> {
+    if format == BibFormat::CslJson {
+        let items: Vec<Value> = entries
+            .iter()
+            .map(|(memorial, accessed)| to_csl_json(memorial, accessed.as_deref()))
+            .collect();
+        return Ok(serde_json::to_string_pretty(&items)? + "\n");
+    }
+
+    let records: Vec<String> = entries
+        .iter()
+        .map(|(memorial, accessed)| {
+            let accessed = accessed.as_deref();
+            match format {
+                BibFormat::BibTex => to_bibtex(memorial, accessed),
+                BibFormat::BibLatex => to_biblatex(memorial, accessed),
+                _ => to_ris(memorial, accessed),
+            }
+        })
+        .collect();
+    Ok(records.join("\n"))
+}
+
+/// Write citations of every matching memorial in a reference manager format
+///
+/// Memorials are written in name order, and each access date is the time its
+/// newest stored revision was fetched. Returns the number of memorials written.
+pub fn export_bibliography<W: Write>(
+    store: &MemorialStore,
+    query: &MemorialQuery,
+    format: BibFormat,
+    out: &mut W,
+) -> Result<usize> {
+    let mut entries = Vec::new();
+    for memorial in store.query_all(&query.clone().sort_by(SortField::Name, false))? {
+        let accessed = match memorial.id {
+            Some(id) => store.last_fetched(id)?,
+            None => None,
+        };
+        entries.push((memorial, accessed));
+    }
+
+    out.write_all(to_bibliography(&entries, format)?.as_bytes())?;
+    Ok(entries.len())
+}
+
+/// BibTeX `@misc` entry for a memorial page
+pub fn to_bibtex(memorial: &Memorial, accessed: Option<&str>) -> String {
+    let mut fields = vec![("title", braced(&title(memorial)))];
+    if let Some(editor) = editor(memorial) {
+        fields.push(("editor", braced(editor)));
+    }
+    fields.push(("publisher", braced(PUBLISHER)));
+    if let Some(url) = memorial.url() {
+        fields.push(("howpublished", format!("{{\\url{{{}}}}}", url)));
+    }
+    if let Some(date) = accessed.and_then(iso_date) {
+        fields.push(("note", format!("{{Accessed {}}}", date)));
+    }
+    bibtex_entry("misc", memorial, &fields)
+}
+
+/// BibLaTeX `@online` entry for a memorial page
+pub fn to_biblatex(memorial: &Memorial, accessed: Option<&str>) -> String {
+    let mut fields = vec![("title", braced(&title(memorial)))];
+    if let Some(editor) = editor(memorial) {
+        fields.push(("editor", braced(editor)));
+    }
+    fields.push(("organization", braced(PUBLISHER)));
+    if let Some(url) = memorial.url() {
+        fields.push(("url", format!("{{{}}}", url)));
+    }
+    if let Some(date) = accessed.and_then(iso_date) {
+        fields.push(("urldate", format!("{{{}}}", date)));
+    }
+    bibtex_entry("online", memorial, &fields)
+}
+
+/// RIS record for a memorial page
+pub fn to_ris(memorial: &Memorial, accessed: Option<&str>) -> String {
+    let mut lines = vec![("TY", "ELEC".to_string()), ("TI", title(memorial))];
+    if let Some(editor) = editor(memorial) {
+        lines.push(("ED", editor.to_string()));
+    }
+    lines.push(("PB", PUBLISHER.to_string()));
+    if let Some(url) = memorial.url() {
+        lines.push(("UR", url));
+    }
+    if let Some(date) = accessed.and_then(iso_date) {
+        lines.push(("Y2", date.replace('-', "/")));
+    }
+    if let Some(id) = memorial.id {
+        lines.push(("ID", format!("findagrave{}", id)));
+    }
+    lines.push(("ER", String::new()));
+
+    lines
+        .into_iter()
+        .map(|(tag, value)| match value.as_str() {
+            "" => format!("{}  -\n", tag),
+            value => format!("{}  - {}\n", tag, value),
+        })
+        .collect()
+}
+
+/// CSL-JSON `webpage` item for a memorial page
+pub fn to_csl_json(memorial: &Memorial, accessed: Option<&str>) -> Value {
+    let mut item = Map::new();
+    item.insert("id".to_string(), Value::from(key(memorial)));
+    item.insert("type".to_string(), Value::from("webpage"));
+    item.insert("title".to_string(), Value::from(title(memorial)));
+    item.insert("container-title".to_string(), Value::from(PUBLISHER));
+    item.insert("publisher".to_string(), Value::from(PUBLISHER));
+    if let Some(editor) = editor(memorial) {
+        // Contributor names are Find a Grave user names, not family/given pairs
+        item.insert("editor".to_string(), json!([{ "literal": editor }]));
+    }
+    if let Some(url) = memorial.url() {
+        item.insert("URL".to_string(), Value::from(url));
+    }
+    let parts = accessed.and_then(iso_date).and_then(|date| {
+        date.split('-')
+            .map(|part| part.parse::<u32>().ok())
+            .collect::<Option<Vec<_>>>()
+    });
+    if let Some(parts) = parts {
+        item.insert("accessed".to_string(), json!({ "date-parts": [parts] }));
+    }
+    Value::Object(item)
+}
+
+/// Page title as Find a Grave shows it, such as `John Smith (1840-1901) - Find a Grave Memorial`
+fn title(memorial: &Memorial) -> String {
+    let years = match (memorial.birth_year(), memorial.death_year()) {
+        (None, None) => String::new(),
+        (birth, death) => format!(
+            " ({}-{})",
+            birth.map(|year| year.to_string()).unwrap_or_default(),
+            death.map(|year| year.to_string()).unwrap_or_default()
+        ),
+    };
+    format!("{}{} - Find a Grave Memorial", memorial.name, years)
+}
+
+/// Contributor credited for the page: its maintainer, or else its creator
+fn editor(memorial: &Memorial) -> Option<&str> {
+    memorial
+        .maintained_by
+        .as_deref()
+        .or(memorial.created_by.as_deref())
+}
+
+/// Citation key, such as `findagrave12345`
+fn key(memorial: &Memorial) -> String {
+    match memorial.id {
+        Some(id) => format!("findagrave{}", id),
+        None => "findagrave".to_string(),
+    }
+}
+
+/// `YYYY-MM-DD` part of a fetch time
+fn iso_date(accessed: &str) -> Option<String> {
+    let date = accessed.get(..10)?;
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(date.to_string())
+}
+
+fn bibtex_entry(kind: &str, memorial: &Memorial, fields: &[(&str, String)]) -> String {
+    let mut entry = format!("@{}{{{},\n", kind, key(memorial));
+    for (name, value) in fields {
+        entry.push_str(&format!("  {} = {},\n", name, value));
+    }
+    entry.push_str("}\n");
+    entry
+}
+
+/// A BibTeX value in double braces, so it is kept verbatim rather than split into names
+fn braced(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            c => escaped.push(c),
+        }
+    }
+    format!("{{{{{}}}}}", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memorial() -> Memorial {
+        Memorial {
+            id: Some(1234),
+            name: "John Smith".to_string(),
+            birth_date: Some("3 Mar 1840".to_string()),
+            death_date: Some("1901".to_string()),
+            created_by: Some("Founder".to_string()),
+            maintained_by: Some("Smith & Sons_1".to_string()),
+            ..Memorial::default()
+        }
+    }
+
+    const ACCESSED: Option<&str> = Some("2026-10-19 08:30:00");
+
+    #[test]
+    fn test_bibtex_and_biblatex() {
+        assert_eq!(
+            to_bibtex(&memorial(), ACCESSED),
+            "@misc{findagrave1234,\n\
+             \x20 title = {{John Smith (1840-1901) - Find a Grave Memorial}},\n\
+             \x20 editor = {{Smith \\& Sons\\_1}},\n\
+             \x20 publisher = {{Find a Grave}},\n\
+             \x20 howpublished = {\\url{https://www.findagrave.com/memorial/1234}},\n\
+             \x20 note = {Accessed 2026-10-19},\n\
+             }\n"
+        );
+
+        let biblatex = to_biblatex(&memorial(), ACCESSED);
+        assert!(biblatex.starts_with("@online{findagrave1234,\n"));
+        assert!(biblatex.contains("  url = {https://www.findagrave.com/memorial/1234},\n"));
+        assert!(biblatex.contains("  urldate = {2026-10-19},\n"));
+    }
+
+    #[test]
+    fn test_ris_and_csl_json() {
+        let unmaintained = Memorial {
+            maintained_by: None,
+            ..memorial()
+        };
+        assert_eq!(
+            to_ris(&unmaintained, ACCESSED),
+            "TY  - ELEC\n\
+             TI  - John Smith (1840-1901) - Find a Grave Memorial\n\
+             ED  - Founder\n\
+             PB  - Find a Grave\n\
+             UR  - https://www.findagrave.com/memorial/1234\n\
+             Y2  - 2026/10/19\n\
+             ID  - findagrave1234\n\
+             ER  -\n"
+        );
+
+        let csl: Value = serde_json::from_str(
+            &to_bibliography(
+                &[(memorial(), ACCESSED.map(String::from))],
+                BibFormat::CslJson,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(csl[0]["type"], "webpage");
+        assert_eq!(csl[0]["URL"], "https://www.findagrave.com/memorial/1234");
+        assert_eq!(csl[0]["editor"], json!([{ "literal": "Smith & Sons_1" }]));
+        assert_eq!(
+            csl[0]["accessed"],
+            json!({ "date-parts": [[2026, 10, 19]] })
+        );
+    }
+
+    #[test]
+    fn test_export_bibliography_by_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path = db_path.to_str().unwrap();
+        let mut store = MemorialStore::open(db_path).unwrap();
+        store.store(&memorial()).unwrap();
+        store
+            .store(&Memorial {
+                id: Some(99),
+                name: "Ann Brown".to_string(),
+                ..Memorial::default()
+            })
+            .unwrap();
+        crate::annotations::add_to_project("Smith line", &[1234], None, db_path).unwrap();
+
+        let mut out = Vec::new();
+        let query = MemorialQuery::new().project("Smith line");
+        let count = export_bibliography(&store, &query, BibFormat::Ris, &mut out).unwrap();
+        let ris = String::from_utf8(out).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(ris.matches("TY  - ELEC").count(), 1);
+        assert!(ris.contains("Y2  - "));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{Error, Result};
+use chrono::NaiveDate;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// Words that qualify a date, such as `c. 1820`, `abt 1820` or `bef 1900`
+const QUALIFIERS: &[(&str, DateQualifier)] = &[
+    ("c", DateQualifier::About),
+    ("ca", DateQualifier::About),
+    ("circa", DateQualifier::About),
+    ("abt", DateQualifier::About),
+    ("about", DateQualifier::About),
+    ("est", DateQualifier::About),
+    ("bef", DateQualifier::Before),
+    ("before", DateQualifier::Before),
+    ("aft", DateQualifier::After),
+    ("after", DateQualifier::After),
+];
+
+const MONTHS: &[&str] = &[
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// How a date is qualified on the page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateQualifier {
+    /// No qualifier
+    #[default]
+    Exact,
+    /// Approximate, such as `c. 1820` or `abt 1820`
+    About,
+    /// Some time before the date, such as `bef 1900`
+    Before,
+    /// Some time after the date, such as `aft 1900`
+    After,
+}
+
+/// A date that may be missing its day or month, as found on memorial pages
+///
+/// Accepts forms such as `12 Apr 1861`, `April 12, 1861`, `Apr 1861`, `1861`
+/// and `1861-04-12`, optionally preceded by a qualifier such as `c.`, `abt`
+/// or `bef`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialDate {
+    /// Year
+    pub year: i32,
+    /// Month from 1 to 12, if known
+    pub month: Option<u32>,
+    /// Day of the month, if known
+    pub day: Option<u32>,
+    /// Qualifier written before the date
+    pub qualifier: DateQualifier,
+}
+
+impl PartialDate {
+    /// Compare two dates as far as both are known
+    ///
+    /// Returns `None` when the dates share a year (or month) and one of them
+    /// is too vague to tell which came first.
+    pub fn compare(&self, other: &PartialDate) -> Option<Ordering> {
+        match self.year.cmp(&other.year) {
+            Ordering::Equal => {}
+            ordering => return Some(ordering),
+        }
+        match self.month?.cmp(&other.month?) {
+            Ordering::Equal => {}
+            ordering => return Some(ordering),
+        }
+        Some(self.day?.cmp(&other.day?))
+    }
+
+    /// Whole years from this date to a later one
+    ///
+    /// When the month or day is unknown the birthday is assumed to have
+    /// passed, giving the largest possible age.
+    pub fn years_until(&self, later: &PartialDate) -> i32 {
+        let mut years = later.year - self.year;
+        let birthday_pending = match (self.month, later.month) {
+            (Some(a), Some(b)) if b < a => true,
+            (Some(a), Some(b)) if b == a => {
+                matches!((self.day, later.day), (Some(x), Some(y)) if y < x)
+            }
+            _ => false,
+        };
+        if birthday_pending {
+            years -= 1;
+        }
+        years
+    }
+}
+
+impl FromStr for PartialDate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::ParseError(format!("Unrecognized date: {}", s.trim()));
+
+        let lower = s.trim().to_lowercase();
+        let mut words: Vec<&str> = lower
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        let qualifier = words
+            .first()
+            .and_then(|word| {
+                let word = word.trim_end_matches('.');
+                QUALIFIERS.iter().find(|(name, _)| *name == word)
+            })
+            .map(|(_, qualifier)| *qualifier);
+        if qualifier.is_some() {
+            words.remove(0);
+        }
+
+        let (mut year, mut month, mut day) = (None, None, None);
+        match words.as_slice() {
+            // ISO dates such as 1861-04-12 or 1861-04
+            [iso] if iso.contains('-') => {
+                let parts = iso
+                    .split('-')
+                    .map(|part| part.parse::<u32>().map_err(|_| invalid()))
+                    .collect::<Result<Vec<_>>>()?;
+                match parts.as_slice() {
+                    [y, rest @ ..] if rest.len() <= 2 && *y >= 1000 => {
+                        year = Some(*y as i32);
+                        month = rest.first().copied();
+                        day = rest.get(1).copied();
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => {
+                for word in &words {
+                    let word = word.trim_end_matches('.');
+                    if let Some(m) = month_number(word) {
+                        if month.replace(m).is_some() {
+                            return Err(invalid());
+                        }
+                    } else if word.len() == 4 && word.chars().all(|c| c.is_ascii_digit()) {
+                        if year.replace(word.parse().map_err(|_| invalid())?).is_some() {
+                            return Err(invalid());
+                        }
+                    } else if (1..=2).contains(&word.len())
+                        && word.chars().all(|c| c.is_ascii_digit())
+                    {
+                        if day.replace(word.parse().map_err(|_| invalid())?).is_some() {
+                            return Err(invalid());
+                        }
+                    } else {
+                        return Err(invalid());
+                    }
+                }
+            }
+        }
+
+        let year: i32 = year.ok_or_else(invalid)?;
+        match (month, day) {
+            (None, Some(_)) => return Err(invalid()),
+            (Some(m), None) if !(1..=12).contains(&m) => return Err(invalid()),
+            (Some(m), Some(d)) if NaiveDate::from_ymd_opt(year, m, d).is_none() => {
+                return Err(invalid())
+            }
+            _ => {}
+        }
+
+        Ok(PartialDate {
+            year,
+            month,
+            day,
+            qualifier: qualifier.unwrap_or_default(),
+        })
+    }
+}
+
+/// Month number for a full or abbreviated English month name
+fn month_number(word: &str) -> Option<u32> {
+    if word.len() < 3 || !word.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let word = if word == "sept" { "sep" } else { word };
+    MONTHS
+        .iter()
+        .position(|month| month.starts_with(word))
+        .map(|i| i as u32 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: Option<u32>, day: Option<u32>) -> PartialDate {
+        PartialDate {
+            year,
+            month,
+            day,
+            qualifier: DateQualifier::Exact,
+        }
+    }
+
+    #[test]
+    fn test_parse_partial_dates() {
+        let parse = |s: &str| s.parse::<PartialDate>().ok();
+        assert_eq!(parse("12 Apr 1861"), Some(date(1861, Some(4), Some(12))));
+        assert_eq!(parse("April 12, 1861"), Some(date(1861, Some(4), Some(12))));
+        assert_eq!(parse("Sept. 1861"), Some(date(1861, Some(9), None)));
+        assert_eq!(parse("1861-04-12"), Some(date(1861, Some(4), Some(12))));
+        assert_eq!(parse("1861"), Some(date(1861, None, None)));
+        assert_eq!(parse("c. 1820").unwrap().qualifier, DateQualifier::About);
+        let before = parse("bef 12 Apr 1861").unwrap();
+        assert_eq!(before.qualifier, DateQualifier::Before);
+        assert_eq!((before.year, before.day), (1861, Some(12)));
+        assert_eq!(parse("After 1900").unwrap().qualifier, DateQualifier::After);
+
+        assert_eq!(parse("31 Feb 1861"), None);
+        assert_eq!(parse("12 1861"), None);
+        assert_eq!(parse("1861-13"), None);
+        assert_eq!(parse("sometime in spring"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_compare_and_age() {
+        let birth = date(1820, Some(6), Some(15));
+        let death = date(1890, Some(3), None);
+        assert_eq!(birth.compare(&death), Some(Ordering::Less));
+        assert_eq!(birth.years_until(&death), 69);
+        assert_eq!(date(1890, None, None).compare(&death), None);
+        assert_eq!(date(1820, None, None).years_until(&death), 70);
+    }
+}
@@ -1,54 +1,708 @@
-use crate::{Error, Memorial, Result};
-use rusqlite::{params, Connection};
+use crate::migrations;
+use crate::phonetic::{name_keys, score_name, NameKey};
+use crate::snapshot::{compress, content_hash, decompress};
+use crate::{
+    Coordinates, Error, FieldChange, Memorial, MemorialQuery, NameMatch, PersonName, Result,
+    Snapshot,
+};
+use rusqlite::types::Type;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::Serialize;
 use std::path::Path;
+use std::time::Duration;
 
-/// Store a memorial in the SQLite database
-pub fn store_in_db(memorial: &Memorial, db_path: &str) -> Result<()> {
-    // Open or create the database file
-    let mut conn = Connection::open(db_path).map_err(|e| Error::DatabaseError(e.to_string()))?;
+pub use crate::migrations::{PendingMigration, SchemaStatus};
+
+/// Number of prepared statements kept per connection
+const STATEMENT_CACHE_CAPACITY: usize = 32;
+
+/// Number of memorials read at a time by `MemorialStore::query_all`
+const PAGE_SIZE: usize = 1000;
+
+/// A handle on the SQLite memorial database that owns one open connection
+///
+/// Opening the store applies pending schema migrations once. Every call
+/// afterwards reuses the same connection and its prepared-statement cache,
+/// which makes it the right choice for batch work; the free functions in this
+/// module open a store per call.
+pub struct MemorialStore {
+    conn: Connection,
+}
+
+impl MemorialStore {
+    /// Open or create the database file and bring its schema up to date
+    pub fn open(db_path: &str) -> Result<Self> {
+        Ok(Self {
+            conn: open_db(db_path)?,
+        })
+    }
+
+    /// Create a store backed by a private in-memory database
+    pub fn open_in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory().map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(Self {
+            conn: prepare_connection(conn)?,
+        })
+    }
+
+    /// Insert or update a memorial and return its ID
+    ///
+    /// Memorials without an ID are assigned one by the database. A memorial
+    /// that was merged away as a duplicate is not re-created; the ID of the
+    /// memorial it was merged into is returned instead.
+    pub fn store(&mut self, memorial: &Memorial) -> Result<u64> {
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let id = upsert_memorial(&tx, memorial, None)?;
+        tx.commit()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(id)
+    }
+
+    /// Store a memorial read from a page fetched at `fetched_at`
+    ///
+    /// Like [`store`](Self::store), but the memorial is dated by the fetch
+    /// (`YYYY-MM-DD HH:MM:SS`) rather than now, so re-reading an old page does
+    /// not make it look freshly fetched.
+    pub fn store_fetched(&mut self, memorial: &Memorial, fetched_at: &str) -> Result<u64> {
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let id = upsert_memorial(&tx, memorial, Some(fetched_at))?;
+        tx.commit()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(id)
+    }
+
+    /// Insert or update many memorials in a single transaction
+    ///
+    /// Either every memorial is stored or, on error, none are. Returns the
+    /// memorial IDs in input order.
+    pub fn store_many(&mut self, memorials: &[Memorial]) -> Result<Vec<u64>> {
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let ids = memorials
+            .iter()
+            .map(|memorial| upsert_memorial(&tx, memorial, None))
+            .collect::<Result<Vec<_>>>()?;
+        tx.commit()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(ids)
+    }
+
+    /// Replace duplicate memorials with a single merged memorial
+    ///
+    /// The merged memorial is stored and the duplicates deleted in one
+    /// transaction. The duplicate IDs are recorded so that fetching them again
+    /// later does not re-create them. Their revision history is kept, and
+    /// their tags, notes and project memberships move to the merged memorial.
+    pub fn merge(&mut self, merged: &Memorial, merged_away: &[u64]) -> Result<u64> {
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let id = upsert_memorial(&tx, merged, None)?;
+
+        for &duplicate in merged_away.iter().filter(|&&duplicate| duplicate != id) {
+            let pair = params![duplicate, id];
+            // Move annotations before the delete cascades them away
+            tx.execute(
+                "UPDATE OR IGNORE tags SET memorial_id = ?2 WHERE memorial_id = ?1",
+                pair,
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+            tx.execute(
+                "UPDATE OR IGNORE project_memorials SET memorial_id = ?2 WHERE memorial_id = ?1",
+                pair,
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+            tx.execute(
+                "UPDATE notes SET memorial_id = ?2 WHERE memorial_id = ?1",
+                pair,
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+            tx.execute("DELETE FROM memorials WHERE id = ?1", params![duplicate])
+                .map_err(|e| Error::DatabaseError(e.to_string()))?;
+            tx.execute(
+                "DELETE FROM name_keys WHERE memorial_id = ?1",
+                params![duplicate],
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+            tx.execute(
+                "INSERT OR REPLACE INTO merged_memorials (merged_id, into_id) VALUES (?1, ?2)",
+                pair,
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+            // Memorials merged into this duplicate earlier now point at the survivor
+            tx.execute(
+                "UPDATE merged_memorials SET into_id = ?2 WHERE into_id = ?1",
+                pair,
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        }
+
+        tx.commit()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(id)
+    }
+
+    /// ID of the memorial a duplicate was merged into, if it was merged away
+    pub fn merged_into(&self, id: u64) -> Result<Option<u64>> {
+        merged_into(&self.conn, id)
+    }
+
+    /// Retrieve a memorial by ID
+    pub fn get(&self, id: u64) -> Result<Option<Memorial>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT {} FROM memorials WHERE id = ?1",
+                MEMORIAL_COLUMNS
+            ))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        stmt.query_row(params![id], row_to_memorial)
+            .optional()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// Search memorials by name, restricted by flag filters
+    pub fn search(&self, name: &str, filters: &SearchFilters) -> Result<Vec<Memorial>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT {} FROM memorials
+             WHERE name LIKE ?1
+             AND (?2 IS NULL OR is_veteran = ?2)
+             AND (?3 IS NULL OR is_famous = ?3)
+             AND (?4 IS NULL OR is_cenotaph = ?4)
+             AND (?5 IS NULL OR not_buried_here = ?5)
+             ORDER BY name LIMIT 100",
+                MEMORIAL_COLUMNS
+            ))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        let search_pattern = format!("%{}%", name);
+        let rows = stmt
+            .query_map(
+                params![
+                    search_pattern,
+                    filters.veteran,
+                    filters.famous,
+                    filters.cenotaph,
+                    filters.not_buried_here
+                ],
+                row_to_memorial,
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
 
-    // Initialize the database schema
-    initialize_db(&conn)?;
+    /// Run a structured query with filters, sort order and paging
+    pub fn query(&self, query: &MemorialQuery) -> Result<Vec<Memorial>> {
+        let (clauses, values) = query.to_sql();
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT {} FROM memorials{}",
+                MEMORIAL_COLUMNS, clauses
+            ))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
 
-    // Start a transaction for inserting the data
-    let tx = conn
-        .transaction()
+        let rows = stmt
+            .query_map(params_from_iter(values), row_to_memorial)
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// Search names that sound alike or are spelled similarly
+    ///
+    /// Hits are ranked by how closely the stored name is spelled to the query.
+    pub fn name_search(&self, query: &NameQuery) -> Result<Vec<NameHit>> {
+        let query_keys = name_keys(&PersonName::parse(&query.name));
+        if query_keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Phonetic matches can use the indexed codes to narrow the candidates;
+        // edit distance has to be checked against every stored name
+        let mut codes = Vec::new();
+        let sql = match query.mode {
+            NameMatch::Phonetic => {
+                for key in &query_keys {
+                    codes.push(key.soundex.clone());
+                    codes.extend(
+                        [&key.metaphone, &key.metaphone_alt]
+                            .into_iter()
+                            .filter(|code| !code.is_empty())
+                            .cloned(),
+                    );
+                }
+                let placeholders = (1..=codes.len())
+                    .map(|i| format!("?{}", i))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "SELECT memorial_id, part, normalized, soundex, metaphone, metaphone_alt
+                     FROM name_keys WHERE memorial_id IN (
+                         SELECT memorial_id FROM name_keys
+                         WHERE soundex IN ({0}) OR metaphone IN ({0}) OR metaphone_alt IN ({0})
+                     ) ORDER BY memorial_id, position",
+                    placeholders
+                )
+            }
+            NameMatch::Fuzzy(_) => {
+                "SELECT memorial_id, part, normalized, soundex, metaphone, metaphone_alt
+                 FROM name_keys ORDER BY memorial_id, position"
+                    .to_string()
+            }
+        };
+
+        let mut stmt = self
+            .conn
+            .prepare_cached(&sql)
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let rows = stmt
+            .query_map(params_from_iter(&codes), |row| {
+                let part: String = row.get(1)?;
+                let part = part.parse().map_err(|e: Error| {
+                    rusqlite::Error::FromSqlConversionFailure(1, Type::Text, Box::new(e))
+                })?;
+                Ok((
+                    row.get::<_, u64>(0)?,
+                    NameKey {
+                        part,
+                        normalized: row.get(2)?,
+                        soundex: row.get(3)?,
+                        metaphone: row.get(4)?,
+                        metaphone_alt: row.get(5)?,
+                    },
+                ))
+            })
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        let mut candidates: Vec<(u64, Vec<NameKey>)> = Vec::new();
+        for row in rows {
+            let (memorial_id, key) = row.map_err(|e| Error::DatabaseError(e.to_string()))?;
+            match candidates.last_mut() {
+                Some((id, keys)) if *id == memorial_id => keys.push(key),
+                _ => candidates.push((memorial_id, vec![key])),
+            }
+        }
+
+        let mut scored: Vec<(u64, f64)> = candidates
+            .iter()
+            .filter_map(|(id, keys)| {
+                score_name(&query_keys, keys, query.mode).map(|score| (*id, score))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        scored.truncate(query.limit);
+
+        let mut hits = Vec::with_capacity(scored.len());
+        for (id, score) in scored {
+            if let Some(memorial) = self.get(id)? {
+                hits.push(NameHit { memorial, score });
+            }
+        }
+        Ok(hits)
+    }
+
+    /// Full-text search over names, places, plot details and biographies
+    ///
+    /// Results are ranked by relevance, with name matches weighted highest.
+    pub fn full_text_search(&self, query: &FullTextQuery) -> Result<Vec<FullTextHit>> {
+        let expression = match full_text_expression(&query.terms, query.prefix) {
+            Some(expression) => expression,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT {}, hits.score, hits.snippet FROM memorials
+             JOIN (
+                 SELECT rowid AS hit_id,
+                     bm25(memorials_fts, 10.0, 4.0, 2.0, 1.0) AS score,
+                     snippet(memorials_fts, -1, '[', ']', '...', 12) AS snippet
+                 FROM memorials_fts WHERE memorials_fts MATCH ?1
+             ) AS hits ON memorials.id = hits.hit_id
+             ORDER BY hits.score, name LIMIT ?2 OFFSET ?3",
+                MEMORIAL_COLUMNS
+            ))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(
+                params![expression, query.limit as i64, query.offset as i64],
+                |row| {
+                    Ok(FullTextHit {
+                        memorial: row_to_memorial(row)?,
+                        // bm25 scores are negative, with the best match lowest
+                        score: -row.get::<_, f64>(MEMORIAL_COLUMN_COUNT)?,
+                        snippet: row.get(MEMORIAL_COLUMN_COUNT + 1)?,
+                    })
+                },
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// Run a structured query and return every match, whatever its limit and offset
+    ///
+    /// Matches are read a page at a time in the query's sort order.
+    pub fn query_all(&self, query: &MemorialQuery) -> Result<Vec<Memorial>> {
+        let mut memorials = Vec::new();
+        loop {
+            let page = self.query(&query.clone().limit(PAGE_SIZE).offset(memorials.len()))?;
+            let done = page.len() < PAGE_SIZE;
+            memorials.extend(page);
+            if done {
+                return Ok(memorials);
+            }
+        }
+    }
+
+    /// List stored memorials ordered by ID
+    pub fn list(&self, limit: usize, offset: usize) -> Result<Vec<Memorial>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT {} FROM memorials ORDER BY id LIMIT ?1 OFFSET ?2",
+                MEMORIAL_COLUMNS
+            ))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(params![limit as i64, offset as i64], row_to_memorial)
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// Count stored memorials
+    pub fn count(&self) -> Result<usize> {
+        self.conn
+            .prepare_cached("SELECT COUNT(*) FROM memorials")
+            .and_then(|mut stmt| stmt.query_row([], |row| row.get::<_, i64>(0)))
+            .map(|count| count as usize)
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// Delete a memorial with its revision history, snapshots, tags, notes and
+    /// project memberships
+    ///
+    /// Returns `false` if no memorial had that ID.
+    pub fn delete(&mut self, id: u64) -> Result<bool> {
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        tx.execute(
+            "DELETE FROM memorial_revisions WHERE memorial_id = ?1",
+            params![id],
+        )
         .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        tx.execute("DELETE FROM name_keys WHERE memorial_id = ?1", params![id])
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        tx.execute("DELETE FROM snapshots WHERE memorial_id = ?1", params![id])
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let deleted = tx
+            .execute("DELETE FROM memorials WHERE id = ?1", params![id])
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        tx.commit()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(deleted > 0)
+    }
 
-    // Insert the memorial data
-    tx.execute(
-        "INSERT INTO memorials (
-            id, name, birth_date, birth_location, death_date,
-            death_location, burial_location, plot_details
-        ) VALUES (
-            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
-        ) ON CONFLICT(id) DO UPDATE SET
-            name = ?2,
-            birth_date = ?3,
-            birth_location = ?4,
-            death_date = ?5,
-            death_location = ?6,
-            burial_location = ?7,
-            plot_details = ?8,
-            updated_at = CURRENT_TIMESTAMP
-        ",
-        params![
-            &memorial.id,
-            &memorial.name,
-            &memorial.birth_date,
-            &memorial.birth_location,
-            &memorial.death_date,
-            &memorial.death_location,
-            &memorial.burial_location,
-            &memorial.plot_details
-        ],
-    )
-    .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    /// List IDs of memorials not updated or re-checked within `max_age`
+    ///
+    /// A memorial counts as fresh from the later of its last update and the
+    /// last time a refresh confirmed it unchanged.
+    pub fn stale_ids(&self, max_age: Duration) -> Result<Vec<u64>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT id FROM memorials
+             WHERE COALESCE(checked_at, updated_at) < datetime('now', ?1)
+             ORDER BY updated_at",
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        let modifier = format!("-{} seconds", max_age.as_secs());
+        let ids = stmt
+            .query_map(params![modifier], |row| row.get(0))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        ids.collect::<rusqlite::Result<_>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// Record that a memorial was re-fetched and found unchanged
+    pub fn mark_checked(&self, id: u64) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE memorials SET checked_at = CURRENT_TIMESTAMP WHERE id = ?1")
+            .and_then(|mut stmt| stmt.execute(params![id]))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Time the newest revision of a memorial was fetched (`YYYY-MM-DD HH:MM:SS`)
+    ///
+    /// This is the date to cite the memorial as accessed.
+    pub fn last_fetched(&self, memorial_id: u64) -> Result<Option<String>> {
+        self.conn
+            .prepare_cached(
+                "SELECT fetched_at FROM memorial_revisions
+             WHERE memorial_id = ?1 ORDER BY fetched_at DESC, id DESC LIMIT 1",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_row(params![memorial_id], |row| row.get(0))
+                    .optional()
+            })
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// List every stored revision of a memorial, oldest first
+    pub fn list_revisions(&self, memorial_id: u64) -> Result<Vec<Revision>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT id, memorial_id, fetched_at, data FROM memorial_revisions
+             WHERE memorial_id = ?1 ORDER BY fetched_at, id",
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        let rows = stmt
+            .query_map(params![memorial_id], row_to_revision)
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
 
-    // Commit the transaction
-    tx.commit()
+    /// Store a memorial as it was captured at an earlier time
+    ///
+    /// The capture is kept as a revision dated `captured_at`
+    /// (`YYYY-MM-DD HH:MM:SS`). The memorial itself is only updated when no
+    /// newer copy is stored, so importing old captures never overwrites a
+    /// fresher fetch. Returns `false` if the same capture was already stored.
+    pub fn store_capture(&mut self, memorial: &Memorial, captured_at: &str) -> Result<bool> {
+        let id = memorial
+            .id
+            .ok_or_else(|| Error::MissingField("memorial ID".to_string()))?;
+        let tx = self
+            .conn
+            .transaction()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let id = merged_into(&tx, id)?.unwrap_or(id);
+
+        let mut memorial = memorial.clone();
+        memorial.id = Some(id);
+        let data = serde_json::to_string(&memorial)?;
+
+        let (duplicates, newer, older): (i64, i64, i64) = tx
+            .query_row(
+                "SELECT
+                    COUNT(*) FILTER (WHERE fetched_at = ?2 AND data = ?3),
+                    COUNT(*) FILTER (WHERE fetched_at > ?2),
+                    COUNT(*) FILTER (WHERE fetched_at <= ?2)
+                 FROM memorial_revisions WHERE memorial_id = ?1",
+                params![id, captured_at, data],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        if duplicates > 0 {
+            return Ok(false);
+        }
+        let stored = tx
+            .query_row("SELECT 1 FROM memorials WHERE id = ?1", params![id], |_| {
+                Ok(())
+            })
+            .optional()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?
+            .is_some();
+
+        tx.execute(
+            "INSERT INTO memorial_revisions (memorial_id, fetched_at, data) VALUES (?1, ?2, ?3)",
+            params![id, captured_at, data],
+        )
         .map_err(|e| Error::DatabaseError(e.to_string()))?;
 
+        // A stored memorial without revisions predates them and may be newer
+        if !stored || (newer == 0 && older > 0) {
+            upsert_memorial(&tx, &memorial, None)?;
+            // Date the memorial by its capture, so `refresh` still treats it
+            // as stale and fetches the live page
+            tx.execute(
+                "UPDATE memorials SET updated_at = ?2, checked_at = NULL WHERE id = ?1",
+                params![id, captured_at],
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        }
+
+        tx.commit()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(true)
+    }
+
+    /// Retrieve a single revision by its revision ID
+    pub fn get_revision(&self, revision_id: i64) -> Result<Option<Revision>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT id, memorial_id, fetched_at, data FROM memorial_revisions WHERE id = ?1",
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        stmt.query_row(params![revision_id], row_to_revision)
+            .optional()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// Store the raw HTML of a fetched memorial page, compressed
+    ///
+    /// Nothing is written when the page is identical to the memorial's latest
+    /// snapshot. Returns whether a new snapshot was stored.
+    pub fn store_snapshot(&mut self, memorial_id: u64, url: &str, html: &str) -> Result<bool> {
+        let hash = content_hash(html);
+        let latest: Option<String> = self
+            .conn
+            .prepare_cached(
+                "SELECT content_hash FROM snapshots
+                 WHERE memorial_id = ?1 ORDER BY fetched_at DESC, id DESC LIMIT 1",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_row(params![memorial_id], |row| row.get(0))
+                    .optional()
+            })
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        if latest.as_deref() == Some(hash.as_str()) {
+            return Ok(false);
+        }
+
+        let compressed = compress(html)?;
+        self.conn
+            .prepare_cached(
+                "INSERT INTO snapshots (memorial_id, url, content_hash, html)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )
+            .and_then(|mut stmt| stmt.execute(params![memorial_id, url, hash, compressed]))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        Ok(true)
+    }
+
+    /// Retrieve the most recent snapshot of a memorial's page
+    pub fn latest_snapshot(&self, memorial_id: u64) -> Result<Option<Snapshot>> {
+        let row = self
+            .conn
+            .prepare_cached(
+                "SELECT id, memorial_id, url, content_hash, fetched_at, html FROM snapshots
+                 WHERE memorial_id = ?1 ORDER BY fetched_at DESC, id DESC LIMIT 1",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_row(params![memorial_id], |row| {
+                    Ok((
+                        Snapshot {
+                            id: row.get(0)?,
+                            memorial_id: row.get(1)?,
+                            url: row.get(2)?,
+                            content_hash: row.get(3)?,
+                            fetched_at: row.get(4)?,
+                            html: String::new(),
+                        },
+                        row.get::<_, Vec<u8>>(5)?,
+                    ))
+                })
+                .optional()
+            })
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        row.map(|(snapshot, html)| {
+            Ok(Snapshot {
+                html: decompress(&html)?,
+                ..snapshot
+            })
+        })
+        .transpose()
+    }
+
+    /// List the IDs of memorials with at least one snapshot, in ID order
+    pub fn snapshot_memorial_ids(&self) -> Result<Vec<u64>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT DISTINCT memorial_id FROM snapshots ORDER BY memorial_id")
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// List the distinct burial locations of stored memorials
+    pub fn cemetery_names(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT DISTINCT burial_location FROM memorials
+                 WHERE burial_location IS NOT NULL",
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        let rows = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))
+    }
+
+    /// Compare two revisions of a memorial field by field, from `from_id` to `to_id`
+    ///
+    /// Both revisions must belong to `memorial_id`.
+    pub fn diff_revisions(
+        &self,
+        memorial_id: u64,
+        from_id: i64,
+        to_id: i64,
+    ) -> Result<Vec<FieldChange>> {
+        let revision = |revision_id| {
+            self.get_revision(revision_id)?
+                .filter(|revision| revision.memorial_id == memorial_id)
+                .ok_or_else(|| {
+                    Error::InvalidInput(format!(
+                        "No revision {} for memorial {}",
+                        revision_id, memorial_id
+                    ))
+                })
+        };
+        let from = revision(from_id)?;
+        let to = revision(to_id)?;
+
+        Ok(from.memorial.diff(&to.memorial))
+    }
+}
+
+/// Store a memorial in the SQLite database
+pub fn store_in_db(memorial: &Memorial, db_path: &str) -> Result<()> {
+    MemorialStore::open(db_path)?.store(memorial)?;
     Ok(())
 }
 
@@ -58,107 +712,539 @@ pub fn get_memorial_by_id(id: u64, db_path: &str) -> Result<Option<Memorial>> {
         return Ok(None);
     }
 
-    let conn = Connection::open(db_path).map_err(|e| Error::DatabaseError(e.to_string()))?;
+    MemorialStore::open(db_path)?.get(id)
+}
 
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, name, birth_date, birth_location, death_date, 
-         death_location, burial_location, plot_details
-         FROM memorials WHERE id = ?1",
-        )
-        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+/// Optional flag filters applied to a memorial search
+///
+/// Each filter left as `None` matches every memorial; `Some(value)` only
+/// matches memorials whose flag equals `value`.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    /// Match on the veteran flag
+    pub veteran: Option<bool>,
+    /// Match on the famous memorial flag
+    pub famous: Option<bool>,
+    /// Match on the cenotaph flag
+    pub cenotaph: Option<bool>,
+    /// Match on the "not buried here" flag
+    pub not_buried_here: Option<bool>,
+}
 
-    let memorial_result = stmt.query_row(params![id], |row| {
-        Ok(Memorial {
-            id: row.get(0).ok(),
-            name: row.get(1)?,
-            birth_date: row.get(2).ok(),
-            birth_location: row.get(3).ok(),
-            death_date: row.get(4).ok(),
-            death_location: row.get(5).ok(),
-            burial_location: row.get(6).ok(),
-            plot_details: row.get(7).ok(),
-        })
-    });
+impl SearchFilters {
+    /// Check whether a memorial passes every filter
+    pub fn matches(&self, memorial: &Memorial) -> bool {
+        let flag_matches = |filter: Option<bool>, value: bool| filter.is_none_or(|f| f == value);
 
-    match memorial_result {
-        Ok(memorial) => Ok(Some(memorial)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(Error::DatabaseError(e.to_string())),
+        flag_matches(self.veteran, memorial.is_veteran)
+            && flag_matches(self.famous, memorial.is_famous)
+            && flag_matches(self.cenotaph, memorial.is_cenotaph)
+            && flag_matches(self.not_buried_here, memorial.not_buried_here)
     }
 }
 
 /// Search memorials in the database by name
 pub fn search_memorials_by_name(name: &str, db_path: &str) -> Result<Vec<Memorial>> {
+    search_memorials(name, &SearchFilters::default(), db_path)
+}
+
+/// Search memorials in the database by name, restricted by flag filters
+pub fn search_memorials(
+    name: &str,
+    filters: &SearchFilters,
+    db_path: &str,
+) -> Result<Vec<Memorial>> {
     if !Path::new(db_path).exists() {
         return Ok(Vec::new());
     }
 
-    let conn = Connection::open(db_path).map_err(|e| Error::DatabaseError(e.to_string()))?;
+    MemorialStore::open(db_path)?.search(name, filters)
+}
+
+/// Run a structured query against the database
+pub fn query_memorials(query: &MemorialQuery, db_path: &str) -> Result<Vec<Memorial>> {
+    if !Path::new(db_path).exists() {
+        return Ok(Vec::new());
+    }
+
+    MemorialStore::open(db_path)?.query(query)
+}
+
+/// A search for names that sound alike or are spelled similarly
+#[derive(Debug, Clone)]
+pub struct NameQuery {
+    /// Name to match, such as `Katherine Schmidt` or just `Schmidt`
+    pub name: String,
+    /// How names are compared
+    pub mode: NameMatch,
+    /// Maximum number of hits returned
+    pub limit: usize,
+}
+
+impl Default for NameQuery {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            mode: NameMatch::Phonetic,
+            limit: 100,
+        }
+    }
+}
+
+/// A memorial matched by a name search
+#[derive(Debug, Clone, Serialize)]
+pub struct NameHit {
+    /// The matching memorial
+    pub memorial: Memorial,
+    /// Spelling similarity to the query, from 0 to 1
+    pub score: f64,
+}
+
+/// Run a phonetic or fuzzy name search against the database
+pub fn name_search(query: &NameQuery, db_path: &str) -> Result<Vec<NameHit>> {
+    if !Path::new(db_path).exists() {
+        return Ok(Vec::new());
+    }
+
+    MemorialStore::open(db_path)?.name_search(query)
+}
+
+/// A ranked full-text search over stored memorials
+#[derive(Debug, Clone)]
+pub struct FullTextQuery {
+    /// Search terms; every term must match somewhere in the memorial
+    pub terms: String,
+    /// Treat each term as a prefix, so `Wil` matches `William`
+    pub prefix: bool,
+    /// Maximum number of hits returned
+    pub limit: usize,
+    /// Number of hits skipped, for pagination
+    pub offset: usize,
+}
+
+impl Default for FullTextQuery {
+    fn default() -> Self {
+        Self {
+            terms: String::new(),
+            prefix: false,
+            limit: 100,
+            offset: 0,
+        }
+    }
+}
+
+/// A memorial matched by a full-text search
+#[derive(Debug, Clone, Serialize)]
+pub struct FullTextHit {
+    /// The matching memorial
+    pub memorial: Memorial,
+    /// Relevance score, higher is better
+    pub score: f64,
+    /// Matching text with the hits in `[brackets]`
+    pub snippet: String,
+}
+
+/// Run a full-text search against the database
+pub fn full_text_search(query: &FullTextQuery, db_path: &str) -> Result<Vec<FullTextHit>> {
+    if !Path::new(db_path).exists() {
+        return Ok(Vec::new());
+    }
+
+    MemorialStore::open(db_path)?.full_text_search(query)
+}
+
+/// Build an FTS5 match expression from free-text search terms
+///
+/// Each term is quoted so punctuation in names cannot break the query syntax.
+fn full_text_expression(terms: &str, prefix: bool) -> Option<String> {
+    let quoted: Vec<String> = terms
+        .split_whitespace()
+        .map(|term| {
+            let term = format!("\"{}\"", term.replace('"', "\"\""));
+            if prefix {
+                term + "*"
+            } else {
+                term
+            }
+        })
+        .collect();
+
+    if quoted.is_empty() {
+        None
+    } else {
+        Some(quoted.join(" "))
+    }
+}
+
+/// List IDs of memorials not updated or re-checked within `max_age`
+pub fn stale_memorial_ids(max_age: Duration, db_path: &str) -> Result<Vec<u64>> {
+    if !Path::new(db_path).exists() {
+        return Ok(Vec::new());
+    }
+
+    MemorialStore::open(db_path)?.stale_ids(max_age)
+}
+
+/// Record that a memorial was re-fetched and found unchanged
+pub fn mark_checked(id: u64, db_path: &str) -> Result<()> {
+    MemorialStore::open(db_path)?.mark_checked(id)
+}
+
+/// A stored version of a memorial as parsed at a point in time
+#[derive(Debug, Clone, Serialize)]
+pub struct Revision {
+    /// Revision ID
+    pub revision_id: i64,
+    /// ID of the memorial this revision belongs to
+    pub memorial_id: u64,
+    /// UTC time the version was fetched (`YYYY-MM-DD HH:MM:SS`)
+    pub fetched_at: String,
+    /// The memorial as parsed at that time
+    pub memorial: Memorial,
+}
+
+/// List every stored revision of a memorial, oldest first
+pub fn list_revisions(memorial_id: u64, db_path: &str) -> Result<Vec<Revision>> {
+    if !Path::new(db_path).exists() {
+        return Ok(Vec::new());
+    }
 
+    MemorialStore::open(db_path)?.list_revisions(memorial_id)
+}
+
+/// Retrieve a single revision by its revision ID
+pub fn get_revision(revision_id: i64, db_path: &str) -> Result<Option<Revision>> {
+    if !Path::new(db_path).exists() {
+        return Ok(None);
+    }
+
+    MemorialStore::open(db_path)?.get_revision(revision_id)
+}
+
+/// Compare two revisions of a memorial field by field, from `from_id` to `to_id`
+pub fn diff_revisions(
+    memorial_id: u64,
+    from_id: i64,
+    to_id: i64,
+    db_path: &str,
+) -> Result<Vec<FieldChange>> {
+    MemorialStore::open(db_path)?.diff_revisions(memorial_id, from_id, to_id)
+}
+
+/// Insert or update a memorial and record its revision, returning its ID
+///
+/// `fetched_at` dates a memorial read from an earlier fetch; the row keeps
+/// its last check and the revision is dated by the fetch. `None` means now.
+fn upsert_memorial(
+    conn: &Connection,
+    memorial: &Memorial,
+    fetched_at: Option<&str>,
+) -> Result<u64> {
+    if let Some(into_id) = memorial
+        .id
+        .map(|id| merged_into(conn, id))
+        .transpose()?
+        .flatten()
+    {
+        return Ok(into_id);
+    }
+    let person_name = memorial.parsed_name();
     let mut stmt = conn
-        .prepare(
-            "SELECT id, name, birth_date, birth_location, death_date,
-         death_location, burial_location, plot_details
-         FROM memorials WHERE name LIKE ?1
-         ORDER BY name LIMIT 100",
+        .prepare_cached(
+            "INSERT INTO memorials (
+            id, name, birth_date, birth_location, death_date,
+            death_location, burial_location, plot_details,
+            is_veteran, is_famous, is_cenotaph, not_buried_here,
+            created_by, maintained_by, created_date, sponsored_by, bio,
+            birth_year, death_year,
+            name_prefix, given_names, nickname, surname, maiden_name, name_suffix,
+            plot_latitude, plot_longitude, cemetery_latitude, cemetery_longitude,
+            updated_at
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+            ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29,
+            COALESCE(?30, CURRENT_TIMESTAMP)
+        ) ON CONFLICT(id) DO UPDATE SET
+            name = ?2,
+            birth_date = ?3,
+            birth_location = ?4,
+            death_date = ?5,
+            death_location = ?6,
+            burial_location = ?7,
+            plot_details = ?8,
+            is_veteran = ?9,
+            is_famous = ?10,
+            is_cenotaph = ?11,
+            not_buried_here = ?12,
+            created_by = ?13,
+            maintained_by = ?14,
+            created_date = ?15,
+            sponsored_by = ?16,
+            bio = ?17,
+            birth_year = ?18,
+            death_year = ?19,
+            name_prefix = ?20,
+            given_names = ?21,
+            nickname = ?22,
+            surname = ?23,
+            maiden_name = ?24,
+            name_suffix = ?25,
+            plot_latitude = ?26,
+            plot_longitude = ?27,
+            cemetery_latitude = ?28,
+            cemetery_longitude = ?29,
+            updated_at = COALESCE(?30, CURRENT_TIMESTAMP),
+            checked_at = IIF(?30 IS NULL, CURRENT_TIMESTAMP, checked_at)
+        ",
         )
         .map_err(|e| Error::DatabaseError(e.to_string()))?;
 
-    let search_pattern = format!("%{}%", name);
-    let memorial_iter = stmt
-        .query_map(params![search_pattern], |row| {
-            Ok(Memorial {
-                id: row.get(0).ok(),
-                name: row.get(1)?,
-                birth_date: row.get(2).ok(),
-                birth_location: row.get(3).ok(),
-                death_date: row.get(4).ok(),
-                death_location: row.get(5).ok(),
-                burial_location: row.get(6).ok(),
-                plot_details: row.get(7).ok(),
-            })
+    stmt.execute(params![
+        &memorial.id,
+        &memorial.name,
+        &memorial.birth_date,
+        &memorial.birth_location,
+        &memorial.death_date,
+        &memorial.death_location,
+        &memorial.burial_location,
+        &memorial.plot_details,
+        &memorial.is_veteran,
+        &memorial.is_famous,
+        &memorial.is_cenotaph,
+        &memorial.not_buried_here,
+        &memorial.created_by,
+        &memorial.maintained_by,
+        &memorial.created_date,
+        &memorial.sponsored_by,
+        &memorial.bio,
+        memorial.birth_year(),
+        memorial.death_year(),
+        &person_name.prefix,
+        Some(person_name.given_names()).filter(|given| !given.is_empty()),
+        &person_name.nickname,
+        &person_name.surname,
+        &person_name.maiden,
+        &person_name.suffix,
+        memorial.plot_coordinates.map(|c| c.latitude),
+        memorial.plot_coordinates.map(|c| c.longitude),
+        memorial.cemetery_coordinates.map(|c| c.latitude),
+        memorial.cemetery_coordinates.map(|c| c.longitude),
+        fetched_at
+    ])
+    .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    // Keep the parsed version in the revision history
+    let memorial_id = match memorial.id {
+        Some(id) => id,
+        None => conn.last_insert_rowid() as u64,
+    };
+    record_revision(conn, memorial_id, memorial, fetched_at)?;
+    write_name_keys(conn, memorial_id, &person_name)
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    Ok(memorial_id)
+}
+
+/// Look up the memorial a merged-away duplicate now lives in
+pub(crate) fn merged_into(conn: &Connection, id: u64) -> Result<Option<u64>> {
+    conn.prepare_cached("SELECT into_id FROM merged_memorials WHERE merged_id = ?1")
+        .and_then(|mut stmt| stmt.query_row(params![id], |row| row.get(0)).optional())
+        .map_err(|e| Error::DatabaseError(e.to_string()))
+}
+
+/// Replace the phonetic and normalized name keys for a memorial
+pub(crate) fn write_name_keys(
+    conn: &Connection,
+    memorial_id: u64,
+    name: &PersonName,
+) -> rusqlite::Result<()> {
+    conn.prepare_cached("DELETE FROM name_keys WHERE memorial_id = ?1")?
+        .execute(params![memorial_id])?;
+
+    let mut insert = conn.prepare_cached(
+        "INSERT INTO name_keys (
+            memorial_id, position, part, normalized, soundex, metaphone, metaphone_alt
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for (position, key) in name_keys(name).iter().enumerate() {
+        insert.execute(params![
+            memorial_id,
+            position,
+            key.part.as_str(),
+            key.normalized,
+            key.soundex,
+            key.metaphone,
+            key.metaphone_alt
+        ])?;
+    }
+    Ok(())
+}
+
+/// Store a memorial version unless it matches the latest stored revision
+///
+/// Versions are compared field by field rather than as stored JSON, so a
+/// revision written before a field was added does not count as a change.
+fn record_revision(
+    conn: &Connection,
+    memorial_id: u64,
+    memorial: &Memorial,
+    fetched_at: Option<&str>,
+) -> Result<()> {
+    let mut memorial = memorial.clone();
+    memorial.id = Some(memorial_id);
+    let data = serde_json::to_string(&memorial)?;
+
+    let latest: Option<String> = conn
+        .prepare_cached(
+            "SELECT data FROM memorial_revisions
+             WHERE memorial_id = ?1 ORDER BY fetched_at DESC, id DESC LIMIT 1",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_row(params![memorial_id], |row| row.get(0))
+                .optional()
         })
         .map_err(|e| Error::DatabaseError(e.to_string()))?;
 
-    let mut results = Vec::new();
-    for memorial in memorial_iter {
-        results.push(memorial.map_err(|e| Error::DatabaseError(e.to_string()))?);
+    // A revision that no longer decodes is replaced by a fresh one
+    let unchanged = latest
+        .and_then(|latest| serde_json::from_str::<Memorial>(&latest).ok())
+        .is_some_and(|latest| latest.diff(&memorial).is_empty());
+    if !unchanged {
+        conn.prepare_cached(
+            "INSERT INTO memorial_revisions (memorial_id, fetched_at, data)
+             VALUES (?1, COALESCE(?2, CURRENT_TIMESTAMP), ?3)",
+        )
+        .and_then(|mut stmt| stmt.execute(params![memorial_id, fetched_at, data]))
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
     }
 
-    Ok(results)
+    Ok(())
+}
+
+/// Map a revision row to a revision, decoding the stored memorial
+fn row_to_revision(row: &rusqlite::Row) -> rusqlite::Result<Revision> {
+    let data: String = row.get(3)?;
+    let memorial = serde_json::from_str(&data)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, Type::Text, Box::new(e)))?;
+
+    Ok(Revision {
+        revision_id: row.get(0)?,
+        memorial_id: row.get(1)?,
+        fetched_at: row.get(2)?,
+        memorial,
+    })
 }
 
-/// Initialize the database schema if it doesn't exist yet
-fn initialize_db(conn: &Connection) -> Result<()> {
+/// Columns selected when loading a memorial, in the order `row_to_memorial` expects
+const MEMORIAL_COLUMNS: &str = "id, name, birth_date, birth_location, death_date,
+         death_location, burial_location, plot_details,
+         is_veteran, is_famous, is_cenotaph, not_buried_here,
+         created_by, maintained_by, created_date, sponsored_by, bio,
+         name_prefix, given_names, nickname, surname, maiden_name, name_suffix,
+         plot_latitude, plot_longitude, cemetery_latitude, cemetery_longitude";
+
+/// Number of columns in `MEMORIAL_COLUMNS`
+const MEMORIAL_COLUMN_COUNT: usize = 27;
+
+/// Map a row selected with `MEMORIAL_COLUMNS` to a memorial
+fn row_to_memorial(row: &rusqlite::Row) -> rusqlite::Result<Memorial> {
+    Ok(Memorial {
+        id: row.get(0).ok(),
+        name: row.get(1)?,
+        birth_date: row.get(2).ok(),
+        birth_location: row.get(3).ok(),
+        death_date: row.get(4).ok(),
+        death_location: row.get(5).ok(),
+        burial_location: row.get(6).ok(),
+        plot_details: row.get(7).ok(),
+        is_veteran: row.get(8)?,
+        is_famous: row.get(9)?,
+        is_cenotaph: row.get(10)?,
+        not_buried_here: row.get(11)?,
+        created_by: row.get(12).ok(),
+        maintained_by: row.get(13).ok(),
+        created_date: row.get(14).ok(),
+        sponsored_by: row.get(15).ok(),
+        bio: row.get(16).ok(),
+        person_name: row_to_person_name(row)?,
+        plot_coordinates: row_to_coordinates(row, 23)?,
+        cemetery_coordinates: row_to_coordinates(row, 25)?,
+    })
+}
+
+/// Read a latitude and the longitude in the next column, if both were stored
+fn row_to_coordinates(row: &rusqlite::Row, index: usize) -> rusqlite::Result<Option<Coordinates>> {
+    let latitude: Option<f64> = row.get(index)?;
+    let longitude: Option<f64> = row.get(index + 1)?;
+    Ok(latitude
+        .zip(longitude)
+        .and_then(|(latitude, longitude)| Coordinates::new(latitude, longitude)))
+}
+
+/// Read the name parts selected with `MEMORIAL_COLUMNS`, if any were stored
+fn row_to_person_name(row: &rusqlite::Row) -> rusqlite::Result<Option<PersonName>> {
+    let given: Option<String> = row.get(18)?;
+    let surname: Option<String> = row.get(20)?;
+    if given.is_none() && surname.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(PersonName {
+        prefix: row.get(17)?,
+        given: given
+            .map(|given| given.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default(),
+        nickname: row.get(19)?,
+        surname,
+        maiden: row.get(21)?,
+        suffix: row.get(22)?,
+    }))
+}
+
+/// Apply any pending schema migrations to the database file
+///
+/// Returns the schema versions that were applied.
+pub fn migrate(db_path: &str) -> Result<Vec<u32>> {
+    let mut conn = Connection::open(db_path).map_err(|e| Error::DatabaseError(e.to_string()))?;
+    migrations::migrate(&mut conn)
+}
+
+/// Report the schema version of the database file without changing it
+pub fn schema_status(db_path: &str) -> Result<SchemaStatus> {
+    let conn = if Path::new(db_path).exists() {
+        Connection::open(db_path)
+    } else {
+        Connection::open_in_memory()
+    }
+    .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    migrations::schema_status(&conn)
+}
+
+/// Open or create the database file and bring its schema up to date
+pub(crate) fn open_db(db_path: &str) -> Result<Connection> {
+    let conn = Connection::open(db_path).map_err(|e| Error::DatabaseError(e.to_string()))?;
+    prepare_connection(conn)
+}
+
+/// Apply connection settings and pending migrations to a fresh connection
+fn prepare_connection(mut conn: Connection) -> Result<Connection> {
     conn.execute_batch(
         "
         PRAGMA foreign_keys = ON;
         PRAGMA journal_mode = WAL;
-        
-        CREATE TABLE IF NOT EXISTS memorials (
-            id INTEGER PRIMARY KEY,
-            name TEXT NOT NULL,
-            birth_date TEXT,
-            birth_location TEXT,
-            death_date TEXT,
-            death_location TEXT,
-            burial_location TEXT,
-            plot_details TEXT,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        );
-        
-        CREATE INDEX IF NOT EXISTS idx_memorial_name ON memorials(name);
-        CREATE INDEX IF NOT EXISTS idx_memorial_dates ON memorials(birth_date, death_date);
     ",
     )
-    .map_err(|e| Error::DatabaseError(e.to_string()))
+    .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    migrations::migrate(&mut conn)?;
+    Ok(conn)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::SortField;
     use std::fs;
     use tempfile::tempdir;
 
@@ -172,6 +1258,9 @@ mod tests {
             death_location: Some("Another City".to_string()),
             burial_location: Some("Test Cemetery".to_string()),
             plot_details: Some("Section X, Plot 123".to_string()),
+            is_veteran: true,
+            maintained_by: Some("Test Maintainer".to_string()),
+            ..Memorial::default()
         }
     }
 
@@ -192,6 +1281,9 @@ mod tests {
         assert_eq!(retrieved.id, memorial.id);
         assert_eq!(retrieved.name, memorial.name);
         assert_eq!(retrieved.birth_date, memorial.birth_date);
+        assert!(retrieved.is_veteran);
+        assert!(!retrieved.is_cenotaph);
+        assert_eq!(retrieved.maintained_by, memorial.maintained_by);
 
         // Clean up
         fs::remove_file(db_path).unwrap();
@@ -219,4 +1311,412 @@ mod tests {
         // Clean up
         fs::remove_file(db_path).unwrap();
     }
+
+    #[test]
+    fn test_stale_memorial_ids() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test_stale.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        store_in_db(&create_test_memorial(), db_path_str).unwrap();
+
+        // Just stored, so nothing is a day old yet
+        let stale = stale_memorial_ids(Duration::from_secs(86_400), db_path_str).unwrap();
+        assert!(stale.is_empty());
+
+        let conn = Connection::open(db_path_str).unwrap();
+        conn.execute(
+            "UPDATE memorials SET updated_at = datetime('now', '-2 days'),
+             checked_at = datetime('now', '-2 days')",
+            [],
+        )
+        .unwrap();
+
+        let stale = stale_memorial_ids(Duration::from_secs(86_400), db_path_str).unwrap();
+        assert_eq!(stale, vec![12345]);
+
+        mark_checked(12345, db_path_str).unwrap();
+        let stale = stale_memorial_ids(Duration::from_secs(86_400), db_path_str).unwrap();
+        assert!(stale.is_empty());
+    }
+
+    #[test]
+    fn test_revisions_keep_distinct_versions() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test_revisions.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let mut memorial = create_test_memorial();
+        store_in_db(&memorial, db_path_str).unwrap();
+        // Storing an identical parse does not add a revision
+        store_in_db(&memorial, db_path_str).unwrap();
+
+        memorial.death_date = Some("1981-01-01".to_string());
+        store_in_db(&memorial, db_path_str).unwrap();
+
+        let revisions = list_revisions(12345, db_path_str).unwrap();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(
+            revisions[0].memorial.death_date,
+            Some("1980-12-31".to_string())
+        );
+
+        let changes = diff_revisions(
+            12345,
+            revisions[0].revision_id,
+            revisions[1].revision_id,
+            db_path_str,
+        )
+        .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].field, "death_date");
+
+        assert!(diff_revisions(12345, revisions[0].revision_id, 999, db_path_str).is_err());
+
+        // Revisions of another memorial are not this memorial's history
+        memorial.id = Some(67890);
+        store_in_db(&memorial, db_path_str).unwrap();
+        let other = list_revisions(67890, db_path_str).unwrap()[0].revision_id;
+        let err = diff_revisions(12345, revisions[0].revision_id, other, db_path_str).unwrap_err();
+        assert!(err.to_string().contains("No revision"));
+    }
+
+    #[test]
+    fn test_name_keys_fold_ligatures_and_skip_non_latin_parts() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        for (id, name) in [(1, "Иван Ĳsselmuiden"), (2, "Иван Петров")] {
+            store
+                .store(&Memorial {
+                    id: Some(id),
+                    name: name.to_string(),
+                    ..Memorial::default()
+                })
+                .unwrap();
+        }
+
+        let keys: Vec<(u64, String)> = store
+            .conn
+            .prepare("SELECT memorial_id, normalized FROM name_keys ORDER BY memorial_id, position")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(keys, [(1, "ijsselmuiden".to_string())]);
+    }
+
+    #[test]
+    fn test_revision_from_older_release_is_not_a_change() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test_old_revisions.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let memorial = create_test_memorial();
+        store_in_db(&memorial, db_path_str).unwrap();
+
+        // Revisions written before the coordinate fields existed lack their keys
+        let store = MemorialStore::open(db_path_str).unwrap();
+        let mut data = serde_json::to_value(&memorial).unwrap();
+        let object = data.as_object_mut().unwrap();
+        object.remove("plot_coordinates");
+        object.remove("cemetery_coordinates");
+        store
+            .conn
+            .execute(
+                "UPDATE memorial_revisions SET data = ?1",
+                params![data.to_string()],
+            )
+            .unwrap();
+
+        store_in_db(&memorial, db_path_str).unwrap();
+        assert_eq!(list_revisions(12345, db_path_str).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_store_handle_crud() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+
+        let mut second = create_test_memorial();
+        second.id = Some(67890);
+        second.name = "Second Person".to_string();
+        let mut unnumbered = create_test_memorial();
+        unnumbered.id = None;
+
+        let ids = store
+            .store_many(&[create_test_memorial(), second, unnumbered])
+            .unwrap();
+        assert_eq!(ids[..2], [12345, 67890]);
+        assert_eq!(store.count().unwrap(), 3);
+
+        let listed = store.list(2, 0).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].id, Some(12345));
+        // Limit and offset are ignored when reading every match
+        let all = store
+            .query_all(&MemorialQuery::new().limit(1).offset(2))
+            .unwrap();
+        assert_eq!(all.len(), 3);
+
+        let fetched = store.last_fetched(12345).unwrap().unwrap();
+        assert_eq!(fetched, store.list_revisions(12345).unwrap()[0].fetched_at);
+        assert!(store.last_fetched(99).unwrap().is_none());
+
+        assert!(store.delete(67890).unwrap());
+        assert!(!store.delete(67890).unwrap());
+        assert!(store.get(67890).unwrap().is_none());
+        assert!(store.list_revisions(67890).unwrap().is_empty());
+        assert_eq!(store.count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_store_many_is_atomic() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+
+        // Reject the second memorial so the batch fails partway through
+        let rejected = Memorial {
+            id: Some(2),
+            name: "x".to_string(),
+            ..Memorial::default()
+        };
+        store
+            .conn
+            .execute_batch(
+                "CREATE TRIGGER reject_x BEFORE INSERT ON memorials
+             WHEN NEW.name = 'x' BEGIN SELECT RAISE(ABORT, 'rejected'); END;",
+            )
+            .unwrap();
+
+        assert!(store
+            .store_many(&[create_test_memorial(), rejected])
+            .is_err());
+        assert_eq!(store.count().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_full_text_search() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+
+        let mut soldier = create_test_memorial();
+        soldier.bio = Some("Served with the 1st Virginia Cavalry at Gettysburg.".to_string());
+        let mut william = create_test_memorial();
+        william.id = Some(2);
+        william.name = "William Shakespeare".to_string();
+        william.burial_location = Some("Holy Trinity Church, Stratford".to_string());
+        store.store_many(&[soldier, william]).unwrap();
+
+        let query = |terms: &str, prefix: bool| FullTextQuery {
+            terms: terms.to_string(),
+            prefix,
+            ..FullTextQuery::default()
+        };
+
+        let hits = store
+            .full_text_search(&query("virginia cavalry", false))
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].memorial.id, Some(12345));
+        assert!(hits[0].snippet.contains("[Virginia]"));
+
+        // Places and prefixes
+        assert_eq!(
+            store
+                .full_text_search(&query("stratford", false))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(store
+            .full_text_search(&query("Shake", false))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.full_text_search(&query("Shake", true)).unwrap().len(),
+            1
+        );
+
+        // Quoting keeps stray syntax from breaking the query
+        assert!(store.full_text_search(&query("\"AND (", false)).is_ok());
+
+        // Pagination
+        let all = store.full_text_search(&query("cemetery", false)).unwrap();
+        assert_eq!(all.len(), 1);
+        let paged = FullTextQuery {
+            offset: 1,
+            ..query("cemetery", false)
+        };
+        assert!(store.full_text_search(&paged).unwrap().is_empty());
+
+        // The index follows updates and deletes
+        let mut renamed = store.get(2).unwrap().unwrap();
+        renamed.name = "Anne Hathaway".to_string();
+        store.store(&renamed).unwrap();
+        assert!(store
+            .full_text_search(&query("Shakespeare", false))
+            .unwrap()
+            .is_empty());
+        store.delete(2).unwrap();
+        assert!(store
+            .full_text_search(&query("Hathaway", false))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_search_with_flag_filters() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test_filters.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        store_in_db(&create_test_memorial(), db_path_str).unwrap();
+
+        let veterans = SearchFilters {
+            veteran: Some(true),
+            ..SearchFilters::default()
+        };
+        assert_eq!(
+            search_memorials("Test", &veterans, db_path_str)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let cenotaphs = SearchFilters {
+            cenotaph: Some(true),
+            ..SearchFilters::default()
+        };
+        assert!(search_memorials("Test", &cenotaphs, db_path_str)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_structured_query() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        let memorial = |id, name: &str, died: &str, burial: &str, veteran| Memorial {
+            id: Some(id),
+            name: name.to_string(),
+            birth_date: Some("1 Jan 1820".to_string()),
+            death_date: Some(died.to_string()),
+            death_location: Some("Richmond, Virginia".to_string()),
+            burial_location: Some(burial.to_string()),
+            is_veteran: veteran,
+            ..Memorial::default()
+        };
+        store
+            .store_many(&[
+                memorial(1, "Amos Carter", "3 May 1863", "Hollywood Cemetery", true),
+                memorial(2, "Beth Carter", "1870", "Hollywood Cemetery", false),
+                memorial(3, "Cole Dunn", "1862-09-17", "Oakwood Cemetery", true),
+            ])
+            .unwrap();
+
+        let ids = |query: MemorialQuery| -> Vec<Option<u64>> {
+            store.query(&query).unwrap().iter().map(|m| m.id).collect()
+        };
+
+        assert_eq!(
+            ids(MemorialQuery::new().died_between(1860, 1865)),
+            [Some(1), Some(3)]
+        );
+        assert_eq!(
+            ids(MemorialQuery::new()
+                .died_in("virginia")
+                .cemetery("Hollywood")
+                .sort_by(SortField::DeathYear, true)),
+            [Some(2), Some(1)]
+        );
+        assert_eq!(ids(MemorialQuery::new().location("Oakwood")), [Some(3)]);
+        assert_eq!(
+            ids(MemorialQuery::new().name("carter").flags(SearchFilters {
+                veteran: Some(true),
+                ..SearchFilters::default()
+            })),
+            [Some(1)]
+        );
+        assert_eq!(
+            ids(MemorialQuery::new()
+                .born_between(1820, 1820)
+                .offset(1)
+                .limit(1)),
+            [Some(2)]
+        );
+    }
+
+    #[test]
+    fn test_name_search() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        let memorial = |id, name: &str| Memorial {
+            id: Some(id),
+            name: name.to_string(),
+            ..Memorial::default()
+        };
+        store
+            .store_many(&[
+                memorial(1, "Catherine Smith"),
+                memorial(2, "Katherine Schmidt"),
+                memorial(3, "Mary Jones"),
+            ])
+            .unwrap();
+
+        let search = |store: &MemorialStore, name: &str, mode| {
+            let query = NameQuery {
+                name: name.to_string(),
+                mode,
+                limit: 10,
+            };
+            store
+                .name_search(&query)
+                .unwrap()
+                .iter()
+                .map(|hit| hit.memorial.id.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        // The exact spelling ranks first
+        assert_eq!(
+            search(&store, "Katherine Schmidt", NameMatch::Phonetic),
+            [2, 1]
+        );
+        assert_eq!(search(&store, "Smith", NameMatch::Phonetic), [1, 2]);
+        assert_eq!(search(&store, "Jonas", NameMatch::Fuzzy(1)), [3]);
+        assert!(search(&store, "Jonas", NameMatch::Fuzzy(0)).is_empty());
+
+        // Keys follow renames and deletes
+        store.store(&memorial(3, "Mary Brown")).unwrap();
+        assert!(search(&store, "Jones", NameMatch::Phonetic).is_empty());
+        store.delete(1).unwrap();
+        assert_eq!(search(&store, "Smith", NameMatch::Phonetic), [2]);
+    }
+
+    #[test]
+    fn test_person_name_round_trip_and_surname_search() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        let mut mary = Memorial::new();
+        mary.id = Some(1);
+        mary.name = "Mary Ann Jones Smith".to_string();
+        mary.person_name = Some(PersonName::parse_with_maiden(&mary.name, Some("Jones")));
+        store.store(&mary).unwrap();
+
+        // Names without parsed parts are split when stored
+        let mut john = Memorial::new();
+        john.id = Some(2);
+        john.name = "John Smithson".to_string();
+        store.store(&john).unwrap();
+
+        let stored = store.get(1).unwrap().unwrap();
+        assert_eq!(stored.person_name, mary.person_name);
+        assert_eq!(
+            store.get(2).unwrap().unwrap().parsed_name().to_gedcom(),
+            "John /Smithson/"
+        );
+
+        let ids = |surname: &str| -> Vec<Option<u64>> {
+            let query = MemorialQuery::new().surname(surname);
+            store.query(&query).unwrap().iter().map(|m| m.id).collect()
+        };
+        assert_eq!(ids("smith"), [Some(1)]);
+        assert_eq!(ids("JONES"), [Some(1)]);
+        assert_eq!(ids("Smithson"), [Some(2)]);
+        assert!(ids("Ann").is_empty());
+    }
 }
@@ -0,0 +1,480 @@
+use crate::db::MemorialStore;
+use crate::{
+    levenshtein, normalize_name, soundex, Coordinates, Error, Memorial, MemorialQuery, PersonName,
+    Result, SortField,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// Options controlling duplicate detection
+#[derive(Debug, Clone)]
+pub struct DedupeOptions {
+    /// Minimum similarity, from 0 to 1, for two memorials to be clustered
+    pub threshold: f64,
+}
+
+impl Default for DedupeOptions {
+    fn default() -> Self {
+        Self { threshold: 0.85 }
+    }
+}
+
+/// A group of memorials that probably describe the same person
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCluster {
+    /// The memorials, lowest ID first
+    pub memorials: Vec<Memorial>,
+    /// Average similarity between the memorials in the cluster
+    pub score: f64,
+}
+
+impl DuplicateCluster {
+    /// IDs of the memorials in the cluster
+    pub fn ids(&self) -> Vec<u64> {
+        self.memorials.iter().filter_map(|m| m.id).collect()
+    }
+}
+
+/// How a single field is chosen when duplicates are merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeRule {
+    /// Always keep the kept memorial's value
+    Primary,
+    /// Keep the kept memorial's value, or the first duplicate that has one
+    FirstNonEmpty,
+    /// Take the longest value from any of the memorials
+    Longest,
+}
+
+impl FromStr for MergeRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "primary" => Ok(MergeRule::Primary),
+            "first" => Ok(MergeRule::FirstNonEmpty),
+            "longest" => Ok(MergeRule::Longest),
+            other => Err(Error::InvalidInput(format!(
+                "Unknown merge rule: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Field-level rules for merging duplicate memorials
+///
+/// Fields without an override use the default rule. Flags count as present
+/// when they are set, so `first` and `longest` keep a flag set by any of the
+/// duplicates.
+#[derive(Debug, Clone)]
+pub struct MergePolicy {
+    /// Rule for fields without an override
+    pub default: MergeRule,
+    /// Rules for individual fields, keyed by field name
+    pub overrides: HashMap<String, MergeRule>,
+}
+
+impl Default for MergePolicy {
+    fn default() -> Self {
+        Self {
+            default: MergeRule::FirstNonEmpty,
+            overrides: HashMap::new(),
+        }
+    }
+}
+
+/// Flags describing where the person lies, which follow the kept memorial
+/// unless overridden: a cenotaph merged with the real grave is not a cenotaph
+const BURIAL_FLAGS: [&str; 2] = ["is_cenotaph", "not_buried_here"];
+
+impl MergePolicy {
+    /// Rule that applies to a field
+    pub fn rule(&self, field: &str) -> MergeRule {
+        match self.overrides.get(field) {
+            Some(rule) => *rule,
+            None if BURIAL_FLAGS.contains(&field) => MergeRule::Primary,
+            None => self.default,
+        }
+    }
+
+    /// Choose which memorial supplies a text field, by index
+    fn choose(&self, field: &str, values: &[Option<&str>]) -> Option<usize> {
+        let present = |value: &Option<&str>| value.is_some_and(|v| !v.trim().is_empty());
+        match self.rule(field) {
+            MergeRule::Primary => Some(0),
+            MergeRule::FirstNonEmpty => values.iter().position(present),
+            MergeRule::Longest => values
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| present(value))
+                // Ties go to the earliest memorial
+                .max_by_key(|(i, value)| (value.map_or(0, str::len), usize::MAX - i))
+                .map(|(i, _)| i),
+        }
+    }
+
+    fn merge_text<F>(&self, field: &str, members: &[&Memorial], value: F) -> Option<String>
+    where
+        F: Fn(&Memorial) -> &Option<String>,
+    {
+        let values: Vec<Option<&str>> = members.iter().map(|m| value(m).as_deref()).collect();
+        self.choose(field, &values)
+            .and_then(|i| value(members[i]).clone())
+    }
+
+    fn merge_point<F>(&self, field: &str, members: &[&Memorial], value: F) -> Option<Coordinates>
+    where
+        F: Fn(&Memorial) -> Option<Coordinates>,
+    {
+        match self.rule(field) {
+            MergeRule::Primary => value(members[0]),
+            // Coordinates have no length, so the longest is the first one given
+            MergeRule::FirstNonEmpty | MergeRule::Longest => members.iter().find_map(|m| value(m)),
+        }
+    }
+
+    fn merge_flag<F>(&self, field: &str, members: &[&Memorial], value: F) -> bool
+    where
+        F: Fn(&Memorial) -> bool,
+    {
+        match self.rule(field) {
+            MergeRule::Primary => value(members[0]),
+            MergeRule::FirstNonEmpty | MergeRule::Longest => members.iter().any(|m| value(m)),
+        }
+    }
+}
+
+impl FromStr for MergePolicy {
+    type Err = Error;
+
+    /// Parse overrides such as `bio=longest,plot_details=primary`
+    ///
+    /// A bare rule such as `longest` changes the default rule.
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = Memorial::new()
+            .fields()
+            .into_iter()
+            .map(|(field, _)| field)
+            .filter(|field| *field != "id")
+            .collect();
+
+        let mut policy = MergePolicy::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((field, rule)) => {
+                    let field = field.trim();
+                    if !fields.contains(&field) {
+                        return Err(Error::InvalidInput(format!(
+                            "Unknown memorial field: {}",
+                            field
+                        )));
+                    }
+                    policy
+                        .overrides
+                        .insert(field.to_string(), rule.trim().parse()?);
+                }
+                None => policy.default = entry.parse()?,
+            }
+        }
+        Ok(policy)
+    }
+}
+
+/// Similarity of two memorials from 0 to 1, by name, dates and cemetery
+///
+/// The name counts for half of the score, compared by surname and first
+/// given name so that abbreviated middle names still match. Birth year,
+/// death year and burial location share the rest. Parts missing from either
+/// memorial are left out and the remaining weights scaled up.
+pub fn memorial_similarity(a: &Memorial, b: &Memorial) -> f64 {
+    let mut parts = vec![(0.5, name_similarity(a, b))];
+
+    let year_similarity = |a: Option<i32>, b: Option<i32>| {
+        let (a, b) = (a?, b?);
+        Some(match (a - b).abs() {
+            0 => 1.0,
+            1 => 0.5,
+            _ => 0.0,
+        })
+    };
+    parts.extend(year_similarity(a.birth_year(), b.birth_year()).map(|s| (0.15, s)));
+    parts.extend(year_similarity(a.death_year(), b.death_year()).map(|s| (0.15, s)));
+
+    if let (Some(x), Some(y)) = (&a.burial_location, &b.burial_location) {
+        parts.push((0.2, text_similarity(&normalize_name(x), &normalize_name(y))));
+    }
+
+    let total_weight: f64 = parts.iter().map(|(weight, _)| weight).sum();
+    parts
+        .iter()
+        .map(|(weight, score)| weight * score)
+        .sum::<f64>()
+        / total_weight
+}
+
+/// Find clusters of probable duplicates among the stored memorials
+///
+/// Only memorials whose surnames share a Soundex code are compared, which
+/// keeps the comparison fast on large stores.
+pub fn find_duplicates(
+    store: &MemorialStore,
+    options: &DedupeOptions,
+) -> Result<Vec<DuplicateCluster>> {
+    let memorials = store.query_all(&MemorialQuery::new().sort_by(SortField::Id, false))?;
+
+    let mut blocks: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, memorial) in memorials.iter().enumerate() {
+        let name = memorial.parsed_name();
+        let surname = name.surname.as_deref().unwrap_or(&memorial.name);
+        blocks.entry(soundex(surname)).or_default().push(i);
+    }
+
+    // Union-find over the memorial indices
+    let mut parent: Vec<usize> = (0..memorials.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut scores = HashMap::new();
+    for members in blocks.values() {
+        for (n, &i) in members.iter().enumerate() {
+            for &j in &members[n + 1..] {
+                let score = memorial_similarity(&memorials[i], &memorials[j]);
+                if score >= options.threshold {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[a.max(b)] = a.min(b);
+                }
+                scores.insert((i, j), score);
+            }
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for i in 0..memorials.len() {
+        let r = root(&mut parent, i);
+        groups.entry(r).or_default().push(i);
+    }
+
+    Ok(groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|group| {
+            let pair_scores: Vec<f64> = group
+                .iter()
+                .enumerate()
+                .flat_map(|(n, &i)| group[n + 1..].iter().map(move |&j| (i, j)))
+                .filter_map(|pair| scores.get(&pair).copied())
+                .collect();
+            DuplicateCluster {
+                memorials: group.iter().map(|&i| memorials[i].clone()).collect(),
+                score: pair_scores.iter().sum::<f64>() / pair_scores.len().max(1) as f64,
+            }
+        })
+        .collect())
+}
+
+/// Merge duplicate memorials field by field into one memorial
+///
+/// The first memorial is the one kept: it supplies the ID, and its values
+/// win under the [`MergeRule::Primary`] rule.
+pub fn merge_memorials(memorials: &[Memorial], policy: &MergePolicy) -> Memorial {
+    let members: Vec<&Memorial> = memorials.iter().collect();
+    let Some(primary) = members.first() else {
+        return Memorial::new();
+    };
+
+    let names: Vec<Option<&str>> = members.iter().map(|m| Some(m.name.as_str())).collect();
+    let name_source = members[policy.choose("name", &names).unwrap_or(0)];
+    let parsed_names: Vec<_> = members.iter().map(|m| m.parsed_name()).collect();
+    let maidens: Vec<Option<&str>> = parsed_names.iter().map(|n| n.maiden.as_deref()).collect();
+
+    let mut person_name = name_source.parsed_name();
+    person_name.maiden = policy
+        .choose("maiden_name", &maidens)
+        .and_then(|i| parsed_names[i].maiden.clone());
+
+    Memorial {
+        id: primary.id,
+        name: name_source.name.clone(),
+        person_name: Some(person_name),
+        birth_date: policy.merge_text("birth_date", &members, |m| &m.birth_date),
+        birth_location: policy.merge_text("birth_location", &members, |m| &m.birth_location),
+        death_date: policy.merge_text("death_date", &members, |m| &m.death_date),
+        death_location: policy.merge_text("death_location", &members, |m| &m.death_location),
+        burial_location: policy.merge_text("burial_location", &members, |m| &m.burial_location),
+        plot_details: policy.merge_text("plot_details", &members, |m| &m.plot_details),
+        is_veteran: policy.merge_flag("is_veteran", &members, |m| m.is_veteran),
+        is_famous: policy.merge_flag("is_famous", &members, |m| m.is_famous),
+        is_cenotaph: policy.merge_flag("is_cenotaph", &members, |m| m.is_cenotaph),
+        not_buried_here: policy.merge_flag("not_buried_here", &members, |m| m.not_buried_here),
+        created_by: policy.merge_text("created_by", &members, |m| &m.created_by),
+        maintained_by: policy.merge_text("maintained_by", &members, |m| &m.maintained_by),
+        created_date: policy.merge_text("created_date", &members, |m| &m.created_date),
+        sponsored_by: policy.merge_text("sponsored_by", &members, |m| &m.sponsored_by),
+        bio: policy.merge_text("bio", &members, |m| &m.bio),
+        plot_coordinates: policy.merge_point("plot_coordinates", &members, |m| m.plot_coordinates),
+        cemetery_coordinates: policy
+            .merge_point("cemetery_coordinates", &members, |m| m.cemetery_coordinates),
+    }
+}
+
+/// Merge a cluster in the store and return the surviving memorial
+///
+/// `keep` selects the memorial whose ID survives; by default it is the
+/// lowest ID in the cluster, which is normally the original Find a Grave
+/// memorial rather than a later duplicate.
+pub fn merge_cluster(
+    store: &mut MemorialStore,
+    cluster: &DuplicateCluster,
+    keep: Option<u64>,
+    policy: &MergePolicy,
+) -> Result<Memorial> {
+    let mut members = cluster.memorials.clone();
+    if let Some(keep) = keep {
+        let position = members
+            .iter()
+            .position(|m| m.id == Some(keep))
+            .ok_or_else(|| {
+                Error::InvalidInput(format!("Memorial {} is not in the cluster", keep))
+            })?;
+        let kept = members.remove(position);
+        members.insert(0, kept);
+    }
+
+    let merged = merge_memorials(&members, policy);
+    store.merge(&merged, &cluster.ids())?;
+    Ok(merged)
+}
+
+/// Similarity of two names by surname and first given name
+///
+/// A first name given only as an initial matches any name with that initial.
+fn name_similarity(a: &Memorial, b: &Memorial) -> f64 {
+    let (a, b) = (a.parsed_name(), b.parsed_name());
+    let surname = text_similarity(
+        &normalize_name(a.surname.as_deref().unwrap_or_default()),
+        &normalize_name(b.surname.as_deref().unwrap_or_default()),
+    );
+
+    let first = |name: &PersonName| name.given.first().map(|given| normalize_name(given));
+    match (first(&a), first(&b)) {
+        (Some(x), Some(y)) if !x.is_empty() && !y.is_empty() => {
+            let given = if (x.len() == 1 || y.len() == 1) && x[..1] == y[..1] {
+                0.9
+            } else {
+                text_similarity(&x, &y)
+            };
+            0.6 * surname + 0.4 * given
+        }
+        _ => surname,
+    }
+}
+
+/// Similarity of two strings from 0 to 1, by edit distance
+fn text_similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memorial(id: u64, name: &str, born: &str, died: &str, cemetery: &str) -> Memorial {
+        Memorial {
+            id: Some(id),
+            name: name.to_string(),
+            birth_date: Some(born.to_string()),
+            death_date: Some(died.to_string()),
+            burial_location: Some(cemetery.to_string()),
+            ..Memorial::default()
+        }
+    }
+
+    #[test]
+    fn test_memorial_similarity() {
+        let a = memorial(1, "John Henry Smith", "1820", "1890", "Oak Hill Cemetery");
+        let b = memorial(2, "John H. Smith", "1820", "1890", "Oak Hill Cem.");
+        let c = memorial(3, "John Henry Smith", "1820", "1862", "Arlington");
+
+        assert!(memorial_similarity(&a, &b) > 0.85);
+        assert!(memorial_similarity(&a, &c) < 0.85);
+        assert_eq!(memorial_similarity(&a, &a), 1.0);
+    }
+
+    #[test]
+    fn test_find_and_merge_duplicates() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        let mut original = memorial(10, "John Henry Smith", "1820", "1890", "Oak Hill");
+        original.bio = Some("Farmer.".to_string());
+        let mut copy = memorial(25, "John H. Smith", "1820", "1890", "Oak Hill");
+        copy.bio = Some("Farmer and county judge.".to_string());
+        copy.plot_details = Some("Section B".to_string());
+        copy.is_veteran = true;
+        let other = memorial(30, "Mary Smith", "1830", "1901", "Oak Hill");
+        store.store_many(&[original, copy, other]).unwrap();
+
+        let clusters = find_duplicates(&store, &DedupeOptions::default()).unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].ids(), [10, 25]);
+
+        let policy: MergePolicy = "bio=longest,name=primary".parse().unwrap();
+        let merged = merge_cluster(&mut store, &clusters[0], None, &policy).unwrap();
+        assert_eq!(merged.id, Some(10));
+        assert_eq!(merged.name, "John Henry Smith");
+        assert_eq!(merged.bio.as_deref(), Some("Farmer and county judge."));
+        assert_eq!(merged.plot_details.as_deref(), Some("Section B"));
+        assert!(merged.is_veteran);
+
+        // The duplicate is gone and storing it again does not re-create it
+        assert!(store.get(25).unwrap().is_none());
+        assert_eq!(store.merged_into(25).unwrap(), Some(10));
+        let refetched = memorial(25, "John H. Smith", "1820", "1890", "Oak Hill");
+        assert_eq!(store.store(&refetched).unwrap(), 10);
+        assert!(store.get(25).unwrap().is_none());
+        assert_eq!(store.count().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_merge_policy_from_str() {
+        let policy: MergePolicy = "longest, plot_details=primary".parse().unwrap();
+        assert_eq!(policy.default, MergeRule::Longest);
+        assert_eq!(policy.rule("plot_details"), MergeRule::Primary);
+        assert_eq!(policy.rule("bio"), MergeRule::Longest);
+        assert_eq!(policy.rule("is_veteran"), MergeRule::Longest);
+        assert_eq!(policy.rule("is_cenotaph"), MergeRule::Primary);
+        let policy: MergePolicy = "is_cenotaph=first".parse().unwrap();
+        assert_eq!(policy.rule("is_cenotaph"), MergeRule::FirstNonEmpty);
+        assert!("shoe_size=longest".parse::<MergePolicy>().is_err());
+        assert!("bio=newest".parse::<MergePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_merge_keeps_burial_flags_of_kept_memorial() {
+        let grave = Memorial {
+            id: Some(1),
+            name: "John Smith".to_string(),
+            ..Memorial::default()
+        };
+        let cenotaph = Memorial {
+            id: Some(2),
+            is_cenotaph: true,
+            not_buried_here: true,
+            is_veteran: true,
+            ..grave.clone()
+        };
+
+        let merged = merge_memorials(&[grave, cenotaph], &MergePolicy::default());
+        assert!(!merged.is_cenotaph && !merged.not_buried_here);
+        assert!(merged.is_veteran);
+    }
+}
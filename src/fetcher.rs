@@ -46,6 +46,7 @@ mod tests {
             user_agent: "test-agent".to_string(),
             timeout_secs: 30,
             db_path: "test.db".to_string(),
+            ..Config::default()
         };
 
         assert_eq!(config.user_agent, "test-agent");
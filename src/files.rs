@@ -0,0 +1,345 @@
+use crate::{
+    check_snapshot_backend, parse_page, save_memorial, Config, Error, Memorial, MemorialRepository,
+    Result,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File extensions picked up when parsing a directory
+const PAGE_EXTENSIONS: &[&str] = &["html", "htm", "mhtml", "mht"];
+
+/// A memorial page read from disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedPage {
+    /// The page HTML
+    pub html: String,
+    /// Address the page was saved from, when the file records it
+    pub url: Option<String>,
+}
+
+/// Outcome of parsing a single file
+#[derive(Debug)]
+pub struct FileItem {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Parsed memorial or the error that stopped it
+    pub result: Result<Memorial>,
+}
+
+/// Read a saved `.html` page or `.mhtml` web archive
+///
+/// Web archives are recognised by their MIME headers rather than their file
+/// extension; the first HTML part is returned.
+pub fn read_saved_page(path: &Path) -> Result<SavedPage> {
+    let bytes = fs::read(path)?;
+    let text = String::from_utf8_lossy(&bytes).replace("\r\n", "\n");
+
+    let (headers, body) = split_headers(&text);
+    let content_type = header(&headers, "content-type").unwrap_or_default();
+    if content_type
+        .to_ascii_lowercase()
+        .starts_with("multipart/related")
+    {
+        let url = header(&headers, "snapshot-content-location");
+        return read_mhtml(content_type, body, url)
+            .ok_or_else(|| Error::ParseError(format!("No HTML page in {}", path.display())));
+    }
+
+    Ok(SavedPage {
+        url: saved_from_url(&text),
+        html: text,
+    })
+}
+
+/// Parse a saved memorial page, optionally storing it
+///
+/// When the page itself does not carry a memorial ID, it is taken from the
+/// address the page was saved from. Pages with no ID at all cannot be stored.
+pub fn parse_file(path: &Path, config: &Config, store_to_db: bool) -> Result<Memorial> {
+    check_snapshot_backend(config, store_to_db)?;
+
+    let mut repository = store_to_db.then(|| config.open_repository()).transpose()?;
+    read_and_store(path, config, repository.as_mut())
+}
+
+/// Parse a saved page, storing it in `repository` if one is given
+fn read_and_store(
+    path: &Path,
+    config: &Config,
+    repository: Option<&mut Box<dyn MemorialRepository + Send>>,
+) -> Result<Memorial> {
+    let page = read_saved_page(path)?;
+    let mut memorial = parse_page(&page.html)?;
+    memorial.id = memorial
+        .id
+        .or_else(|| page.url.as_deref().and_then(memorial_id_from_url));
+
+    if let Some(repository) = repository {
+        let id = memorial
+            .id
+            .ok_or_else(|| Error::MissingField("memorial ID".to_string()))?;
+        let url = page
+            .url
+            .unwrap_or_else(|| format!("https://www.findagrave.com/memorial/{}", id));
+        save_memorial(&memorial, &url, &page.html, config, repository.as_mut())?;
+    }
+
+    Ok(memorial)
+}
+
+/// Parse a saved page, or every `.html`, `.htm`, `.mhtml` and `.mht` file in a directory
+///
+/// Directories are read one level deep, in file-name order. Each file gets
+/// its own result, so one bad file does not stop the rest.
+pub fn parse_files(path: &Path, config: &Config, store_to_db: bool) -> Result<Vec<FileItem>> {
+    check_snapshot_backend(config, store_to_db)?;
+
+    let paths = if path.is_dir() {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            let is_page = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| PAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+            if path.is_file() && is_page {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        paths
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    // One repository for every file, rather than reopening it for each
+    let mut repository = store_to_db.then(|| config.open_repository()).transpose()?;
+    Ok(paths
+        .into_iter()
+        .map(|path| FileItem {
+            result: read_and_store(&path, config, repository.as_mut()),
+            path,
+        })
+        .collect())
+}
+
+/// Find the HTML part of a multipart web archive and decode it
+fn read_mhtml(content_type: &str, body: &str, url: Option<&str>) -> Option<SavedPage> {
+    let boundary = content_type
+        .split(';')
+        .filter_map(|param| param.trim().split_once('='))
+        .find(|(name, _)| name.eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"'))?;
+    let delimiter = format!("--{}", boundary);
+
+    body.split(delimiter.as_str()).skip(1).find_map(|part| {
+        let (headers, content) = split_headers(part.trim_start_matches('\n'));
+        let part_type = header(&headers, "content-type")?;
+        if !part_type.to_ascii_lowercase().starts_with("text/html") {
+            return None;
+        }
+
+        let encoding = header(&headers, "content-transfer-encoding").unwrap_or("8bit");
+        let bytes = match encoding.to_ascii_lowercase().as_str() {
+            "quoted-printable" => decode_quoted_printable(content),
+            "base64" => {
+                let compact: String = content.split_whitespace().collect();
+                STANDARD.decode(compact).ok()?
+            }
+            _ => content.as_bytes().to_vec(),
+        };
+
+        Some(SavedPage {
+            html: String::from_utf8_lossy(&bytes).into_owned(),
+            url: header(&headers, "content-location")
+                .or(url)
+                .map(str::to_string),
+        })
+    })
+}
+
+/// Split MIME headers from the body at the first blank line, unfolding continuation lines
+fn split_headers(text: &str) -> (Vec<(String, String)>, &str) {
+    let (head, body) = text.split_once("\n\n").unwrap_or((text, ""));
+    let mut headers: Vec<(String, String)> = Vec::new();
+
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        } else {
+            // Not a header block, e.g. a plain HTML file
+            return (Vec::new(), text);
+        }
+    }
+
+    (headers, body)
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Decode quoted-printable text, where `=XX` is a byte and `=` ends a soft line break
+fn decode_quoted_printable(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'=' if bytes.get(i + 1) == Some(&b'\n') => i += 2,
+            b'=' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                    }
+                    None => {
+                        decoded.push(b'=');
+                        i += 1;
+                    }
+                }
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Address recorded by browsers in a `<!-- saved from url=(0042)https://… -->` comment
+fn saved_from_url(html: &str) -> Option<String> {
+    let start = html.find("saved from url=")? + "saved from url=".len();
+    let rest = &html[start..];
+    let rest = rest
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(')'))
+        .map_or(rest, |(_, rest)| rest);
+    let end = rest.find(char::is_whitespace)?;
+    Some(rest[..end].to_string())
+}
+
+/// Memorial ID from an address such as `https://www.findagrave.com/memorial/123/jane-doe`
+///
+/// Older `fg.cgi?page=gr&GRid=123` addresses, common in web archives, are
+/// recognised too.
+pub(crate) fn memorial_id_from_url(url: &str) -> Option<u64> {
+    let (_, rest) = url
+        .split_once("/memorial/")
+        .or_else(|| url.split_once("GRid="))?;
+    rest.split(['/', '?', '&', '#']).next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemorialStore;
+    use tempfile::tempdir;
+
+    const MHTML: &str = "From: <Saved by Blink>\r\n\
+Snapshot-Content-Location: https://www.findagrave.com/memorial/4242/jane-doe\r\n\
+MIME-Version: 1.0\r\n\
+Content-Type: multipart/related;\r\n\
+\ttype=\"text/html\";\r\n\
+\tboundary=\"----MultipartBoundary--abc\"\r\n\
+\r\n\
+------MultipartBoundary--abc\r\n\
+Content-Type: text/html\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+Content-Location: https://www.findagrave.com/memorial/4242/jane-doe\r\n\
+\r\n\
+<html><body><h1 itemprop=3D\"name\">Jane Do=\r\n\
+e</h1><span itemprop=3D\"birthDate\">1 Jan 1850</span></body></html>\r\n\
+------MultipartBoundary--abc\r\n\
+Content-Type: text/css\r\n\
+\r\n\
+h1 { color: red }\r\n\
+------MultipartBoundary--abc--\r\n";
+
+    #[test]
+    fn test_read_mhtml() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("jane.mhtml");
+        fs::write(&path, MHTML).unwrap();
+
+        let page = read_saved_page(&path).unwrap();
+        assert!(page.html.contains(r#"<h1 itemprop="name">Jane Doe</h1>"#));
+        assert_eq!(
+            page.url.as_deref(),
+            Some("https://www.findagrave.com/memorial/4242/jane-doe")
+        );
+
+        let memorial = parse_file(&path, &Config::default(), false).unwrap();
+        assert_eq!(memorial.name, "Jane Doe");
+        assert_eq!(memorial.id, Some(4242));
+    }
+
+    #[test]
+    fn test_parse_directory_reports_each_file() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.mhtml"), MHTML).unwrap();
+        fs::write(
+            dir.path().join("b.html"),
+            "<!-- saved from url=(0048)https://www.findagrave.com/memorial/77/john-roe -->\n\
+             <html><body><h1 itemprop=\"name\">John Roe</h1></body></html>",
+        )
+        .unwrap();
+        fs::write(dir.path().join("c.htm"), [0xff, 0xfe, 0x00]).unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let db_path = dir.path().join("test.db");
+        let config = Config {
+            db_path: db_path.to_str().unwrap().to_string(),
+            snapshots: true,
+            ..Config::default()
+        };
+        let items = parse_files(dir.path(), &config, true).unwrap();
+        let names: Vec<_> = items
+            .iter()
+            .map(|item| item.path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["a.mhtml", "b.html", "c.htm"]);
+        assert!(items[0].result.is_ok());
+        assert_eq!(items[1].result.as_ref().unwrap().id, Some(77));
+        // A page without a memorial ID cannot be stored
+        assert!(items[2].result.is_err());
+
+        let store = MemorialStore::open(&config.db_path).unwrap();
+        assert_eq!(store.get(77).unwrap().unwrap().name, "John Roe");
+        assert!(store.latest_snapshot(4242).unwrap().is_some());
+        assert_eq!(
+            store.latest_snapshot(77).unwrap().unwrap().url,
+            "https://www.findagrave.com/memorial/77/john-roe"
+        );
+    }
+
+    #[test]
+    fn test_memorial_id_from_url() {
+        let id = |url| memorial_id_from_url(url);
+        assert_eq!(
+            id("https://www.findagrave.com/memorial/123/jane-doe"),
+            Some(123)
+        );
+        assert_eq!(id("https://www.findagrave.com/memorial/123?x=1"), Some(123));
+        assert_eq!(
+            id("http://www.findagrave.com/cgi-bin/fg.cgi?page=gr&GRid=123&ref=acom"),
+            Some(123)
+        );
+        assert_eq!(id("https://www.findagrave.com/cemetery/9"), None);
+    }
+}
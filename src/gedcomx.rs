@@ -0,0 +1,711 @@
+use crate::db::MemorialStore;
+use crate::files::memorial_id_from_url;
+use crate::{
+    Coordinates, DateQualifier, Error, Memorial, MemorialQuery, PartialDate, PersonName, Result,
+    SortField,
+};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::io::Write;
+
+/// Media type of GEDCOM X JSON documents
+pub const GEDCOMX_MEDIA_TYPE: &str = "application/x-gedcomx-v1+json";
+
+const BIRTH: &str = "http://gedcomx.org/Birth";
+const DEATH: &str = "http://gedcomx.org/Death";
+const BURIAL: &str = "http://gedcomx.org/Burial";
+const MILITARY_SERVICE: &str = "http://gedcomx.org/MilitaryService";
+const BIRTH_NAME: &str = "http://gedcomx.org/BirthName";
+const NICKNAME: &str = "http://gedcomx.org/Nickname";
+const PREFIX: &str = "http://gedcomx.org/Prefix";
+const GIVEN: &str = "http://gedcomx.org/Given";
+const SURNAME: &str = "http://gedcomx.org/Surname";
+const SUFFIX: &str = "http://gedcomx.org/Suffix";
+const DIGITAL_ARTIFACT: &str = "http://gedcomx.org/DigitalArtifact";
+
+// Find a Grave details with no standard GEDCOM X type use `data:` URIs, as
+// the specification suggests for custom types
+const CENOTAPH: &str = "data:,Cenotaph";
+const MEMORIAL: &str = "data:,Memorial";
+const FAMOUS: &str = "data:,Famous";
+const NOT_BURIED_HERE: &str = "data:,NotBuriedHere";
+
+/// GEDCOM X document describing memorials
+///
+/// Each memorial becomes a person with birth, death and burial facts whose
+/// places point to place descriptions (carrying the cemetery's and grave's
+/// GPS positions), and a source description of its Find a Grave page. A
+/// cenotaph is recorded as a `data:,Cenotaph` fact and a memorial where the
+/// person is not buried as a `data:,Memorial` fact, so they are not mistaken
+/// for burials. [`from_gedcomx`] reads the document back.
+pub fn to_gedcomx(memorials: &[Memorial]) -> Value {
+    let mut persons = Vec::new();
+    let mut places: Vec<Value> = Vec::new();
+    let mut sources = Vec::new();
+
+    for (index, memorial) in memorials.iter().enumerate() {
+        let key = memorial
+            .id
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| format!("x{}", index + 1));
+
+        let mut facts = Vec::new();
+        for (kind, date, place) in [
+            (BIRTH, &memorial.birth_date, &memorial.birth_location),
+            (DEATH, &memorial.death_date, &memorial.death_location),
+        ] {
+            if date.is_some() || place.is_some() {
+                let place = place
+                    .as_deref()
+                    .map(|place| place_reference(&mut places, place, None, None));
+                facts.push(fact(kind, date.as_deref(), place));
+            }
+        }
+        if memorial.burial_location.is_some()
+            || memorial.plot_details.is_some()
+            || memorial.plot_coordinates.is_some()
+        {
+            let kind = if memorial.is_cenotaph {
+                CENOTAPH
+            } else if memorial.not_buried_here {
+                MEMORIAL
+            } else {
+                BURIAL
+            };
+            let place = memorial.burial_location.as_deref().map(|cemetery| {
+                place_reference(
+                    &mut places,
+                    cemetery,
+                    memorial.cemetery_coordinates,
+                    memorial.plot_coordinates,
+                )
+            });
+            let mut burial = fact(kind, None, place);
+            if let Some(plot) = &memorial.plot_details {
+                burial["value"] = Value::from(plot.as_str());
+            }
+            if memorial.not_buried_here {
+                burial["qualifiers"] = json!([{ "name": NOT_BURIED_HERE }]);
+            }
+            facts.push(burial);
+        }
+        if memorial.is_veteran {
+            facts.push(fact(MILITARY_SERVICE, None, None));
+        }
+        if memorial.is_famous {
+            facts.push(fact(FAMOUS, None, None));
+        }
+
+        let mut person = json!({
+            "id": format!("P{}", key),
+            "names": names(memorial),
+            "sources": [{ "description": format!("#S{}", key) }],
+        });
+        if let Some(url) = memorial.url() {
+            person["identifiers"] = json!({ "http://gedcomx.org/Primary": [url] });
+        }
+        if !facts.is_empty() {
+            person["facts"] = Value::Array(facts);
+        }
+        if let Some(bio) = &memorial.bio {
+            person["notes"] = json!([{ "subject": "Biography", "text": bio }]);
+        }
+        persons.push(person);
+
+        sources.push(source_description(memorial, &key));
+    }
+
+    let mut document = json!({ "persons": persons });
+    if !places.is_empty() {
+        document["places"] = Value::Array(places);
+    }
+    document["sourceDescriptions"] = Value::Array(sources);
+    document
+}
+
+/// A GEDCOM X person that could not be read as a memorial
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedPerson {
+    /// Position of the person in the document's `persons`, counting from 1
+    pub index: usize,
+    /// The person's `id` in the document, if it has one
+    pub id: Option<String>,
+    /// The person's name as read
+    pub name: String,
+    /// Why the person was rejected
+    pub reason: String,
+}
+
+/// Memorials read from a GEDCOM X document
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GedcomxImport {
+    /// Persons read as memorials, in document order
+    pub memorials: Vec<Memorial>,
+    /// Persons that could not be read
+    pub rejected: Vec<RejectedPerson>,
+}
+
+/// Read memorials back from a GEDCOM X document
+///
+/// Understands the documents written by [`to_gedcomx`], and takes what it can
+/// from other GEDCOM X JSON: the first name of each person, its birth, death
+/// and burial facts, and the memorial ID from a Find a Grave address among
+/// the person's identifiers or sources. Persons without such an address are
+/// rejected, since a memorial cannot be stored without its ID.
+pub fn from_gedcomx(document: &Value) -> Result<GedcomxImport> {
+    read_persons(document, |_| Ok(Memorial::new()))
+}
+
+/// Read memorials from a GEDCOM X document and store them
+///
+/// Each person updates the stored copy of its memorial: fields the document
+/// does not carry keep their stored values. Nothing is written on a dry run.
+pub fn import_gedcomx(
+    store: &mut MemorialStore,
+    document: &Value,
+    dry_run: bool,
+) -> Result<GedcomxImport> {
+    let import = read_persons(document, |id| Ok(store.get(id)?.unwrap_or_default()))?;
+    if !dry_run {
+        store.store_many(&import.memorials)?;
+    }
+    Ok(import)
+}
+
+/// Read each person onto the memorial `base` returns for its ID
+fn read_persons<F>(document: &Value, mut base: F) -> Result<GedcomxImport>
+where
+    F: FnMut(u64) -> Result<Memorial>,
+{
+    let persons = document["persons"]
+        .as_array()
+        .ok_or_else(|| Error::ParseError("GEDCOM X document has no persons".to_string()))?;
+    let places = document["places"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or(&[]);
+    let sources = document["sourceDescriptions"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or(&[]);
+
+    let mut import = GedcomxImport::default();
+    for (index, person) in persons.iter().enumerate() {
+        let source = person["sources"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|reference| find(sources, reference["description"].as_str()?))
+            .next();
+        let urls = person["identifiers"]
+            .as_object()
+            .into_iter()
+            .flat_map(|identifiers| identifiers.values())
+            .filter_map(Value::as_array)
+            .flatten()
+            .chain(source.map(|source| &source["about"]));
+        let Some(id) = urls
+            .filter_map(Value::as_str)
+            .find_map(memorial_id_from_url)
+        else {
+            let mut memorial = Memorial::new();
+            read_names(person, &mut memorial);
+            import.rejected.push(RejectedPerson {
+                index: index + 1,
+                id: person["id"].as_str().map(str::to_string),
+                name: memorial.name,
+                reason: "No Find a Grave memorial address among its identifiers or sources"
+                    .to_string(),
+            });
+            continue;
+        };
+        let mut memorial = base(id)?;
+        memorial.id = Some(id);
+        read_names(person, &mut memorial);
+        if let Some(source) = source {
+            for note in source["notes"].as_array().into_iter().flatten() {
+                let Some(text) = note["text"].as_str() else {
+                    continue;
+                };
+                let text = Some(text.to_string());
+                match note["subject"].as_str() {
+                    Some("Created by") => memorial.created_by = text,
+                    Some("Maintained by") => memorial.maintained_by = text,
+                    Some("Created") => memorial.created_date = text,
+                    Some("Sponsored by") => memorial.sponsored_by = text,
+                    _ => {}
+                }
+            }
+        }
+
+        // Facts only replace the stored values they carry
+        for fact in person["facts"].as_array().into_iter().flatten() {
+            let date = fact["date"]["original"]
+                .as_str()
+                .or_else(|| fact["date"]["formal"].as_str())
+                .map(|date| date.trim_start_matches('+').to_string());
+            let place = fact["place"]["original"].as_str().map(str::to_string);
+            match fact["type"].as_str() {
+                Some(BIRTH) => {
+                    set_present(&mut memorial.birth_date, date);
+                    set_present(&mut memorial.birth_location, place);
+                }
+                Some(DEATH) => {
+                    set_present(&mut memorial.death_date, date);
+                    set_present(&mut memorial.death_location, place);
+                }
+                Some(kind @ (BURIAL | CENOTAPH | MEMORIAL)) => {
+                    memorial.is_cenotaph = kind == CENOTAPH;
+                    memorial.not_buried_here = kind == MEMORIAL
+                        || fact["qualifiers"]
+                            .as_array()
+                            .into_iter()
+                            .flatten()
+                            .any(|qualifier| qualifier["name"] == NOT_BURIED_HERE);
+                    set_present(&mut memorial.burial_location, place);
+                    set_present(
+                        &mut memorial.plot_details,
+                        fact["value"].as_str().map(str::to_string),
+                    );
+                    let description = fact["place"]["description"]
+                        .as_str()
+                        .and_then(|reference| find(places, reference));
+                    if let Some(description) = description {
+                        match description["jurisdiction"]["resource"]
+                            .as_str()
+                            .and_then(|reference| find(places, reference))
+                        {
+                            // A grave position, within its cemetery
+                            Some(cemetery) => {
+                                set_present(
+                                    &mut memorial.plot_coordinates,
+                                    coordinates(description),
+                                );
+                                set_present(
+                                    &mut memorial.cemetery_coordinates,
+                                    coordinates(cemetery),
+                                );
+                            }
+                            None => set_present(
+                                &mut memorial.cemetery_coordinates,
+                                coordinates(description),
+                            ),
+                        }
+                    }
+                }
+                Some(MILITARY_SERVICE) => memorial.is_veteran = true,
+                Some(FAMOUS) => memorial.is_famous = true,
+                _ => {}
+            }
+        }
+
+        let bio = person["notes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|note| note["subject"] == "Biography")
+            .and_then(|note| note["text"].as_str());
+        if let Some(bio) = bio {
+            memorial.bio = Some(bio.to_string());
+        }
+        import.memorials.push(memorial);
+    }
+    Ok(import)
+}
+
+/// Write every matching memorial as one GEDCOM X document, in name order
+///
+/// Returns the number of memorials written.
+pub fn export_gedcomx<W: Write>(
+    store: &MemorialStore,
+    query: &MemorialQuery,
+    out: &mut W,
+) -> Result<usize> {
+    let memorials = store.query_all(&query.clone().sort_by(SortField::Name, false))?;
+
+    serde_json::to_writer_pretty(&mut *out, &to_gedcomx(&memorials))?;
+    writeln!(out)?;
+    Ok(memorials.len())
+}
+
+/// The person's names: the full name, then the maiden name and nickname if known
+fn names(memorial: &Memorial) -> Value {
+    let name = memorial.parsed_name();
+    let mut parts = Vec::new();
+    let given = name.given_names();
+    for (kind, value) in [
+        (PREFIX, name.prefix.as_deref()),
+        (
+            GIVEN,
+            Some(given.as_str()).filter(|given| !given.is_empty()),
+        ),
+        (SURNAME, name.surname.as_deref()),
+        (SUFFIX, name.suffix.as_deref()),
+    ] {
+        if let Some(value) = value {
+            parts.push(json!({ "type": kind, "value": value }));
+        }
+    }
+
+    let mut names = vec![json!({
+        "preferred": true,
+        "nameForms": [{ "fullText": memorial.name, "parts": parts }],
+    })];
+    if let Some(maiden) = &name.maiden {
+        let full = format!("{} {}", given, maiden);
+        names.push(json!({
+            "type": BIRTH_NAME,
+            "nameForms": [{
+                "fullText": full.trim(),
+                "parts": [{ "type": SURNAME, "value": maiden }],
+            }],
+        }));
+    }
+    if let Some(nickname) = &name.nickname {
+        names.push(json!({
+            "type": NICKNAME,
+            "nameForms": [{ "fullText": nickname }],
+        }));
+    }
+    Value::Array(names)
+}
+
+/// Set the memorial's name and name parts from a person's names
+fn read_names(person: &Value, memorial: &mut Memorial) {
+    let names = person["names"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    let mut parsed = PersonName::default();
+    let mut read = false;
+    for name in names {
+        let form = &name["nameForms"][0];
+        let part = |kind: &str| {
+            form["parts"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|part| part["type"] == kind)
+                .and_then(|part| part["value"].as_str())
+                .map(str::to_string)
+        };
+        match name["type"].as_str() {
+            Some(BIRTH_NAME) => parsed.maiden = part(SURNAME),
+            Some(NICKNAME) => parsed.nickname = form["fullText"].as_str().map(str::to_string),
+            _ if !read => {
+                read = true;
+                memorial.name = form["fullText"].as_str().unwrap_or_default().to_string();
+                parsed.prefix = part(PREFIX);
+                parsed.given = part(GIVEN)
+                    .map(|given| given.split_whitespace().map(str::to_string).collect())
+                    .unwrap_or_default();
+                parsed.surname = part(SURNAME);
+                parsed.suffix = part(SUFFIX);
+            }
+            _ => {}
+        }
+    }
+    if read {
+        memorial.person_name = Some(parsed);
+    }
+}
+
+/// Replace a field only when the document carries a value for it
+fn set_present<T>(field: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *field = value;
+    }
+}
+
+/// A fact with an optional date and place
+fn fact(kind: &str, date: Option<&str>, place: Option<Value>) -> Value {
+    let mut fact = json!({ "type": kind });
+    if let Some(date) = date {
+        fact["date"] = json!({ "original": date });
+        if let Some(formal) = formal_date(date) {
+            fact["date"]["formal"] = Value::from(formal);
+        }
+    }
+    if let Some(place) = place {
+        fact["place"] = place;
+    }
+    fact
+}
+
+/// GEDCOM X formal date, such as `+1861-04-12`, `A+1820` for `abt 1820` or
+/// the open range `/+1900` for `bef 1900`
+fn formal_date(date: &str) -> Option<String> {
+    let date = date.parse::<PartialDate>().ok()?;
+    let mut formal = format!("+{:04}", date.year);
+    if let Some(month) = date.month {
+        formal.push_str(&format!("-{:02}", month));
+        if let Some(day) = date.day {
+            formal.push_str(&format!("-{:02}", day));
+        }
+    }
+    Some(match date.qualifier {
+        DateQualifier::Exact => formal,
+        DateQualifier::About => format!("A{}", formal),
+        DateQualifier::Before => format!("/{}", formal),
+        DateQualifier::After => format!("{}/", formal),
+    })
+}
+
+/// Reference to a place, describing it (and a grave within it) the first time it is seen
+fn place_reference(
+    places: &mut Vec<Value>,
+    name: &str,
+    position: Option<Coordinates>,
+    grave: Option<Coordinates>,
+) -> Value {
+    let id = describe_place(places, name, position, None);
+    let id = match grave {
+        Some(grave) => describe_place(places, name, Some(grave), Some(&id)),
+        None => id,
+    };
+    json!({ "original": name, "description": format!("#{}", id) })
+}
+
+/// ID of the description of a place, adding the description if it is new
+fn describe_place(
+    places: &mut Vec<Value>,
+    name: &str,
+    position: Option<Coordinates>,
+    within: Option<&str>,
+) -> String {
+    let mut description = json!({ "names": [{ "value": name }] });
+    if let Some(position) = position {
+        description["latitude"] = Value::from(position.latitude);
+        description["longitude"] = Value::from(position.longitude);
+    }
+    if let Some(within) = within {
+        description["jurisdiction"] = json!({ "resource": format!("#{}", within) });
+    }
+
+    let same = |place: &Value| {
+        let mut place = place.clone();
+        if let Some(place) = place.as_object_mut() {
+            place.remove("id");
+        }
+        place == description
+    };
+    if let Some(existing) = places.iter().find(|place| same(place)) {
+        return existing["id"].as_str().unwrap_or_default().to_string();
+    }
+    let id = format!("PL{}", places.len() + 1);
+    description["id"] = Value::from(id.as_str());
+    places.push(description);
+    id
+}
+
+/// Source description of the memorial's Find a Grave page
+fn source_description(memorial: &Memorial, key: &str) -> Value {
+    let mut citation = format!(
+        "Find a Grave, database and images, memorial page for {}",
+        memorial.name
+    );
+    if let Some(id) = memorial.id {
+        citation.push_str(&format!(", Find a Grave Memorial ID {}", id));
+    }
+    if let Some(url) = memorial.url() {
+        citation.push_str(&format!(" ({})", url));
+    }
+
+    let mut source = Map::new();
+    source.insert("id".to_string(), Value::from(format!("S{}", key)));
+    source.insert("resourceType".to_string(), Value::from(DIGITAL_ARTIFACT));
+    if let Some(url) = memorial.url() {
+        source.insert("about".to_string(), Value::from(url));
+    }
+    source.insert(
+        "titles".to_string(),
+        json!([{ "value": format!("{} - Find a Grave Memorial", memorial.name) }]),
+    );
+    source.insert("citations".to_string(), json!([{ "value": citation }]));
+
+    // The page's contributors, read back by subject in `from_gedcomx`
+    let notes: Vec<Value> = [
+        ("Created by", &memorial.created_by),
+        ("Maintained by", &memorial.maintained_by),
+        ("Created", &memorial.created_date),
+        ("Sponsored by", &memorial.sponsored_by),
+    ]
+    .into_iter()
+    .filter_map(|(subject, text)| Some(json!({ "subject": subject, "text": text.as_ref()? })))
+    .collect();
+    if !notes.is_empty() {
+        source.insert("notes".to_string(), Value::Array(notes));
+    }
+    Value::Object(source)
+}
+
+/// The element of `items` a `#id` reference points to
+fn find<'a>(items: &'a [Value], reference: &str) -> Option<&'a Value> {
+    let id = reference.strip_prefix('#')?;
+    items.iter().find(|item| item["id"] == id)
+}
+
+fn coordinates(place: &Value) -> Option<Coordinates> {
+    Coordinates::new(place["latitude"].as_f64()?, place["longitude"].as_f64()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memorial() -> Memorial {
+        Memorial {
+            id: Some(1234),
+            name: "Dr Mary Ann \"Polly\" Smith Jr.".to_string(),
+            person_name: Some(PersonName {
+                prefix: Some("Dr".to_string()),
+                given: vec!["Mary".to_string(), "Ann".to_string()],
+                nickname: Some("Polly".to_string()),
+                surname: Some("Smith".to_string()),
+                maiden: Some("Jones".to_string()),
+                suffix: Some("Jr.".to_string()),
+            }),
+            birth_date: Some("abt 1820".to_string()),
+            birth_location: Some("Columbus, Ohio".to_string()),
+            death_date: Some("12 Apr 1861".to_string()),
+            death_location: Some("Columbus, Ohio".to_string()),
+            burial_location: Some("Green Lawn Cemetery".to_string()),
+            plot_details: Some("Section 4".to_string()),
+            is_veteran: true,
+            is_famous: true,
+            not_buried_here: true,
+            created_by: Some("Founder".to_string()),
+            maintained_by: Some("Jane Doe".to_string()),
+            created_date: Some("1 Jan 2005".to_string()),
+            sponsored_by: Some("A Friend".to_string()),
+            bio: Some("Nurse in the war.".to_string()),
+            plot_coordinates: Coordinates::new(39.95, -83.03),
+            cemetery_coordinates: Coordinates::new(39.94, -83.02),
+            ..Memorial::default()
+        }
+    }
+
+    #[test]
+    fn test_formal_dates() {
+        assert_eq!(formal_date("Apr 1861").as_deref(), Some("+1861-04"));
+        assert_eq!(formal_date("c. 1820").as_deref(), Some("A+1820"));
+        assert_eq!(formal_date("bef 1900").as_deref(), Some("/+1900"));
+        assert_eq!(
+            formal_date("after 3 Mar 1900").as_deref(),
+            Some("+1900-03-03/")
+        );
+        assert_eq!(formal_date("Unknown"), None);
+    }
+
+    #[test]
+    fn test_gedcomx_document() {
+        let document = to_gedcomx(&[memorial()]);
+        let person = &document["persons"][0];
+        assert_eq!(person["id"], "P1234");
+        assert_eq!(
+            person["names"][0]["nameForms"][0]["parts"][1],
+            json!({ "type": GIVEN, "value": "Mary Ann" })
+        );
+        assert_eq!(person["names"][1]["type"], BIRTH_NAME);
+
+        let facts = person["facts"].as_array().unwrap();
+        assert_eq!(
+            facts[0]["date"],
+            json!({ "original": "abt 1820", "formal": "A+1820" })
+        );
+        assert_eq!(facts[1]["date"]["formal"], "+1861-04-12");
+        // Birth and death share one place description
+        assert_eq!(facts[0]["place"]["description"], "#PL1");
+        assert_eq!(facts[1]["place"]["description"], "#PL1");
+        assert_eq!(facts[2]["type"], MEMORIAL);
+        assert_eq!(facts[2]["place"]["description"], "#PL3");
+
+        let places = document["places"].as_array().unwrap();
+        assert_eq!(places.len(), 3);
+        assert_eq!(places[2]["jurisdiction"]["resource"], "#PL2");
+        assert_eq!(places[1]["latitude"], 39.94);
+
+        let source = &document["sourceDescriptions"][0];
+        assert_eq!(source["id"], "S1234");
+        assert_eq!(source["about"], "https://www.findagrave.com/memorial/1234");
+        assert_eq!(person["sources"][0]["description"], "#S1234");
+    }
+
+    #[test]
+    fn test_gedcomx_round_trip() {
+        let plain = Memorial {
+            id: Some(99),
+            name: "Tom Brown".to_string(),
+            burial_location: Some("Green Lawn Cemetery".to_string()),
+            cemetery_coordinates: Coordinates::new(39.94, -83.02),
+            is_cenotaph: true,
+            ..Memorial::default()
+        };
+        let originals = [memorial(), plain];
+        let text = serde_json::to_string(&to_gedcomx(&originals)).unwrap();
+        let read = from_gedcomx(&serde_json::from_str(&text).unwrap())
+            .unwrap()
+            .memorials;
+
+        assert_eq!(read.len(), 2);
+        for (original, read) in originals.iter().zip(&read) {
+            let mut expected = original.clone();
+            expected.person_name = Some(original.parsed_name());
+            assert_eq!(
+                serde_json::to_value(read).unwrap(),
+                serde_json::to_value(&expected).unwrap()
+            );
+        }
+        assert!(from_gedcomx(&json!({ "records": [] })).is_err());
+    }
+
+    #[test]
+    fn test_gedcomx_person_without_memorial_is_rejected() {
+        let mut document = to_gedcomx(&[memorial()]);
+        document["persons"].as_array_mut().unwrap().push(json!({
+            "id": "P2",
+            "names": [{ "nameForms": [{ "fullText": "Tom Brown" }] }],
+            "identifiers": { "http://gedcomx.org/Primary": ["https://example.org/tom"] },
+        }));
+
+        let import = from_gedcomx(&document).unwrap();
+        assert_eq!(import.memorials.len(), 1);
+        assert_eq!(import.memorials[0].id, Some(1234));
+        assert_eq!(import.rejected.len(), 1);
+        let rejected = &import.rejected[0];
+        assert_eq!(rejected.index, 2);
+        assert_eq!(rejected.id.as_deref(), Some("P2"));
+        assert_eq!(rejected.name, "Tom Brown");
+    }
+
+    #[test]
+    fn test_import_keeps_stored_fields_the_document_lacks() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        store.store(&memorial()).unwrap();
+
+        let document = json!({
+            "persons": [{
+                "names": [{ "nameForms": [{ "fullText": "Mary Ann Smith" }] }],
+                "identifiers": {
+                    "http://gedcomx.org/Primary": ["https://www.findagrave.com/memorial/1234"]
+                },
+                "facts": [{ "type": DEATH, "date": { "original": "13 Apr 1861" } }],
+            }],
+        });
+        let dry_run = import_gedcomx(&mut store, &document, true).unwrap();
+        assert_eq!(
+            dry_run.memorials[0].death_date.as_deref(),
+            Some("13 Apr 1861")
+        );
+        assert_eq!(
+            store.get(1234).unwrap().unwrap().death_date.as_deref(),
+            Some("12 Apr 1861")
+        );
+
+        import_gedcomx(&mut store, &document, false).unwrap();
+        let stored = store.get(1234).unwrap().unwrap();
+        assert_eq!(stored.name, "Mary Ann Smith");
+        assert_eq!(stored.death_date.as_deref(), Some("13 Apr 1861"));
+        assert_eq!(stored.death_location, memorial().death_location);
+        assert_eq!(stored.bio.as_deref(), Some("Nurse in the war."));
+        assert_eq!(stored.plot_coordinates, memorial().plot_coordinates);
+        assert!(stored.is_veteran);
+    }
+}
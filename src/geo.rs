@@ -0,0 +1,298 @@
+use crate::db::MemorialStore;
+use crate::{Coordinates, Error, Memorial, MemorialQuery, Result, SortField};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Folder for memorials with coordinates but no burial location
+const UNKNOWN_CEMETERY: &str = "Unknown cemetery";
+
+/// Map formats for exporting burial locations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoFormat {
+    /// A GeoJSON `FeatureCollection` (RFC 7946)
+    GeoJson,
+    /// A KML document with one folder per cemetery
+    Kml,
+}
+
+impl FromStr for GeoFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "geojson" => Ok(GeoFormat::GeoJson),
+            "kml" => Ok(GeoFormat::Kml),
+            other => Err(Error::InvalidInput(format!(
+                "Unknown map format: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A memorial placed on the map
+struct Placement<'a> {
+    memorial: &'a Memorial,
+    position: Coordinates,
+    /// Whether the position is of the grave (`plot`) or only the `cemetery`
+    precision: &'static str,
+}
+
+/// Write the burial locations of matching memorials as GeoJSON or KML
+///
+/// The query's filters select the memorials; its sort order and paging are
+/// ignored. Memorials are placed at their grave's GPS position, or at their
+/// cemetery when the grave has none, and memorials with neither are left
+/// out. Returns the number of memorials written.
+pub fn export_geo<W: Write>(
+    store: &MemorialStore,
+    query: &MemorialQuery,
+    format: GeoFormat,
+    out: &mut W,
+) -> Result<usize> {
+    let mut memorials = store.query_all(&query.clone().sort_by(SortField::Name, false))?;
+    memorials.retain(|memorial| {
+        memorial.plot_coordinates.is_some() || memorial.cemetery_coordinates.is_some()
+    });
+
+    match format {
+        GeoFormat::GeoJson => {
+            serde_json::to_writer_pretty(&mut *out, &to_geojson(&memorials))?;
+            writeln!(out)?;
+        }
+        GeoFormat::Kml => out.write_all(to_kml(&memorials).as_bytes())?,
+    }
+    Ok(memorials.len())
+}
+
+/// GeoJSON `FeatureCollection` of memorials with coordinates, ordered by cemetery
+///
+/// Each feature's properties carry the name, dates, plot and cemetery, and
+/// whether the point marks the grave or only the cemetery.
+pub fn to_geojson(memorials: &[Memorial]) -> Value {
+    let features: Vec<Value> = by_cemetery(memorials)
+        .into_iter()
+        .flat_map(|(cemetery, placements)| {
+            placements.into_iter().map(move |placement| {
+                let memorial = placement.memorial;
+                json!({
+                    "type": "Feature",
+                    "id": memorial.id,
+                    "geometry": {
+                        "type": "Point",
+                        // GeoJSON puts longitude first
+                        "coordinates": [placement.position.longitude, placement.position.latitude],
+                    },
+                    "properties": {
+                        "name": memorial.name,
+                        "birth_date": memorial.birth_date,
+                        "death_date": memorial.death_date,
+                        "plot": memorial.plot_details,
+                        "cemetery": cemetery,
+                        "precision": placement.precision,
+                        "url": memorial.url(),
+                    },
+                })
+            })
+        })
+        .collect();
+
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+/// KML document of memorials with coordinates, with a folder per cemetery
+pub fn to_kml(memorials: &[Memorial]) -> String {
+    let mut kml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n\
+         <Document>\n  <name>Find a Grave memorials</name>\n",
+    );
+
+    for (cemetery, placements) in by_cemetery(memorials) {
+        kml.push_str(&format!(
+            "  <Folder>\n    <name>{}</name>\n",
+            escape_xml(cemetery)
+        ));
+        for placement in placements {
+            let memorial = placement.memorial;
+            let data = [
+                ("memorial_id", memorial.id.map(|id| id.to_string())),
+                ("birth_date", memorial.birth_date.clone()),
+                ("death_date", memorial.death_date.clone()),
+                ("plot", memorial.plot_details.clone()),
+                ("precision", Some(placement.precision.to_string())),
+                ("url", memorial.url()),
+            ];
+            let description: Vec<String> = [
+                ("Born", &memorial.birth_date),
+                ("Died", &memorial.death_date),
+                ("Plot", &memorial.plot_details),
+            ]
+            .into_iter()
+            .filter_map(|(label, value)| Some(format!("{}: {}", label, value.as_deref()?)))
+            .collect();
+
+            kml.push_str("    <Placemark>\n");
+            kml.push_str(&format!(
+                "      <name>{}</name>\n",
+                escape_xml(&memorial.name)
+            ));
+            if !description.is_empty() {
+                kml.push_str(&format!(
+                    "      <description>{}</description>\n",
+                    escape_xml(&description.join("\n"))
+                ));
+            }
+            kml.push_str("      <ExtendedData>\n");
+            for (name, value) in data {
+                if let Some(value) = value {
+                    kml.push_str(&format!(
+                        "        <Data name=\"{}\"><value>{}</value></Data>\n",
+                        name,
+                        escape_xml(&value)
+                    ));
+                }
+            }
+            kml.push_str("      </ExtendedData>\n");
+            kml.push_str(&format!(
+                "      <Point><coordinates>{},{}</coordinates></Point>\n",
+                placement.position.longitude, placement.position.latitude
+            ));
+            kml.push_str("    </Placemark>\n");
+        }
+        kml.push_str("  </Folder>\n");
+    }
+
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+/// Where a memorial goes on the map, preferring the grave over the cemetery
+fn placement(memorial: &Memorial) -> Option<Placement<'_>> {
+    let (position, precision) = match (memorial.plot_coordinates, memorial.cemetery_coordinates) {
+        (Some(plot), _) => (plot, "plot"),
+        (None, Some(cemetery)) => (cemetery, "cemetery"),
+        (None, None) => return None,
+    };
+    Some(Placement {
+        memorial,
+        position,
+        precision,
+    })
+}
+
+/// Group placeable memorials by cemetery, in cemetery order
+fn by_cemetery(memorials: &[Memorial]) -> BTreeMap<&str, Vec<Placement<'_>>> {
+    let mut groups: BTreeMap<&str, Vec<Placement>> = BTreeMap::new();
+    for placement in memorials.iter().filter_map(placement) {
+        let cemetery = placement
+            .memorial
+            .burial_location
+            .as_deref()
+            .map(str::trim)
+            .filter(|cemetery| !cemetery.is_empty())
+            .unwrap_or(UNKNOWN_CEMETERY);
+        groups.entry(cemetery).or_default().push(placement);
+    }
+    groups
+}
+
+/// Escape text for use in XML element content and attribute values
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memorial(id: u64, name: &str, died: &str, cemetery: &str) -> Memorial {
+        Memorial {
+            id: Some(id),
+            name: name.to_string(),
+            death_date: Some(died.to_string()),
+            burial_location: Some(cemetery.to_string()),
+            cemetery_coordinates: Coordinates::new(42.0, -71.0),
+            ..Memorial::default()
+        }
+    }
+
+    fn store() -> MemorialStore {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        let mut smith = memorial(1, "John Smith", "1862", "Oak Hill & Annex");
+        smith.plot_coordinates = Coordinates::new(42.5, -71.5);
+        smith.plot_details = Some("Row <3>".to_string());
+        store.store(&smith).unwrap();
+        store
+            .store(&memorial(2, "Mary Smith", "1901", "Elm Grove"))
+            .unwrap();
+        store
+            .store(&memorial(3, "Tom Jones", "1863", "Elm Grove"))
+            .unwrap();
+        store
+            .store(&Memorial {
+                cemetery_coordinates: None,
+                ..memorial(4, "Ann Smith", "1864", "Elm Grove")
+            })
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn test_geojson_grouped_and_filtered() {
+        let store = store();
+        let mut out = Vec::new();
+        let count = export_geo(
+            &store,
+            &MemorialQuery::new().name("Smith"),
+            GeoFormat::GeoJson,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(count, 2);
+
+        let geojson: Value = serde_json::from_slice(&out).unwrap();
+        let features = geojson["features"].as_array().unwrap();
+        // Elm Grove sorts before Oak Hill; Ann Smith has no coordinates
+        assert_eq!(features[0]["properties"]["name"], "Mary Smith");
+        assert_eq!(features[0]["properties"]["precision"], "cemetery");
+        assert_eq!(features[1]["properties"]["cemetery"], "Oak Hill & Annex");
+        assert_eq!(features[1]["properties"]["precision"], "plot");
+        assert_eq!(features[1]["geometry"]["coordinates"], json!([-71.5, 42.5]));
+
+        let mut out = Vec::new();
+        let civil_war = MemorialQuery::new().died_between(1861, 1865);
+        assert_eq!(
+            export_geo(&store, &civil_war, GeoFormat::GeoJson, &mut out).unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_kml_folders_per_cemetery() {
+        let store = store();
+        let mut out = Vec::new();
+        export_geo(&store, &MemorialQuery::new(), GeoFormat::Kml, &mut out).unwrap();
+        let kml = String::from_utf8(out).unwrap();
+
+        assert_eq!(kml.matches("<Folder>").count(), 2);
+        assert_eq!(kml.matches("<Placemark>").count(), 3);
+        assert!(kml.contains("<name>Oak Hill &amp; Annex</name>"));
+        assert!(kml.contains("<description>Died: 1862\nPlot: Row &lt;3&gt;</description>"));
+        assert!(kml.contains("<coordinates>-71.5,42.5</coordinates>"));
+        assert!(kml.find("Elm Grove").unwrap() < kml.find("Oak Hill").unwrap());
+    }
+}
@@ -7,21 +7,98 @@
 //! ## Features
 //!
 //! - Fetch HTML from FindAGrave memorial pages
+//! - Parse saved HTML and MHTML pages offline
 //! - Parse and extract structured data
+//! - Process batches of memorials with bounded concurrency
 //! - Store data in SQLite database
+//! - Structured search by name, dates, places and flags
+//! - Phonetic and fuzzy name matching for inconsistent spellings
+//! - Find and merge duplicate memorials
+//! - Tag, annotate and group memorials into research projects
+//! - Lint stored records for impossible or incomplete data
+//! - Resumable import queue for long-running fetches
+//! - Refresh stale records and report what changed
+//! - Keep raw page snapshots and re-parse them offline
+//! - Import dated memorial captures from WARC web archives
+//! - CSV and TSV export and validated import for spreadsheets
+//! - GeoJSON and KML maps of burial locations, grouped by cemetery
+//! - Static HTML research reports that work offline
+//! - Markdown (Obsidian) vault export with cemetery wikilinks
+//! - WikiTree biographies with Find a Grave source templates
+//! - BibTeX, BibLaTeX, RIS and CSL-JSON citations for reference managers
+//! - GEDCOM X JSON export and import
 //! - Error handling for web scraping operations
 
+pub mod annotations;
+mod batch;
+mod bibliography;
+mod dates;
 pub mod db;
+mod dedupe;
 mod error;
 mod fetcher;
+mod files;
+mod gedcomx;
+mod geo;
+mod lint;
+mod migrations;
 mod models;
+mod name;
 mod parser;
+mod phonetic;
+mod query;
+pub mod queue;
+mod refresh;
+mod report;
+mod repository;
+mod snapshot;
+mod tabular;
+mod vault;
+mod warc;
+mod wikitree;
 
-pub use db::store_in_db;
+pub use batch::{
+    process_memorials, read_inputs, BatchItem, BatchOptions, BatchSummary, ResultOrder,
+};
+pub use bibliography::{
+    export_bibliography, to_biblatex, to_bibliography, to_bibtex, to_csl_json, to_ris, BibEntry,
+    BibFormat,
+};
+pub use dates::{DateQualifier, PartialDate};
+pub use db::{store_in_db, MemorialStore};
+pub use dedupe::{
+    find_duplicates, memorial_similarity, merge_cluster, merge_memorials, DedupeOptions,
+    DuplicateCluster, MergePolicy, MergeRule,
+};
 pub use error::{Error, Result};
 pub use fetcher::fetch_page;
-pub use models::Memorial;
+pub use files::{parse_file, parse_files, read_saved_page, FileItem, SavedPage};
+pub use gedcomx::{
+    export_gedcomx, from_gedcomx, import_gedcomx, to_gedcomx, GedcomxImport, RejectedPerson,
+    GEDCOMX_MEDIA_TYPE,
+};
+pub use geo::{export_geo, to_geojson, to_kml, GeoFormat};
+pub use lint::{lint_memorial, lint_store, LintFinding, LintOptions, LintReport, Severity};
+pub use models::{Coordinates, FieldChange, Memorial};
+pub use name::PersonName;
 pub use parser::parse_page;
+pub use phonetic::{double_metaphone, levenshtein, normalize_name, soundex, NameMatch};
+pub use query::{parse_year_range, MemorialQuery, SortField};
+pub use refresh::{parse_age, refresh_memorials, RefreshOptions, RefreshReport, RefreshSummary};
+pub use report::{write_report, ReportOptions, ReportSummary};
+pub use repository::{
+    open_repository, InMemoryRepository, JsonLinesRepository, MemorialRepository, StorageBackend,
+};
+pub use snapshot::{reparse_snapshots, ReparseOptions, ReparseReport, ReparseSummary, Snapshot};
+pub use tabular::{
+    export_table, import_table, write_rejected, Column, ExportOptions, ImportOptions, ImportReport,
+    RejectedRow, TableFormat,
+};
+pub use vault::{export_vault, VaultSummary};
+pub use warc::{
+    import_warc, read_warc, CaptureReport, WarcCapture, WarcCaptures, WarcImportSummary,
+};
+pub use wikitree::{export_wikitree, to_wikitree};
 
 /// Configuration options for the parser
 #[derive(Debug, Clone)]
@@ -30,8 +107,12 @@ pub struct Config {
     pub user_agent: String,
     /// Connection timeout in seconds
     pub timeout_secs: u64,
-    /// Path to SQLite database
+    /// Path to the storage file (SQLite database or JSON-lines file)
     pub db_path: String,
+    /// Storage backend used when saving memorials
+    pub backend: StorageBackend,
+    /// Keep a compressed snapshot of each fetched page when saving to SQLite
+    pub snapshots: bool,
 }
 
 impl Default for Config {
@@ -40,19 +121,52 @@ impl Default for Config {
             user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36".to_string(),
             timeout_secs: 10,
             db_path: "memorials.db".to_string(),
+            backend: StorageBackend::Sqlite,
+            snapshots: false,
         }
     }
 }
 
+impl Config {
+    /// Open the memorial repository selected by `backend` at `db_path`
+    pub fn open_repository(&self) -> Result<Box<dyn MemorialRepository + Send>> {
+        open_repository(self.backend, &self.db_path)
+    }
+}
+
 /// Process a FindAGrave URL or memorial ID
 ///
 /// This function takes a URL or ID, fetches the page, parses the data,
-/// and optionally stores it in the database.
+/// and optionally stores it in the database. With `config.snapshots` set the
+/// raw page is stored too, so it can be re-parsed later without fetching.
 pub async fn process_memorial(
     url_or_id: &str,
     config: &Config,
     store_to_db: bool,
 ) -> Result<Memorial> {
+    check_snapshot_backend(config, store_to_db)?;
+
+    let (memorial, url, html) = fetch_memorial(url_or_id, config).await?;
+
+    // Store in the configured repository if requested
+    if store_to_db {
+        save_memorial(
+            &memorial,
+            &url,
+            &html,
+            config,
+            config.open_repository()?.as_mut(),
+        )?;
+    }
+
+    Ok(memorial)
+}
+
+/// Fetch and parse a memorial, returning it with the URL and page it came from
+pub(crate) async fn fetch_memorial(
+    url_or_id: &str,
+    config: &Config,
+) -> Result<(Memorial, String, String)> {
     // Normalize input to a proper URL
     let url = if url_or_id.contains("findagrave.com") {
         url_or_id.to_string()
@@ -66,12 +180,36 @@ pub async fn process_memorial(
     // Parse the page
     let memorial = parse_page(&html)?;
 
-    // Store in database if requested
-    if store_to_db {
-        store_in_db(&memorial, &config.db_path)?;
+    Ok((memorial, url, html))
+}
+
+/// Reject snapshots for backends that cannot hold them, before any work is done
+pub(crate) fn check_snapshot_backend(config: &Config, store_to_db: bool) -> Result<()> {
+    if store_to_db && config.snapshots && config.backend != StorageBackend::Sqlite {
+        return Err(Error::InvalidInput(
+            "Snapshots require the sqlite backend".to_string(),
+        ));
     }
+    Ok(())
+}
 
-    Ok(memorial)
+/// Store a parsed memorial in `repository`, opened from `config`
+///
+/// With `config.snapshots` set, the page it was parsed from is stored too,
+/// unless the page belongs to a merged-away duplicate: it was stored under
+/// the surviving memorial, whose own page it is not.
+pub(crate) fn save_memorial(
+    memorial: &Memorial,
+    url: &str,
+    html: &str,
+    config: &Config,
+    repository: &mut dyn MemorialRepository,
+) -> Result<u64> {
+    let id = repository.store(memorial)?;
+    if config.snapshots && memorial.id.is_none_or(|page_id| page_id == id) {
+        MemorialStore::open(&config.db_path)?.store_snapshot(id, url, html)?;
+    }
+    Ok(id)
 }
 
 #[cfg(test)]
@@ -83,5 +221,6 @@ mod tests {
         let config = Config::default();
         assert_eq!(config.timeout_secs, 10);
         assert_eq!(config.db_path, "memorials.db");
+        assert_eq!(config.backend, StorageBackend::Sqlite);
     }
 }
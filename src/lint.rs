@@ -0,0 +1,254 @@
+use crate::db::MemorialStore;
+use crate::{DateQualifier, Memorial, MemorialQuery, PartialDate, Result, SortField};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// How serious a lint finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth a look, but may well be correct
+    Warning,
+    /// The record cannot be right as stored
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A single problem found in a memorial
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintFinding {
+    /// FindAGrave memorial ID
+    pub memorial_id: Option<u64>,
+    /// Name on the memorial, for display
+    pub name: String,
+    /// How serious the problem is
+    pub severity: Severity,
+    /// Short identifier of the rule that fired, such as `death-before-birth`
+    pub rule: &'static str,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.memorial_id {
+            Some(id) => write!(f, "{:<7} {} ({})", self.severity, id, self.name)?,
+            None => write!(f, "{:<7} {}", self.severity, self.name)?,
+        }
+        write!(f, ": {} [{}]", self.message, self.rule)
+    }
+}
+
+/// Options controlling the lint rules
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    /// Largest age at death, in years, accepted as plausible
+    pub max_age: i32,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self { max_age: 120 }
+    }
+}
+
+/// Findings from linting a set of memorials
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LintReport {
+    /// Number of memorials checked
+    pub checked: usize,
+    /// Findings, in memorial order
+    pub findings: Vec<LintFinding>,
+}
+
+impl LintReport {
+    /// Number of findings with the given severity
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    }
+
+    /// Check whether any finding is an error
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+}
+
+/// Check a single memorial for impossible or incomplete data
+pub fn lint_memorial(memorial: &Memorial, options: &LintOptions) -> Vec<LintFinding> {
+    let mut findings = Vec::new();
+    let mut report = |severity, rule, message: String| {
+        findings.push(LintFinding {
+            memorial_id: memorial.id,
+            name: memorial.name.clone(),
+            severity,
+            rule,
+            message,
+        })
+    };
+
+    let mut parse = |label: &str, date: &Option<String>| {
+        let date = date.as_deref().map(str::trim).filter(|d| !is_unknown(d))?;
+        match date.parse::<PartialDate>() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                report(
+                    Severity::Warning,
+                    "unparseable-date",
+                    format!("{} date \"{}\" could not be parsed", label, date),
+                );
+                None
+            }
+        }
+    };
+    let birth = parse("Birth", &memorial.birth_date);
+    let death = parse("Death", &memorial.death_date);
+
+    if let (Some(birth), Some(death)) = (birth, death) {
+        // A birth "before" or death "after" a date may lie further out than
+        // written, so only the bound each gives can be checked
+        if birth.compare(&death) == Some(Ordering::Greater) {
+            if birth.qualifier != DateQualifier::Before && death.qualifier != DateQualifier::After {
+                report(
+                    Severity::Error,
+                    "death-before-birth",
+                    format!(
+                        "death date {} is before birth date {}",
+                        memorial.death_date.as_deref().unwrap_or_default().trim(),
+                        memorial.birth_date.as_deref().unwrap_or_default().trim()
+                    ),
+                );
+            }
+        } else if birth.qualifier != DateQualifier::After
+            && death.qualifier != DateQualifier::Before
+        {
+            let age = birth.years_until(&death);
+            if age > options.max_age {
+                report(
+                    Severity::Error,
+                    "lifespan-too-long",
+                    format!("age at death of {} years exceeds {}", age, options.max_age),
+                );
+            }
+        }
+    }
+
+    let has_death_date = memorial
+        .death_date
+        .as_deref()
+        .is_some_and(|d| !is_unknown(d.trim()));
+    let has_burial = memorial
+        .burial_location
+        .as_deref()
+        .is_some_and(|b| !b.trim().is_empty());
+    if has_death_date && !has_burial {
+        report(
+            Severity::Warning,
+            "missing-burial",
+            "has a death date but no burial location".to_string(),
+        );
+    }
+
+    findings
+}
+
+/// Check every memorial in the store
+pub fn lint_store(store: &MemorialStore, options: &LintOptions) -> Result<LintReport> {
+    let mut report = LintReport::default();
+    for memorial in store.query_all(&MemorialQuery::new().sort_by(SortField::Id, false))? {
+        report.checked += 1;
+        report.findings.extend(lint_memorial(&memorial, options));
+    }
+    Ok(report)
+}
+
+/// Dates that are blank or explicitly recorded as unknown
+fn is_unknown(date: &str) -> bool {
+    date.is_empty() || date.eq_ignore_ascii_case("unknown")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memorial(birth: &str, death: &str, burial: Option<&str>) -> Memorial {
+        Memorial {
+            id: Some(1),
+            name: "John Smith".to_string(),
+            birth_date: Some(birth.to_string()),
+            death_date: Some(death.to_string()),
+            burial_location: burial.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn rules(memorial: &Memorial) -> Vec<&'static str> {
+        lint_memorial(memorial, &LintOptions::default())
+            .into_iter()
+            .map(|finding| finding.rule)
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_lifespans() {
+        let ok = memorial("12 Apr 1820", "3 Mar 1890", Some("Oak Hill Cemetery"));
+        assert!(rules(&ok).is_empty());
+
+        let backwards = memorial("12 Apr 1890", "3 Mar 1820", Some("Oak Hill Cemetery"));
+        assert_eq!(rules(&backwards), ["death-before-birth"]);
+
+        let ancient = memorial("1700", "1890", Some("Oak Hill Cemetery"));
+        assert_eq!(rules(&ancient), ["lifespan-too-long"]);
+        assert_eq!(
+            lint_memorial(&ancient, &LintOptions::default())[0].severity,
+            Severity::Error
+        );
+
+        // Same year, unknown months: cannot tell, so not flagged
+        let vague = memorial("1890", "Mar 1890", Some("Oak Hill Cemetery"));
+        assert!(rules(&vague).is_empty());
+
+        // Open-ended dates are only checked against the bound they give
+        let cemetery = Some("Oak Hill Cemetery");
+        assert!(rules(&memorial("bef 1900", "1890", cemetery)).is_empty());
+        assert!(rules(&memorial("1900", "aft 1890", cemetery)).is_empty());
+        assert_eq!(
+            rules(&memorial("aft 1900", "1890", cemetery)),
+            ["death-before-birth"]
+        );
+        assert!(rules(&memorial("aft 1700", "1890", cemetery)).is_empty());
+        assert!(rules(&memorial("1700", "bef 1890", cemetery)).is_empty());
+        assert_eq!(
+            rules(&memorial("bef 1700", "abt 1890", cemetery)),
+            ["lifespan-too-long"]
+        );
+    }
+
+    #[test]
+    fn test_lint_dates_and_burial() {
+        let garbled = memorial("spring of 1820", "1890", None);
+        assert_eq!(rules(&garbled), ["unparseable-date", "missing-burial"]);
+
+        // An unknown death date is not a date to bury against
+        let unknown = memorial("1820", "Unknown", None);
+        assert!(rules(&unknown).is_empty());
+
+        let report = LintReport {
+            checked: 1,
+            findings: lint_memorial(&garbled, &LintOptions::default()),
+        };
+        assert_eq!(report.count(Severity::Warning), 2);
+        assert!(!report.has_errors());
+    }
+}
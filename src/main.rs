@@ -1,7 +1,32 @@
 use clap::{Parser, Subcommand};
-use findagrave_citation_parser::db::{get_memorial_by_id, search_memorials_by_name};
-use findagrave_citation_parser::{process_memorial, Config, Result};
-use std::path::PathBuf;
+use findagrave_citation_parser::annotations::{
+    add_note, add_tags, add_to_project, delete_project, list_notes, list_projects, list_tags,
+    memorial_tags, remove_from_project, remove_note, remove_tags,
+};
+use findagrave_citation_parser::db::{
+    self, diff_revisions, full_text_search, get_revision, list_revisions, name_search,
+    query_memorials, FullTextQuery, NameQuery, SearchFilters,
+};
+use findagrave_citation_parser::queue::{
+    enqueue, list_entries, queue_status, run_queue, QueueRunOptions, QueueState,
+};
+use findagrave_citation_parser::{
+    export_bibliography, export_gedcomx, export_geo, export_table, export_vault, export_wikitree,
+    find_duplicates, import_table, lint_store, merge_cluster, write_rejected, write_report, Column,
+    DedupeOptions, DuplicateCluster, ExportOptions, ImportOptions, LintOptions, Memorial,
+    MemorialStore, MergePolicy, ReportOptions, Severity, TableFormat,
+};
+use findagrave_citation_parser::{
+    import_gedcomx, import_warc, parse_age, parse_files, parse_year_range, process_memorial,
+    process_memorials, read_inputs, refresh_memorials, reparse_snapshots, to_bibliography,
+    to_gedcomx, to_wikitree, BatchOptions, BatchSummary, Config, Error, MemorialQuery, NameMatch,
+    RefreshOptions, ReparseOptions, Result, ResultOrder, SortField, StorageBackend,
+    WarcImportSummary,
+};
+use futures::StreamExt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -10,6 +35,8 @@ struct Cli {
     command: Commands,
 }
 
+// Parsed once at startup, so the size of the largest variant does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Fetch and parse a Find a Grave memorial
@@ -21,6 +48,289 @@ enum Commands {
         #[arg(short, long)]
         save: bool,
 
+        /// Also keep the raw page HTML so it can be re-parsed later
+        #[arg(long, requires = "save")]
+        snapshot: bool,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Storage backend (sqlite, jsonl)
+        #[arg(short, long, default_value = "sqlite")]
+        backend: String,
+
+        /// Output format (text, json, wikitree, bibtex, biblatex, ris, csl-json, gedcomx)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Fetch and parse many memorials listed in a file or on stdin
+    Batch {
+        /// File with one URL or memorial ID per line (reads stdin if omitted or "-")
+        input: Option<PathBuf>,
+
+        /// Maximum number of memorials fetched at the same time
+        #[arg(short, long, default_value_t = 4)]
+        concurrency: usize,
+
+        /// Print results as they complete instead of in input order
+        #[arg(long)]
+        unordered: bool,
+
+        /// Save results to database
+        #[arg(short, long)]
+        save: bool,
+
+        /// Also keep the raw page HTML so it can be re-parsed later
+        #[arg(long, requires = "save")]
+        snapshot: bool,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Storage backend (sqlite, jsonl)
+        #[arg(short, long, default_value = "sqlite")]
+        backend: String,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Inspect or upgrade the local database schema
+    Db {
+        #[command(subcommand)]
+        action: DbCommands,
+    },
+
+    /// Find probable duplicate memorials, show them side by side, and merge them
+    Dedupe {
+        /// Minimum similarity, from 0 to 1, for memorials to count as duplicates
+        #[arg(long, default_value_t = 0.85)]
+        threshold: f64,
+
+        /// Merge duplicates; needs --keep or --all
+        #[arg(long)]
+        merge: bool,
+
+        /// Only merge the cluster containing this memorial, keeping its ID
+        #[arg(long, requires = "merge")]
+        keep: Option<u64>,
+
+        /// Merge every cluster into its lowest memorial ID
+        #[arg(long, requires = "merge", conflicts_with = "keep")]
+        all: bool,
+
+        /// Field merge rules (primary, first, longest), e.g. "bio=longest,name=primary"
+        #[arg(long, default_value = "", requires = "merge")]
+        policy: String,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Export stored memorials for spreadsheets or maps
+    Export {
+        /// Output format (csv, tsv, geojson, kml, wikitree, bibtex, biblatex, ris, csl-json,
+        /// gedcomx, markdown-vault)
+        #[arg(short, long, default_value = "csv")]
+        format: String,
+
+        /// Vault directory to write notes into (markdown-vault)
+        #[arg(conflicts_with = "output")]
+        path: Option<PathBuf>,
+
+        /// Comma-separated columns to write, in order (csv and tsv; default: all)
+        #[arg(short, long)]
+        columns: Option<String>,
+
+        /// Character encoding of csv and tsv output, e.g. utf-8 or windows-1252
+        #[arg(short, long, default_value = "utf-8")]
+        encoding: String,
+
+        /// Only export memorials whose name contains this text
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Only export memorials with this surname or maiden name
+        #[arg(long)]
+        surname: Option<String>,
+
+        /// Only export memorials born in a year range, e.g. 1820-1840
+        #[arg(long, value_name = "YEARS")]
+        born_between: Option<String>,
+
+        /// Only export memorials that died in a year range, e.g. 1861-1865
+        #[arg(long, value_name = "YEARS")]
+        died_between: Option<String>,
+
+        /// Only export memorials whose cemetery contains this text
+        #[arg(long, value_name = "CEMETERY")]
+        buried_in: Option<String>,
+
+        /// Only export memorials with this tag (repeat to require several)
+        #[arg(long)]
+        tag: Vec<String>,
+
+        /// Only export memorials in this research project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Write to this file instead of standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// Import memorials from a CSV or TSV spreadsheet, validating each row, or a GEDCOM X file
+    Import {
+        /// CSV or TSV file whose header names the columns, or a GEDCOM X JSON file
+        file: PathBuf,
+
+        /// Input format (csv, tsv, gedcomx); guessed from the file extension by default
+        #[arg(short, long)]
+        format: Option<String>,
+
+        /// Character encoding of the input, e.g. utf-8 or windows-1252
+        #[arg(short, long, default_value = "utf-8")]
+        encoding: String,
+
+        /// Validate rows without writing to the database
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Write rejected rows and their reasons to this file
+        #[arg(long)]
+        rejected: Option<PathBuf>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// Check stored memorials for impossible lifespans and incomplete data
+    Lint {
+        /// Largest plausible age at death, in years
+        #[arg(long, default_value_t = 120)]
+        max_age: i32,
+
+        /// Only report errors, not warnings
+        #[arg(long)]
+        errors_only: bool,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Add, remove or list research notes on stored memorials
+    Note {
+        #[command(subcommand)]
+        action: NoteCommands,
+    },
+
+    /// Add memorials to research projects, remove them, or list projects
+    Project {
+        #[command(subcommand)]
+        action: ProjectCommands,
+    },
+
+    /// Add, remove or list tags on stored memorials
+    Tag {
+        #[command(subcommand)]
+        action: TagCommands,
+    },
+
+    /// List stored revisions of a memorial, show one, or diff two
+    History {
+        /// Memorial ID
+        id: u64,
+
+        /// Show the citation as recorded in this revision
+        #[arg(short, long, conflicts_with = "diff")]
+        revision: Option<i64>,
+
+        /// Compare two revisions (older first)
+        #[arg(long, num_args = 2, value_names = ["FROM", "TO"])]
+        diff: Option<Vec<i64>>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Manage the persistent fetch queue for resumable imports
+    Queue {
+        #[command(subcommand)]
+        action: QueueCommands,
+    },
+
+    /// Render the database as a static HTML site that can be browsed offline
+    Report {
+        /// Directory to write the site into
+        #[arg(short, long, default_value = "report")]
+        output: PathBuf,
+
+        /// Title shown on every page
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// Re-run the parser over stored page snapshots without fetching
+    Reparse {
+        /// Report changes without writing them to the database
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Re-fetch stored memorials that have gone stale and report changes
+    Refresh {
+        /// Refresh memorials not updated for this long (e.g. 30d, 12h, 2w)
+        #[arg(long, default_value = "30d")]
+        older_than: String,
+
+        /// Maximum number of memorials fetched at the same time
+        #[arg(short, long, default_value_t = 4)]
+        concurrency: usize,
+
+        /// Report changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
         /// Output format (text, json)
         #[arg(short, long, default_value = "text")]
         format: String,
@@ -28,8 +338,153 @@ enum Commands {
 
     /// Search for memorials in the local database
     Search {
-        /// Name to search for
-        name: String,
+        /// Name to search for; omit to match every name
+        name: Option<String>,
+
+        /// Only show memorials marked as veterans
+        #[arg(long)]
+        veteran: bool,
+
+        /// Only show famous memorials
+        #[arg(long)]
+        famous: bool,
+
+        /// Only show cenotaphs
+        #[arg(long)]
+        cenotaph: bool,
+
+        /// Only show memorials marked "not buried here"
+        #[arg(long)]
+        not_buried_here: bool,
+
+        /// Only show memorials with this surname or maiden name
+        #[arg(long)]
+        surname: Option<String>,
+
+        /// Only show memorials born in a year range, e.g. 1820-1840
+        #[arg(long, value_name = "YEARS")]
+        born_between: Option<String>,
+
+        /// Only show memorials that died in a year range, e.g. 1861-1865
+        #[arg(long, value_name = "YEARS")]
+        died_between: Option<String>,
+
+        /// Only show memorials whose birth location contains this text
+        #[arg(long, value_name = "PLACE")]
+        born_in: Option<String>,
+
+        /// Only show memorials whose death location contains this text
+        #[arg(long, value_name = "PLACE")]
+        died_in: Option<String>,
+
+        /// Only show memorials whose cemetery contains this text
+        #[arg(long, value_name = "CEMETERY")]
+        buried_in: Option<String>,
+
+        /// Only show memorials with this text in any location
+        #[arg(long, value_name = "PLACE")]
+        location: Option<String>,
+
+        /// Only show memorials whose plot details contain this text
+        #[arg(long)]
+        plot: Option<String>,
+
+        /// Only show memorials with this tag (repeat to require several)
+        #[arg(long)]
+        tag: Vec<String>,
+
+        /// Only show memorials in this research project
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Sort results by name, born, died, id or updated
+        #[arg(long)]
+        sort: Option<String>,
+
+        /// Reverse the sort order
+        #[arg(long, requires = "sort")]
+        descending: bool,
+
+        /// Ranked full-text search over names, places, plots and biographies
+        #[arg(long, requires = "name", conflicts_with_all = [
+            "veteran", "famous", "cenotaph", "not_buried_here", "surname", "born_between",
+            "died_between", "born_in", "died_in", "buried_in", "location", "plot", "tag", "project",
+            "sort", "phonetic", "fuzzy",
+        ])]
+        full_text: bool,
+
+        /// Match names that sound alike, ranked by spelling similarity
+        #[arg(long, requires = "name", conflicts_with_all = [
+            "fuzzy", "full_text", "veteran", "famous", "cenotaph", "not_buried_here",
+            "surname", "born_between", "died_between", "born_in", "died_in", "buried_in", "location",
+            "plot", "tag", "project", "sort",
+        ])]
+        phonetic: bool,
+
+        /// Match names within this many spelling edits, ranked by similarity
+        #[arg(long, value_name = "DISTANCE", requires = "name", conflicts_with_all = [
+            "full_text", "veteran", "famous", "cenotaph", "not_buried_here", "surname", "born_between",
+            "died_between", "born_in", "died_in", "buried_in", "location", "plot", "tag", "project",
+            "sort",
+        ])]
+        fuzzy: Option<usize>,
+
+        /// Match each full-text term as a prefix
+        #[arg(long, requires = "full_text")]
+        prefix: bool,
+
+        /// Maximum number of results
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+
+        /// Number of results to skip, for paging
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Storage backend (sqlite, jsonl)
+        #[arg(short, long, default_value = "sqlite")]
+        backend: String,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Parse saved .html or .mhtml memorial pages, or a directory of them, offline
+    ParseFile {
+        /// HTML or MHTML file, or a directory of them
+        path: PathBuf,
+
+        /// Save results to database
+        #[arg(short, long)]
+        save: bool,
+
+        /// Also keep the page HTML so it can be re-parsed later
+        #[arg(long, requires = "save")]
+        snapshot: bool,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Storage backend (sqlite, jsonl)
+        #[arg(short, long, default_value = "sqlite")]
+        backend: String,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+
+    /// Import memorial captures from .warc or .warc.gz web archives as dated revisions
+    ImportWarc {
+        /// WARC files to import
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
 
         /// Database file path
         #[arg(short, long, default_value = "memorials.db")]
@@ -49,6 +504,218 @@ enum Commands {
         #[arg(short, long, default_value = "memorials.db")]
         database: PathBuf,
 
+        /// Storage backend (sqlite, jsonl)
+        #[arg(short, long, default_value = "sqlite")]
+        backend: String,
+
+        /// Output format (text, json, wikitree, bibtex, biblatex, ris, csl-json, gedcomx)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommands {
+    /// Apply pending schema migrations
+    Migrate {
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// Show the schema version and any pending migrations
+    Status {
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueueCommands {
+    /// Add URLs or memorial IDs to the queue
+    Add {
+        /// FindAGrave URLs or memorial IDs
+        inputs: Vec<String>,
+
+        /// File with one URL or memorial ID per line ("-" for stdin)
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// Fetch queued memorials, resuming any interrupted run
+    ///
+    /// The queue is a table in the SQLite database, and fetched memorials are
+    /// stored alongside it, so there is no --backend option.
+    Run {
+        /// Maximum number of memorials fetched at the same time
+        #[arg(short, long, default_value_t = 4)]
+        concurrency: usize,
+
+        /// Retry failed entries until they have been attempted this many times
+        #[arg(long, default_value_t = 3)]
+        max_attempts: u32,
+
+        /// Also keep the raw page HTML so it can be re-parsed later
+        #[arg(long)]
+        snapshot: bool,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// Show how many queue entries are in each state
+    Status {
+        /// Also list the failed entries with their last error
+        #[arg(long)]
+        failed: bool,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum NoteCommands {
+    /// Add a note to a memorial
+    Add {
+        /// Memorial ID
+        id: u64,
+
+        /// Text of the note
+        text: String,
+
+        /// Author of the note (defaults to the current user)
+        #[arg(short, long)]
+        author: Option<String>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// Delete a note
+    Remove {
+        /// Note ID, as shown by `note list`
+        note_id: i64,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// List notes, oldest first
+    List {
+        /// Only list notes on this memorial
+        id: Option<u64>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProjectCommands {
+    /// Add memorials to a project, creating it if needed
+    Add {
+        /// Project name
+        name: String,
+
+        /// Memorial IDs to add
+        ids: Vec<u64>,
+
+        /// Set the project description
+        #[arg(long)]
+        description: Option<String>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// Remove memorials from a project, or delete the project if no IDs are given
+    Remove {
+        /// Project name
+        name: String,
+
+        /// Memorial IDs to remove
+        ids: Vec<u64>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// List projects with their number of memorials
+    List {
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagCommands {
+    /// Tag a memorial
+    Add {
+        /// Memorial ID
+        id: u64,
+
+        /// Tags to add
+        #[arg(required = true)]
+        tags: Vec<String>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// Remove tags from a memorial
+    Remove {
+        /// Memorial ID
+        id: u64,
+
+        /// Tags to remove
+        #[arg(required = true)]
+        tags: Vec<String>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+    },
+
+    /// List the tags on a memorial, or every tag with its number of memorials
+    List {
+        /// Only list the tags on this memorial
+        id: Option<u64>,
+
+        /// Database file path
+        #[arg(short, long, default_value = "memorials.db")]
+        database: PathBuf,
+
         /// Output format (text, json)
         #[arg(short, long, default_value = "text")]
         format: String,
@@ -63,27 +730,1044 @@ async fn main() -> Result<()> {
         Commands::Fetch {
             url_or_id,
             save,
+            snapshot,
+            database,
+            backend,
             format,
         } => {
-            let config = Config::default();
+            let config = Config {
+                snapshots: *snapshot,
+                ..storage_config(database, backend)?
+            };
 
             let memorial = process_memorial(url_or_id, &config, *save).await?;
+            let accessed = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
             match format.as_str() {
                 "json" => println!("{}", serde_json::to_string_pretty(&memorial)?),
+                "wikitree" => print!("{}", to_wikitree(&memorial, Some(&accessed))),
+                "gedcomx" => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&to_gedcomx(std::slice::from_ref(&memorial)))?
+                ),
+                "bibtex" | "biblatex" | "ris" | "csl-json" => print!(
+                    "{}",
+                    to_bibliography(&[(memorial, Some(accessed))], format.parse()?)?
+                ),
                 _ => println!("{}", memorial.to_citation()),
             }
         }
 
+        Commands::Batch {
+            input,
+            concurrency,
+            unordered,
+            save,
+            snapshot,
+            database,
+            backend,
+            format,
+        } => {
+            let config = Config {
+                snapshots: *snapshot,
+                ..storage_config(database, backend)?
+            };
+
+            let inputs = match input {
+                Some(path) if path.as_os_str() != "-" => {
+                    read_inputs(BufReader::new(File::open(path)?))?
+                }
+                _ => read_inputs(io::stdin().lock())?,
+            };
+            let total = inputs.len();
+
+            let options = BatchOptions {
+                concurrency: *concurrency,
+                order: if *unordered {
+                    ResultOrder::Completion
+                } else {
+                    ResultOrder::Input
+                },
+                store_to_db: *save,
+            };
+
+            let mut summary = BatchSummary::default();
+            let mut results = process_memorials(inputs, &config, &options);
+            while let Some(item) = results.next().await {
+                summary.record(&item);
+                let position = item.index + 1;
+                match (&item.result, format.as_str()) {
+                    (Ok(memorial), "json") => println!("{}", serde_json::to_string(memorial)?),
+                    (Ok(memorial), _) => {
+                        println!(
+                            "[{}/{}] ok {}: {}",
+                            position, total, item.input, memorial.name
+                        )
+                    }
+                    (Err(err), _) => {
+                        eprintln!("[{}/{}] failed {}: {}", position, total, item.input, err)
+                    }
+                }
+            }
+
+            eprintln!(
+                "Processed {} memorials: {} succeeded, {} failed",
+                summary.total, summary.succeeded, summary.failed
+            );
+            if summary.failed > 0 {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Db { action } => match action {
+            DbCommands::Migrate { database } => {
+                let applied = db::migrate(database.to_str().unwrap())?;
+                if applied.is_empty() {
+                    println!("Database schema is already up to date");
+                } else {
+                    for version in &applied {
+                        println!("Applied migration {}", version);
+                    }
+                }
+            }
+
+            DbCommands::Status { database, format } => {
+                let status = db::schema_status(database.to_str().unwrap())?;
+                match format.as_str() {
+                    "json" => println!("{}", serde_json::to_string_pretty(&status)?),
+                    _ => {
+                        println!(
+                            "Schema version {} (latest {})",
+                            status.current_version, status.latest_version
+                        );
+                        for migration in &status.pending {
+                            println!("  pending {}: {}", migration.version, migration.description);
+                        }
+                    }
+                }
+            }
+        },
+
+        Commands::Dedupe {
+            threshold,
+            merge,
+            keep,
+            all,
+            policy,
+            database,
+            format,
+        } => {
+            // Clusters chain transitively, so a bulk merge must be asked for
+            if *merge && keep.is_none() && !*all {
+                return Err(Error::InvalidInput(
+                    "--merge needs --keep <ID> for one cluster or --all for every cluster"
+                        .to_string(),
+                ));
+            }
+            let policy: MergePolicy = policy.parse()?;
+            let mut store = MemorialStore::open(database.to_str().unwrap())?;
+            let options = DedupeOptions {
+                threshold: *threshold,
+            };
+            let clusters: Vec<DuplicateCluster> = find_duplicates(&store, &options)?
+                .into_iter()
+                .filter(|cluster| keep.is_none_or(|id| cluster.ids().contains(&id)))
+                .collect();
+
+            if clusters.is_empty() {
+                match keep {
+                    Some(id) => println!("Memorial {} has no probable duplicates", id),
+                    None => println!("No probable duplicates found"),
+                }
+                return Ok(());
+            }
+
+            let mut merged = Vec::new();
+            if *merge {
+                for cluster in &clusters {
+                    merged.push(merge_cluster(&mut store, cluster, *keep, &policy)?);
+                }
+            }
+
+            match format.as_str() {
+                "json" => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&serde_json::json!({
+                        "clusters": clusters,
+                        "merged": merged,
+                    }))?
+                ),
+                _ => {
+                    for (i, cluster) in clusters.iter().enumerate() {
+                        println!("Cluster {} (similarity {:.2})", i + 1, cluster.score);
+                        print_side_by_side(&cluster.memorials);
+                        if let Some(memorial) = merged.get(i) {
+                            println!("Merged into {}", memorial.id.unwrap_or_default());
+                        }
+                        println!();
+                    }
+                }
+            }
+        }
+
+        Commands::Export {
+            format,
+            path,
+            columns,
+            encoding,
+            name,
+            surname,
+            born_between,
+            died_between,
+            buried_in,
+            tag,
+            project,
+            output,
+            database,
+        } => {
+            let mut query = MemorialQuery::new();
+            if let Some(name) = name {
+                query = query.name(name);
+            }
+            if let Some(surname) = surname {
+                query = query.surname(surname);
+            }
+            if let Some(range) = born_between {
+                let (from, to) = parse_year_range(range)?;
+                query = query.born_between(from, to);
+            }
+            if let Some(range) = died_between {
+                let (from, to) = parse_year_range(range)?;
+                query = query.died_between(from, to);
+            }
+            if let Some(cemetery) = buried_in {
+                query = query.cemetery(cemetery);
+            }
+            for tag in tag {
+                query = query.tag(tag);
+            }
+            if let Some(project) = project {
+                query = query.project(project);
+            }
+
+            if columns.is_some() && !matches!(format.as_str(), "csv" | "tsv") {
+                return Err(Error::InvalidInput(
+                    "--columns only applies to csv and tsv".to_string(),
+                ));
+            }
+
+            let store = MemorialStore::open(database.to_str().unwrap())?;
+            if format == "markdown-vault" {
+                let Some(dir) = path else {
+                    return Err(Error::InvalidInput(
+                        "markdown-vault needs a vault directory".to_string(),
+                    ));
+                };
+                let summary = export_vault(&store, &query, dir)?;
+                eprintln!(
+                    "Vault: {} notes created, {} updated, {} unchanged, {} cemetery notes written",
+                    summary.created, summary.updated, summary.unchanged, summary.cemeteries
+                );
+                return Ok(());
+            }
+            if path.is_some() {
+                return Err(Error::InvalidInput(
+                    "Only markdown-vault takes a directory; use --output for other formats"
+                        .to_string(),
+                ));
+            }
+            let mut out: Box<dyn io::Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout().lock()),
+            };
+            let count = match format.as_str() {
+                "geojson" | "kml" => export_geo(&store, &query, format.parse()?, &mut out)?,
+                "wikitree" => export_wikitree(&store, &query, &mut out)?,
+                "gedcomx" => export_gedcomx(&store, &query, &mut out)?,
+                "bibtex" | "biblatex" | "ris" | "csl-json" => {
+                    export_bibliography(&store, &query, format.parse()?, &mut out)?
+                }
+                _ => {
+                    let options = ExportOptions {
+                        format: format.parse()?,
+                        columns: match columns {
+                            Some(columns) => Column::parse_list(columns)?,
+                            None => Column::ALL.to_vec(),
+                        },
+                        encoding: encoding.clone(),
+                    };
+                    export_table(&store, &query, &options, &mut out)?
+                }
+            };
+            out.flush()?;
+            eprintln!("Exported {} memorials", count);
+        }
+
+        Commands::Import {
+            file,
+            format,
+            encoding,
+            dry_run,
+            rejected,
+            database,
+        } => {
+            let gedcomx = match format {
+                Some(format) => format == "gedcomx",
+                None => file.extension().is_some_and(|ext| ext == "json"),
+            };
+            if gedcomx {
+                let document: serde_json::Value = serde_json::from_slice(&std::fs::read(file)?)?;
+                let mut store = MemorialStore::open(database.to_str().unwrap())?;
+                let import = import_gedcomx(&mut store, &document, *dry_run)?;
+                for person in &import.rejected {
+                    eprintln!(
+                        "person {} ({}): {}",
+                        person.index,
+                        person.id.as_deref().unwrap_or(&person.name),
+                        person.reason
+                    );
+                }
+                eprintln!(
+                    "Imported {} memorials, {} rejected{}",
+                    import.memorials.len(),
+                    import.rejected.len(),
+                    if *dry_run { " (dry run)" } else { "" }
+                );
+                if !import.rejected.is_empty() {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            let format = match format {
+                Some(format) => format.parse()?,
+                None => TableFormat::from_path(file),
+            };
+            let options = ImportOptions {
+                format,
+                encoding: encoding.clone(),
+                dry_run: *dry_run,
+            };
+
+            let mut store = MemorialStore::open(database.to_str().unwrap())?;
+            let report = import_table(&mut store, &std::fs::read(file)?, &options)?;
+            for row in &report.rejected {
+                eprintln!("line {}: {}", row.line, row.reason);
+            }
+            if let Some(path) = rejected {
+                write_rejected(&report, format, File::create(path)?)?;
+            }
+
+            eprintln!(
+                "Imported {} rows, {} rejected{}",
+                report.imported,
+                report.rejected.len(),
+                if *dry_run { " (dry run)" } else { "" }
+            );
+            if !report.rejected.is_empty() {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Lint {
+            max_age,
+            errors_only,
+            database,
+            format,
+        } => {
+            let store = MemorialStore::open(database.to_str().unwrap())?;
+            let options = LintOptions { max_age: *max_age };
+            let mut report = lint_store(&store, &options)?;
+            if *errors_only {
+                report
+                    .findings
+                    .retain(|finding| finding.severity == Severity::Error);
+            }
+
+            match format.as_str() {
+                "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+                _ => {
+                    for finding in &report.findings {
+                        println!("{}", finding);
+                    }
+                    println!(
+                        "Checked {} memorials: {} errors, {} warnings",
+                        report.checked,
+                        report.count(Severity::Error),
+                        report.count(Severity::Warning)
+                    );
+                }
+            }
+            if report.has_errors() {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Note { action } => match action {
+            NoteCommands::Add {
+                id,
+                text,
+                author,
+                database,
+            } => {
+                let author = author
+                    .clone()
+                    .or_else(|| std::env::var("USER").ok())
+                    .or_else(|| std::env::var("USERNAME").ok())
+                    .unwrap_or_else(|| "unknown".to_string());
+                let note = add_note(*id, &author, text, database.to_str().unwrap())?;
+                println!("Added note {} to memorial {}", note.id, note.memorial_id);
+            }
+
+            NoteCommands::Remove { note_id, database } => {
+                if remove_note(*note_id, database.to_str().unwrap())? {
+                    println!("Removed note {}", note_id);
+                } else {
+                    println!("No note {}", note_id);
+                }
+            }
+
+            NoteCommands::List {
+                id,
+                database,
+                format,
+            } => {
+                let notes = list_notes(*id, database.to_str().unwrap())?;
+                match format.as_str() {
+                    "json" => println!("{}", serde_json::to_string_pretty(&notes)?),
+                    _ if notes.is_empty() => println!("No notes found"),
+                    _ => {
+                        for note in &notes {
+                            println!(
+                                "#{} memorial {} by {} at {}\n  {}",
+                                note.id, note.memorial_id, note.author, note.created_at, note.body
+                            );
+                        }
+                    }
+                }
+            }
+        },
+
+        Commands::Project { action } => match action {
+            ProjectCommands::Add {
+                name,
+                ids,
+                description,
+                database,
+            } => {
+                let added = add_to_project(
+                    name,
+                    ids,
+                    description.as_deref(),
+                    database.to_str().unwrap(),
+                )?;
+                println!("Added {} memorials to project '{}'", added, name);
+            }
+
+            ProjectCommands::Remove {
+                name,
+                ids,
+                database,
+            } => {
+                let db_path = database.to_str().unwrap();
+                if ids.is_empty() {
+                    if delete_project(name, db_path)? {
+                        println!("Deleted project '{}'", name);
+                    } else {
+                        println!("No project named '{}'", name);
+                    }
+                } else {
+                    let removed = remove_from_project(name, ids, db_path)?;
+                    println!("Removed {} memorials from project '{}'", removed, name);
+                }
+            }
+
+            ProjectCommands::List { database, format } => {
+                let projects = list_projects(database.to_str().unwrap())?;
+                match format.as_str() {
+                    "json" => println!("{}", serde_json::to_string_pretty(&projects)?),
+                    _ if projects.is_empty() => println!("No projects found"),
+                    _ => {
+                        for project in &projects {
+                            println!("{} ({} memorials)", project.name, project.memorial_count);
+                            if let Some(description) = &project.description {
+                                println!("  {}", description);
+                            }
+                        }
+                    }
+                }
+            }
+        },
+
+        Commands::Tag { action } => match action {
+            TagCommands::Add { id, tags, database } => {
+                let added = add_tags(*id, tags, database.to_str().unwrap())?;
+                println!("Added {} tags to memorial {}", added, id);
+            }
+
+            TagCommands::Remove { id, tags, database } => {
+                let removed = remove_tags(*id, tags, database.to_str().unwrap())?;
+                println!("Removed {} tags from memorial {}", removed, id);
+            }
+
+            TagCommands::List {
+                id: Some(id),
+                database,
+                format,
+            } => {
+                let tags = memorial_tags(*id, database.to_str().unwrap())?;
+                match format.as_str() {
+                    "json" => println!("{}", serde_json::to_string_pretty(&tags)?),
+                    _ if tags.is_empty() => println!("Memorial {} has no tags", id),
+                    _ => println!("{}", tags.join(", ")),
+                }
+            }
+
+            TagCommands::List {
+                id: None,
+                database,
+                format,
+            } => {
+                let tags = list_tags(database.to_str().unwrap())?;
+                match format.as_str() {
+                    "json" => println!("{}", serde_json::to_string_pretty(&tags)?),
+                    _ if tags.is_empty() => println!("No tags found"),
+                    _ => {
+                        for tag in &tags {
+                            println!("{} ({})", tag.tag, tag.count);
+                        }
+                    }
+                }
+            }
+        },
+
+        Commands::History {
+            id,
+            revision,
+            diff,
+            database,
+            format,
+        } => {
+            let db_path = database.to_str().unwrap();
+
+            if let Some(revision_id) = revision {
+                let revision = get_revision(*revision_id, db_path)?
+                    .filter(|revision| revision.memorial_id == *id);
+                match revision {
+                    Some(r) => match format.as_str() {
+                        "json" => println!("{}", serde_json::to_string_pretty(&r)?),
+                        _ => println!("{}\nAccessed: {}", r.memorial.to_citation(), r.fetched_at),
+                    },
+                    None => println!("No revision {} for memorial {}", revision_id, id),
+                }
+            } else if let Some(ids) = diff {
+                let changes = diff_revisions(*id, ids[0], ids[1], db_path)?;
+                match format.as_str() {
+                    "json" => println!("{}", serde_json::to_string_pretty(&changes)?),
+                    _ if changes.is_empty() => {
+                        println!("Revisions {} and {} are identical", ids[0], ids[1])
+                    }
+                    _ => {
+                        for change in &changes {
+                            println!("{}", change);
+                        }
+                    }
+                }
+            } else {
+                let revisions = list_revisions(*id, db_path)?;
+                if revisions.is_empty() {
+                    println!("No revisions found for memorial {}", id);
+                    return Ok(());
+                }
+
+                match format.as_str() {
+                    "json" => println!("{}", serde_json::to_string_pretty(&revisions)?),
+                    _ => {
+                        for r in &revisions {
+                            println!("{}\t{}\t{}", r.revision_id, r.fetched_at, r.memorial.name);
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::Queue { action } => match action {
+            QueueCommands::Add {
+                inputs,
+                file,
+                database,
+            } => {
+                let mut inputs = inputs.clone();
+                match file {
+                    Some(path) if path.as_os_str() == "-" => {
+                        inputs.extend(read_inputs(io::stdin().lock())?)
+                    }
+                    Some(path) => inputs.extend(read_inputs(BufReader::new(File::open(path)?))?),
+                    None => {}
+                }
+
+                let added = enqueue(&inputs, database.to_str().unwrap())?;
+                println!(
+                    "Queued {} new memorials ({} already queued)",
+                    added,
+                    inputs.len() - added
+                );
+            }
+
+            QueueCommands::Run {
+                concurrency,
+                max_attempts,
+                snapshot,
+                database,
+            } => {
+                let config = Config {
+                    db_path: database.to_str().unwrap().to_string(),
+                    snapshots: *snapshot,
+                    ..Config::default()
+                };
+                let options = QueueRunOptions {
+                    concurrency: *concurrency,
+                    max_attempts: *max_attempts,
+                };
+
+                let summary = run_queue(&config, &options, |entry, result| match result {
+                    Ok(memorial) => println!("ok {}: {}", entry.input, memorial.name),
+                    Err(err) => eprintln!(
+                        "failed {} (attempt {}): {}",
+                        entry.input, entry.attempts, err
+                    ),
+                })
+                .await?;
+
+                eprintln!(
+                    "Processed {} queued memorials: {} succeeded, {} failed",
+                    summary.total, summary.succeeded, summary.failed
+                );
+            }
+
+            QueueCommands::Status {
+                failed,
+                database,
+                format,
+            } => {
+                let db_path = database.to_str().unwrap();
+                let status = queue_status(db_path)?;
+                let failed_entries = if *failed {
+                    list_entries(Some(QueueState::Failed), db_path)?
+                } else {
+                    Vec::new()
+                };
+
+                match format.as_str() {
+                    "json" => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&serde_json::json!({
+                            "status": status,
+                            "failed_entries": failed_entries,
+                        }))?
+                    ),
+                    _ => {
+                        println!("Pending:   {}", status.pending);
+                        println!("In flight: {}", status.in_flight);
+                        println!("Done:      {}", status.done);
+                        println!("Failed:    {}", status.failed);
+                        for entry in &failed_entries {
+                            println!(
+                                "\n{} (attempts: {}): {}",
+                                entry.input,
+                                entry.attempts,
+                                entry.last_error.as_deref().unwrap_or("unknown error")
+                            );
+                        }
+                    }
+                }
+            }
+        },
+
+        Commands::ParseFile {
+            path,
+            save,
+            snapshot,
+            database,
+            backend,
+            format,
+        } => {
+            let config = Config {
+                snapshots: *snapshot,
+                ..storage_config(database, backend)?
+            };
+
+            let items = parse_files(path, &config, *save)?;
+            let (mut succeeded, mut failed) = (0, 0);
+            for item in &items {
+                let path = item.path.display();
+                match (&item.result, format.as_str()) {
+                    (Ok(memorial), "json") => {
+                        succeeded += 1;
+                        println!(
+                            "{}",
+                            serde_json::json!({ "path": path.to_string(), "memorial": memorial })
+                        )
+                    }
+                    (Ok(memorial), _) => {
+                        succeeded += 1;
+                        println!("ok {}: {}", path, memorial.name)
+                    }
+                    (Err(err), _) => {
+                        failed += 1;
+                        eprintln!("failed {}: {}", path, err)
+                    }
+                }
+            }
+
+            eprintln!(
+                "Parsed {} files: {} succeeded, {} failed",
+                items.len(),
+                succeeded,
+                failed
+            );
+            if failed > 0 {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::ImportWarc {
+            files,
+            database,
+            format,
+        } => {
+            let json = format == "json";
+            let mut total = WarcImportSummary::default();
+            for file in files {
+                let summary = import_warc(file, database.to_str().unwrap(), |report| {
+                    if json {
+                        match serde_json::to_string(report) {
+                            Ok(line) => println!("{}", line),
+                            Err(err) => eprintln!("{}", err),
+                        }
+                        return;
+                    }
+
+                    let name = report.name.as_deref().unwrap_or("");
+                    let captured_at = report.captured_at.as_deref().unwrap_or("");
+                    let memorial_id = report.memorial_id.unwrap_or_default();
+                    match (&report.error, report.memorial_id) {
+                        (Some(err), None) => eprintln!("failed {}: {}", report.url, err),
+                        (Some(err), Some(_)) => eprintln!(
+                            "failed {} {} ({}): {}",
+                            captured_at, memorial_id, report.url, err
+                        ),
+                        (None, _) if report.stored => {
+                            println!("{} {} {}: stored", captured_at, memorial_id, name)
+                        }
+                        (None, _) => {
+                            println!("{} {} {}: already imported", captured_at, memorial_id, name)
+                        }
+                    }
+                })?;
+                total.captures += summary.captures;
+                total.stored += summary.stored;
+                total.duplicates += summary.duplicates;
+                total.failed += summary.failed;
+            }
+
+            eprintln!(
+                "Found {} memorial captures: {} stored, {} already imported, {} failed",
+                total.captures, total.stored, total.duplicates, total.failed
+            );
+            if total.failed > 0 {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Report {
+            output,
+            title,
+            database,
+        } => {
+            let mut options = ReportOptions::default();
+            if let Some(title) = title {
+                options.title = title.clone();
+            }
+
+            let store = MemorialStore::open(database.to_str().unwrap())?;
+            let summary = write_report(&store, output, &options)?;
+            eprintln!(
+                "Wrote {} memorials, {} surnames and {} cemeteries to {}",
+                summary.memorials,
+                summary.surnames,
+                summary.cemeteries,
+                output.join("index.html").display()
+            );
+        }
+
+        Commands::Reparse {
+            dry_run,
+            database,
+            format,
+        } => {
+            let options = ReparseOptions { dry_run: *dry_run };
+
+            let json = format == "json";
+            let summary = reparse_snapshots(database.to_str().unwrap(), &options, |report| {
+                if json {
+                    match serde_json::to_string(report) {
+                        Ok(line) => println!("{}", line),
+                        Err(err) => eprintln!("{}", err),
+                    }
+                    return;
+                }
+
+                match (&report.error, report.changes.len()) {
+                    (Some(err), _) => {
+                        eprintln!("{} {}: failed: {}", report.memorial_id, report.name, err)
+                    }
+                    (None, 0) => println!("{} {}: unchanged", report.memorial_id, report.name),
+                    (None, count) => {
+                        println!("{} {}: {} changed", report.memorial_id, report.name, count);
+                        for change in &report.changes {
+                            println!("  {}", change);
+                        }
+                    }
+                }
+            })?;
+
+            eprintln!(
+                "Re-parsed {} snapshots: {} changed, {} failed{}",
+                summary.reparsed,
+                summary.changed,
+                summary.failed,
+                if *dry_run { " (dry run)" } else { "" }
+            );
+        }
+
+        Commands::Refresh {
+            older_than,
+            concurrency,
+            dry_run,
+            database,
+            format,
+        } => {
+            let config = Config {
+                db_path: database.to_str().unwrap().to_string(),
+                ..Config::default()
+            };
+            let options = RefreshOptions {
+                max_age: parse_age(older_than)?,
+                concurrency: *concurrency,
+                dry_run: *dry_run,
+            };
+
+            let json = format == "json";
+            let summary = refresh_memorials(&config, &options, |report| {
+                if json {
+                    match serde_json::to_string(report) {
+                        Ok(line) => println!("{}", line),
+                        Err(err) => eprintln!("{}", err),
+                    }
+                    return;
+                }
+
+                match (&report.error, report.changes.len()) {
+                    (Some(err), _) => {
+                        eprintln!("{} {}: failed: {}", report.memorial_id, report.name, err)
+                    }
+                    (None, 0) => println!("{} {}: unchanged", report.memorial_id, report.name),
+                    (None, count) => {
+                        println!("{} {}: {} changed", report.memorial_id, report.name, count);
+                        for change in &report.changes {
+                            println!("  {}", change);
+                        }
+                    }
+                }
+            })
+            .await?;
+
+            eprintln!(
+                "Checked {} memorials: {} changed, {} failed{}",
+                summary.checked,
+                summary.changed,
+                summary.failed,
+                if *dry_run { " (dry run)" } else { "" }
+            );
+        }
+
         Commands::Search {
             name,
+            veteran,
+            famous,
+            cenotaph,
+            not_buried_here,
+            surname,
+            born_between,
+            died_between,
+            born_in,
+            died_in,
+            buried_in,
+            location,
+            plot,
+            tag,
+            project,
+            sort,
+            descending,
+            phonetic,
+            fuzzy,
+            full_text,
+            prefix,
+            limit,
+            offset,
             database,
+            backend,
             format,
         } => {
-            let memorials = search_memorials_by_name(name, database.to_str().unwrap())?;
+            let name = name.as_deref().unwrap_or_default();
+
+            if *full_text {
+                let query = FullTextQuery {
+                    terms: name.to_string(),
+                    prefix: *prefix,
+                    limit: *limit,
+                    offset: *offset,
+                };
+                let hits = full_text_search(&query, database.to_str().unwrap())?;
+
+                if hits.is_empty() {
+                    println!("No memorials found matching '{}'", name);
+                    return Ok(());
+                }
+
+                match format.as_str() {
+                    "json" => println!("{}", serde_json::to_string_pretty(&hits)?),
+                    _ => {
+                        println!("Found {} memorials:", hits.len());
+                        for (i, hit) in hits.iter().enumerate() {
+                            println!(
+                                "\n--- Memorial {} (score {:.2}) ---",
+                                offset + i + 1,
+                                hit.score
+                            );
+                            println!("{}", hit.memorial.to_citation());
+                            println!("Match: {}", hit.snippet);
+                        }
+                    }
+                }
+                return Ok(());
+            }
+
+            let mode = match fuzzy {
+                Some(distance) => Some(NameMatch::Fuzzy(*distance)),
+                None => phonetic.then_some(NameMatch::Phonetic),
+            };
+            if let Some(mode) = mode {
+                if backend.parse::<StorageBackend>()? != StorageBackend::Sqlite {
+                    return Err(Error::InvalidInput(
+                        "Phonetic and fuzzy search require the sqlite backend".to_string(),
+                    ));
+                }
+                let query = NameQuery {
+                    name: name.to_string(),
+                    mode,
+                    limit: limit + offset,
+                };
+                let hits: Vec<_> = name_search(&query, database.to_str().unwrap())?
+                    .into_iter()
+                    .skip(*offset)
+                    .collect();
+
+                if hits.is_empty() {
+                    println!("No memorials found matching '{}'", name);
+                    return Ok(());
+                }
+
+                match format.as_str() {
+                    "json" => println!("{}", serde_json::to_string_pretty(&hits)?),
+                    _ => {
+                        println!("Found {} memorials:", hits.len());
+                        for (i, hit) in hits.iter().enumerate() {
+                            println!(
+                                "\n--- Memorial {} (similarity {:.2}) ---",
+                                offset + i + 1,
+                                hit.score
+                            );
+                            println!("{}", hit.memorial.to_citation());
+                        }
+                    }
+                }
+                return Ok(());
+            }
+
+            let filters = SearchFilters {
+                veteran: veteran.then_some(true),
+                famous: famous.then_some(true),
+                cenotaph: cenotaph.then_some(true),
+                not_buried_here: not_buried_here.then_some(true),
+            };
+            let mut query = MemorialQuery::new()
+                .name(name)
+                .flags(filters)
+                .limit(*limit)
+                .offset(*offset);
+            if let Some(surname) = surname {
+                query = query.surname(surname);
+            }
+            if let Some(years) = born_between {
+                let (from, to) = parse_year_range(years)?;
+                query = query.born_between(from, to);
+            }
+            if let Some(years) = died_between {
+                let (from, to) = parse_year_range(years)?;
+                query = query.died_between(from, to);
+            }
+            if let Some(place) = born_in {
+                query = query.born_in(place);
+            }
+            if let Some(place) = died_in {
+                query = query.died_in(place);
+            }
+            if let Some(cemetery) = buried_in {
+                query = query.cemetery(cemetery);
+            }
+            if let Some(place) = location {
+                query = query.location(place);
+            }
+            if let Some(plot) = plot {
+                query = query.plot(plot);
+            }
+            for tag in tag {
+                query = query.tag(tag);
+            }
+            if let Some(project) = project {
+                query = query.project(project);
+            }
+            if let Some(sort) = sort {
+                query = query.sort_by(sort.parse::<SortField>()?, *descending);
+            }
+
+            let config = storage_config(database, backend)?;
+            let memorials = if !database.exists() {
+                Vec::new()
+            } else if config.backend == StorageBackend::Sqlite {
+                query_memorials(&query, &config.db_path)?
+            } else if query.has_structured_filters() || sort.is_some() {
+                return Err(Error::InvalidInput(
+                    "Surname, date, place, tag, project and sort filters require the sqlite backend".to_string(),
+                ));
+            } else {
+                let (name, filters) = query.name_and_flags();
+                config
+                    .open_repository()?
+                    .search(name, filters)?
+                    .into_iter()
+                    .skip(*offset)
+                    .take(*limit)
+                    .collect()
+            };
 
             if memorials.is_empty() {
-                println!("No memorials found matching '{}'", name);
+                if name.is_empty() {
+                    println!("No memorials found");
+                } else {
+                    println!("No memorials found matching '{}'", name);
+                }
                 return Ok(());
             }
 
@@ -92,7 +1776,7 @@ async fn main() -> Result<()> {
                 _ => {
                     println!("Found {} memorials:", memorials.len());
                     for (i, memorial) in memorials.iter().enumerate() {
-                        println!("\n--- Memorial {} ---", i + 1);
+                        println!("\n--- Memorial {} ---", offset + i + 1);
                         println!("{}", memorial.to_citation());
                     }
                 }
@@ -102,13 +1786,32 @@ async fn main() -> Result<()> {
         Commands::Get {
             id,
             database,
+            backend,
             format,
         } => {
-            let memorial = get_memorial_by_id(*id, database.to_str().unwrap())?;
+            let memorial = if database.exists() {
+                storage_config(database, backend)?
+                    .open_repository()?
+                    .get(*id)?
+            } else {
+                None
+            };
 
             match memorial {
                 Some(m) => match format.as_str() {
                     "json" => println!("{}", serde_json::to_string_pretty(&m)?),
+                    "wikitree" => {
+                        let accessed = last_accessed(*id, database, backend)?;
+                        print!("{}", to_wikitree(&m, accessed.as_deref()))
+                    }
+                    "gedcomx" => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&to_gedcomx(std::slice::from_ref(&m)))?
+                    ),
+                    "bibtex" | "biblatex" | "ris" | "csl-json" => {
+                        let accessed = last_accessed(*id, database, backend)?;
+                        print!("{}", to_bibliography(&[(m, accessed)], format.parse()?)?)
+                    }
                     _ => println!("{}", m.to_citation()),
                 },
                 None => println!("No memorial found with ID {}", id),
@@ -118,3 +1821,58 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Print memorials in columns, one row per field, marking rows that differ
+fn print_side_by_side(memorials: &[Memorial]) {
+    const WIDTH: usize = 28;
+    let cell = |value: &str| {
+        if value.chars().count() > WIDTH {
+            let cut: String = value.chars().take(WIDTH - 1).collect();
+            format!("{:<width$}", format!("{}…", cut), width = WIDTH)
+        } else {
+            format!("{:<width$}", value, width = WIDTH)
+        }
+    };
+
+    let columns: Vec<_> = memorials.iter().map(Memorial::fields).collect();
+    for (row, (field, _)) in columns[0].iter().enumerate() {
+        let values: Vec<&str> = columns
+            .iter()
+            .map(|fields| fields[row].1.as_deref().unwrap_or(""))
+            .collect();
+        let marker = if values.iter().all(|v| *v == values[0]) {
+            ' '
+        } else {
+            '*'
+        };
+        let cells: Vec<String> = values.iter().map(|v| cell(v)).collect();
+        println!(
+            "{} {:<16} | {}",
+            marker,
+            field,
+            cells.join(" | ").trim_end()
+        );
+    }
+}
+
+/// When the stored copy of a memorial was last fetched, to cite as its access date
+///
+/// Only the SQLite backend keeps revisions, so other backends have no date.
+fn last_accessed(id: u64, database: &Path, backend: &str) -> Result<Option<String>> {
+    if backend.parse::<StorageBackend>()? != StorageBackend::Sqlite {
+        return Ok(None);
+    }
+    if !database.exists() {
+        return Ok(None);
+    }
+    MemorialStore::open(database.to_str().unwrap())?.last_fetched(id)
+}
+
+/// Build a configuration that stores memorials in `database` using `backend`
+fn storage_config(database: &Path, backend: &str) -> Result<Config> {
+    Ok(Config {
+        db_path: database.to_str().unwrap().to_string(),
+        backend: backend.parse()?,
+        ..Config::default()
+    })
+}
@@ -0,0 +1,700 @@
+use crate::db::write_name_keys;
+use crate::models::year_of;
+use crate::{Error, PersonName, Result};
+use rusqlite::{Connection, Transaction};
+use serde::Serialize;
+
+/// A single ordered schema change
+struct Migration {
+    /// Schema version the database is at after this migration
+    version: u32,
+    /// Short human-readable description
+    description: &'static str,
+    /// Apply the change inside the migration transaction
+    apply: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// All schema migrations, in the order they are applied
+///
+/// Databases created before versioning was introduced report
+/// `user_version = 0` but may already contain some of these changes, so every
+/// migration must be safe to run against such a database.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create memorials table",
+        apply: create_memorials,
+    },
+    Migration {
+        version: 2,
+        description: "add memorial flags and metadata",
+        apply: add_flags_and_metadata,
+    },
+    Migration {
+        version: 3,
+        description: "create fetch queue",
+        apply: create_fetch_queue,
+    },
+    Migration {
+        version: 4,
+        description: "add memorial check time",
+        apply: add_checked_at,
+    },
+    Migration {
+        version: 5,
+        description: "create memorial revisions",
+        apply: create_revisions,
+    },
+    Migration {
+        version: 6,
+        description: "add memorial biography",
+        apply: add_bio,
+    },
+    Migration {
+        version: 7,
+        description: "create full-text search index",
+        apply: create_full_text_index,
+    },
+    Migration {
+        version: 8,
+        description: "add birth and death years",
+        apply: add_years,
+    },
+    Migration {
+        version: 9,
+        description: "create name keys for phonetic search",
+        apply: create_name_keys,
+    },
+    Migration {
+        version: 10,
+        description: "add personal name parts",
+        apply: add_name_parts,
+    },
+    Migration {
+        version: 11,
+        description: "record merged duplicate memorials",
+        apply: create_merged_memorials,
+    },
+    Migration {
+        version: 12,
+        description: "add research tags, notes and projects",
+        apply: create_research_tables,
+    },
+    Migration {
+        version: 13,
+        description: "store raw HTML snapshots of fetched pages",
+        apply: create_snapshots,
+    },
+    Migration {
+        version: 14,
+        description: "add plot and cemetery coordinates",
+        apply: add_coordinates,
+    },
+];
+
+/// A migration that has not been applied yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingMigration {
+    /// Schema version the migration upgrades to
+    pub version: u32,
+    /// Short description of the change
+    pub description: &'static str,
+}
+
+/// Schema version of a database compared with the latest known version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaStatus {
+    /// Version recorded in the database (`PRAGMA user_version`)
+    pub current_version: u32,
+    /// Latest version this build knows how to create
+    pub latest_version: u32,
+    /// Migrations that would be applied by `migrate`
+    pub pending: Vec<PendingMigration>,
+}
+
+/// Latest schema version known to this build
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// Report the schema version of an open database
+pub fn schema_status(conn: &Connection) -> Result<SchemaStatus> {
+    let current_version = user_version(conn)?;
+    Ok(SchemaStatus {
+        current_version,
+        latest_version: latest_version(),
+        pending: MIGRATIONS
+            .iter()
+            .filter(|m| m.version > current_version)
+            .map(|m| PendingMigration {
+                version: m.version,
+                description: m.description,
+            })
+            .collect(),
+    })
+}
+
+/// Apply all pending migrations to an open database
+///
+/// Each migration runs in its own transaction together with the
+/// `user_version` bump, so an interrupted upgrade leaves the database at the
+/// last fully applied version. Returns the versions that were applied.
+pub fn migrate(conn: &mut Connection) -> Result<Vec<u32>> {
+    migrate_to(conn, latest_version())
+}
+
+/// Apply pending migrations up to and including `target` version
+pub(crate) fn migrate_to(conn: &mut Connection, target: u32) -> Result<Vec<u32>> {
+    let current = user_version(conn)?;
+    if current > latest_version() {
+        return Err(Error::DatabaseError(format!(
+            "Database schema version {} is newer than the latest supported version {}",
+            current,
+            latest_version()
+        )));
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|m| m.version > current && m.version <= target)
+    {
+        let tx = conn
+            .transaction()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        (migration.apply)(&tx).map_err(|e| {
+            Error::DatabaseError(format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        tx.commit()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        applied.push(migration.version);
+    }
+
+    Ok(applied)
+}
+
+/// Read `PRAGMA user_version`
+fn user_version(conn: &Connection) -> Result<u32> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| Error::DatabaseError(e.to_string()))
+}
+
+/// Add a column unless an unversioned database already has it
+fn add_column(
+    tx: &Transaction,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> rusqlite::Result<()> {
+    let exists: bool = tx.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        tx.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))?;
+    }
+    Ok(())
+}
+
+fn create_memorials(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS memorials (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            birth_date TEXT,
+            birth_location TEXT,
+            death_date TEXT,
+            death_location TEXT,
+            burial_location TEXT,
+            plot_details TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_memorial_name ON memorials(name);
+        CREATE INDEX IF NOT EXISTS idx_memorial_dates ON memorials(birth_date, death_date);
+    ",
+    )
+}
+
+fn add_flags_and_metadata(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column(tx, "memorials", "is_veteran", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(tx, "memorials", "is_famous", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(tx, "memorials", "is_cenotaph", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(
+        tx,
+        "memorials",
+        "not_buried_here",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column(tx, "memorials", "created_by", "TEXT")?;
+    add_column(tx, "memorials", "maintained_by", "TEXT")?;
+    add_column(tx, "memorials", "created_date", "TEXT")?;
+    add_column(tx, "memorials", "sponsored_by", "TEXT")
+}
+
+fn create_fetch_queue(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS fetch_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            input TEXT NOT NULL UNIQUE,
+            state TEXT NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            memorial_id INTEGER,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_fetch_queue_state ON fetch_queue(state);
+    ",
+    )
+}
+
+fn add_checked_at(tx: &Transaction) -> rusqlite::Result<()> {
+    // SQLite cannot add a column with a CURRENT_TIMESTAMP default, so rows
+    // that were never refreshed fall back to `updated_at`
+    add_column(tx, "memorials", "checked_at", "DATETIME")
+}
+
+fn create_revisions(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS memorial_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            memorial_id INTEGER NOT NULL,
+            fetched_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            data TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_revision_memorial
+            ON memorial_revisions(memorial_id, fetched_at);
+    ",
+    )
+}
+
+fn add_bio(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column(tx, "memorials", "bio", "TEXT")
+}
+
+fn create_full_text_index(tx: &Transaction) -> rusqlite::Result<()> {
+    // The index keeps its own copy of the text so snippets can be generated;
+    // triggers keep it in step with the memorials table
+    tx.execute_batch(
+        "
+        CREATE VIRTUAL TABLE IF NOT EXISTS memorials_fts USING fts5(
+            name, places, plot, bio,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS memorials_fts_insert AFTER INSERT ON memorials BEGIN
+            INSERT INTO memorials_fts (rowid, name, places, plot, bio) VALUES (
+                new.id,
+                new.name,
+                trim(COALESCE(new.birth_location, '') || ' ' ||
+                     COALESCE(new.death_location, '') || ' ' ||
+                     COALESCE(new.burial_location, '')),
+                new.plot_details,
+                new.bio
+            );
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS memorials_fts_delete AFTER DELETE ON memorials BEGIN
+            DELETE FROM memorials_fts WHERE rowid = old.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS memorials_fts_update AFTER UPDATE OF
+            id, name, birth_location, death_location, burial_location, plot_details, bio
+            ON memorials
+        BEGIN
+            DELETE FROM memorials_fts WHERE rowid = old.id;
+            INSERT INTO memorials_fts (rowid, name, places, plot, bio) VALUES (
+                new.id,
+                new.name,
+                trim(COALESCE(new.birth_location, '') || ' ' ||
+                     COALESCE(new.death_location, '') || ' ' ||
+                     COALESCE(new.burial_location, '')),
+                new.plot_details,
+                new.bio
+            );
+        END;
+
+        DELETE FROM memorials_fts;
+        INSERT INTO memorials_fts (rowid, name, places, plot, bio)
+            SELECT id, name,
+                trim(COALESCE(birth_location, '') || ' ' ||
+                     COALESCE(death_location, '') || ' ' ||
+                     COALESCE(burial_location, '')),
+                plot_details, bio
+            FROM memorials;
+    ",
+    )
+}
+
+fn add_years(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column(tx, "memorials", "birth_year", "INTEGER")?;
+    add_column(tx, "memorials", "death_year", "INTEGER")?;
+    tx.execute_batch(
+        "
+        CREATE INDEX IF NOT EXISTS idx_memorial_birth_year ON memorials(birth_year);
+        CREATE INDEX IF NOT EXISTS idx_memorial_death_year ON memorials(death_year);
+    ",
+    )?;
+
+    // Dates are free text, so the years are extracted here rather than in SQL
+    let mut select = tx.prepare("SELECT id, birth_date, death_date FROM memorials")?;
+    let rows = select
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut update =
+        tx.prepare("UPDATE memorials SET birth_year = ?2, death_year = ?3 WHERE id = ?1")?;
+    for (id, birth_date, death_date) in rows {
+        update.execute(rusqlite::params![
+            id,
+            birth_date.as_deref().and_then(year_of),
+            death_date.as_deref().and_then(year_of)
+        ])?;
+    }
+    Ok(())
+}
+
+fn create_name_keys(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS name_keys (
+            memorial_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            part TEXT NOT NULL,
+            normalized TEXT NOT NULL,
+            soundex TEXT NOT NULL,
+            metaphone TEXT NOT NULL,
+            metaphone_alt TEXT NOT NULL,
+            PRIMARY KEY (memorial_id, position)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_name_keys_soundex ON name_keys(soundex);
+        CREATE INDEX IF NOT EXISTS idx_name_keys_metaphone ON name_keys(metaphone);
+        CREATE INDEX IF NOT EXISTS idx_name_keys_metaphone_alt ON name_keys(metaphone_alt);
+    ",
+    )?;
+
+    let mut select = tx.prepare("SELECT id, name FROM memorials")?;
+    let rows = select
+        .query_map([], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (id, name) in rows {
+        write_name_keys(tx, id, &PersonName::parse(&name))?;
+    }
+    Ok(())
+}
+
+fn add_name_parts(tx: &Transaction) -> rusqlite::Result<()> {
+    for column in [
+        "name_prefix",
+        "given_names",
+        "nickname",
+        "surname",
+        "maiden_name",
+        "name_suffix",
+    ] {
+        add_column(tx, "memorials", column, "TEXT")?;
+    }
+    tx.execute_batch(
+        "
+        CREATE INDEX IF NOT EXISTS idx_memorial_surname
+            ON memorials(surname COLLATE NOCASE);
+        CREATE INDEX IF NOT EXISTS idx_memorial_maiden_name
+            ON memorials(maiden_name COLLATE NOCASE);
+    ",
+    )?;
+
+    // Stored names have no markup left, so maiden names stay among the given names
+    let mut select = tx.prepare("SELECT id, name FROM memorials")?;
+    let rows = select
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut update = tx.prepare(
+        "UPDATE memorials SET name_prefix = ?2, given_names = ?3, nickname = ?4,
+             surname = ?5, name_suffix = ?6
+         WHERE id = ?1",
+    )?;
+    for (id, name) in rows {
+        let parts = PersonName::parse(&name);
+        update.execute(rusqlite::params![
+            id,
+            parts.prefix,
+            Some(parts.given_names()).filter(|given| !given.is_empty()),
+            parts.nickname,
+            parts.surname,
+            parts.suffix
+        ])?;
+    }
+    Ok(())
+}
+
+fn create_merged_memorials(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS merged_memorials (
+            merged_id INTEGER PRIMARY KEY,
+            into_id INTEGER NOT NULL,
+            merged_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_merged_into ON merged_memorials(into_id);
+    ",
+    )
+}
+
+fn create_research_tables(tx: &Transaction) -> rusqlite::Result<()> {
+    // Annotations belong to the memorial and are removed along with it
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS tags (
+            memorial_id INTEGER NOT NULL REFERENCES memorials(id) ON DELETE CASCADE,
+            tag TEXT NOT NULL COLLATE NOCASE,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (memorial_id, tag)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_tags_tag ON tags(tag);
+
+        CREATE TABLE IF NOT EXISTS notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            memorial_id INTEGER NOT NULL REFERENCES memorials(id) ON DELETE CASCADE,
+            author TEXT NOT NULL,
+            body TEXT NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_notes_memorial ON notes(memorial_id, created_at);
+
+        CREATE TABLE IF NOT EXISTS projects (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            description TEXT,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE TABLE IF NOT EXISTS project_memorials (
+            project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+            memorial_id INTEGER NOT NULL REFERENCES memorials(id) ON DELETE CASCADE,
+            added_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (project_id, memorial_id)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_project_memorials_memorial
+            ON project_memorials(memorial_id);
+    ",
+    )
+}
+
+fn create_snapshots(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS snapshots (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            memorial_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            content_hash TEXT NOT NULL,
+            html BLOB NOT NULL,
+            fetched_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_snapshot_memorial
+            ON snapshots(memorial_id, fetched_at);
+    ",
+    )
+}
+
+fn add_coordinates(tx: &Transaction) -> rusqlite::Result<()> {
+    for column in [
+        "plot_latitude",
+        "plot_longitude",
+        "cemetery_latitude",
+        "cemetery_longitude",
+    ] {
+        add_column(tx, "memorials", column, "REAL")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        full_text_search, get_memorial_by_id, name_search, query_memorials, store_in_db,
+        FullTextQuery, NameQuery,
+    };
+    use crate::{Memorial, MemorialQuery};
+    use tempfile::tempdir;
+
+    /// Schema written by releases before migrations existed
+    const LEGACY_SCHEMA: &str = "
+        CREATE TABLE memorials (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            birth_date TEXT,
+            birth_location TEXT,
+            death_date TEXT,
+            death_location TEXT,
+            burial_location TEXT,
+            plot_details TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE INDEX idx_memorial_name ON memorials(name);
+        CREATE INDEX idx_memorial_dates ON memorials(birth_date, death_date);
+        INSERT INTO memorials (id, name, death_date) VALUES (42, 'Legacy Person', '1865');
+    ";
+
+    /// Migrate a database, then check it holds the legacy row and accepts new data
+    fn assert_upgrades_cleanly(db_path: &str) {
+        crate::db::migrate(db_path).unwrap();
+
+        let conn = Connection::open(db_path).unwrap();
+        let status = schema_status(&conn).unwrap();
+        assert_eq!(status.current_version, latest_version());
+        assert!(status.pending.is_empty());
+        drop(conn);
+
+        let legacy = get_memorial_by_id(42, db_path).unwrap().unwrap();
+        assert_eq!(legacy.name, "Legacy Person");
+
+        // Rows that predate the full-text index are backfilled into it
+        let query = FullTextQuery {
+            terms: "legacy".to_string(),
+            ..FullTextQuery::default()
+        };
+        assert_eq!(full_text_search(&query, db_path).unwrap().len(), 1);
+        assert!(!legacy.is_veteran);
+
+        // Years are extracted from dates stored before the year columns existed
+        let died = MemorialQuery::new().died_between(1865, 1865);
+        assert_eq!(query_memorials(&died, db_path).unwrap().len(), 1);
+
+        // Name keys are backfilled for phonetic search
+        let sounds_like = NameQuery {
+            name: "Legasy".to_string(),
+            ..NameQuery::default()
+        };
+        assert_eq!(name_search(&sounds_like, db_path).unwrap().len(), 1);
+
+        // Name parts are split from names stored before they had columns
+        assert_eq!(
+            legacy.person_name.and_then(|name| name.surname).as_deref(),
+            Some("Person")
+        );
+
+        let mut memorial = Memorial::new();
+        memorial.id = Some(43);
+        memorial.name = "New Person".to_string();
+        memorial.is_cenotaph = true;
+        store_in_db(&memorial, db_path).unwrap();
+        assert!(
+            get_memorial_by_id(43, db_path)
+                .unwrap()
+                .unwrap()
+                .is_cenotaph
+        );
+    }
+
+    #[test]
+    fn test_fresh_database_reaches_latest_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let applied = migrate(&mut conn).unwrap();
+        assert_eq!(applied, (1..=latest_version()).collect::<Vec<_>>());
+
+        // Running again is a no-op
+        assert!(migrate(&mut conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_upgrade_from_every_earlier_version() {
+        for version in 0..latest_version() {
+            let dir = tempdir().unwrap();
+            let db_path = dir.path().join(format!("v{}.db", version));
+            let db_path_str = db_path.to_str().unwrap();
+
+            // The row predates every later migration, so each backfill sees it
+            let mut conn = Connection::open(db_path_str).unwrap();
+            migrate_to(&mut conn, 1).unwrap();
+            conn.execute(
+                "INSERT INTO memorials (id, name, death_date) VALUES (42, 'Legacy Person', '1865')",
+                [],
+            )
+            .unwrap();
+            migrate_to(&mut conn, version.max(1)).unwrap();
+            if version == 0 {
+                conn.pragma_update(None, "user_version", 0).unwrap();
+            }
+            drop(conn);
+
+            assert_upgrades_cleanly(db_path_str);
+        }
+    }
+
+    #[test]
+    fn test_upgrade_unversioned_legacy_database() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("legacy.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        let conn = Connection::open(db_path_str).unwrap();
+        conn.execute_batch(LEGACY_SCHEMA).unwrap();
+        assert_eq!(schema_status(&conn).unwrap().current_version, 0);
+        drop(conn);
+
+        assert_upgrades_cleanly(db_path_str);
+    }
+
+    #[test]
+    fn test_upgrade_unversioned_database_with_newer_columns() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("partial.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        // An unversioned database that already gained some later columns
+        let conn = Connection::open(db_path_str).unwrap();
+        conn.execute_batch(LEGACY_SCHEMA).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE memorials ADD COLUMN is_veteran INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE memorials ADD COLUMN created_by TEXT;",
+        )
+        .unwrap();
+        drop(conn);
+
+        assert_upgrades_cleanly(db_path_str);
+    }
+
+    #[test]
+    fn test_newer_database_is_rejected() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+        assert!(migrate(&mut conn).is_err());
+    }
+}
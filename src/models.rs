@@ -1,3 +1,4 @@
+use crate::PersonName;
 use serde::{Deserialize, Serialize};
 
 /// Represents a Find a Grave memorial with parsed information
@@ -7,6 +8,9 @@ pub struct Memorial {
     pub id: Option<u64>,
     /// Full name of the person
     pub name: String,
+    /// Name split into given names, surname, maiden name and so on
+    #[serde(default)]
+    pub person_name: Option<PersonName>,
     /// Birth date if available
     pub birth_date: Option<String>,
     /// Birth location if available
@@ -19,6 +23,66 @@ pub struct Memorial {
     pub burial_location: Option<String>,
     /// Plot details if available
     pub plot_details: Option<String>,
+    /// Whether the memorial is marked as a veteran
+    #[serde(default)]
+    pub is_veteran: bool,
+    /// Whether the memorial is listed as a famous memorial
+    #[serde(default)]
+    pub is_famous: bool,
+    /// Whether the burial location is a cenotaph (a monument without remains)
+    #[serde(default)]
+    pub is_cenotaph: bool,
+    /// Whether the page states the person is not buried at this location
+    #[serde(default)]
+    pub not_buried_here: bool,
+    /// Find a Grave member who created the memorial
+    #[serde(default)]
+    pub created_by: Option<String>,
+    /// Find a Grave member who currently maintains the memorial
+    #[serde(default)]
+    pub maintained_by: Option<String>,
+    /// Date the memorial was added to Find a Grave
+    #[serde(default)]
+    pub created_date: Option<String>,
+    /// Sponsor of the memorial if it is sponsored
+    #[serde(default)]
+    pub sponsored_by: Option<String>,
+    /// Biography text if available
+    #[serde(default)]
+    pub bio: Option<String>,
+    /// GPS position of the grave itself, if recorded
+    #[serde(default)]
+    pub plot_coordinates: Option<Coordinates>,
+    /// GPS position of the cemetery, if known
+    #[serde(default)]
+    pub cemetery_coordinates: Option<Coordinates>,
+}
+
+/// A latitude and longitude in decimal degrees (WGS 84)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Coordinates {
+    /// Degrees north of the equator, from -90 to 90
+    pub latitude: f64,
+    /// Degrees east of Greenwich, from -180 to 180
+    pub longitude: f64,
+}
+
+impl Coordinates {
+    /// Create coordinates, or `None` if either value is out of range
+    pub fn new(latitude: f64, longitude: f64) -> Option<Self> {
+        ((-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)).then_some(
+            Self {
+                latitude,
+                longitude,
+            },
+        )
+    }
+}
+
+impl std::fmt::Display for Coordinates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}", self.latitude, self.longitude)
+    }
 }
 
 impl Memorial {
@@ -27,12 +91,24 @@ impl Memorial {
         Memorial {
             id: None,
             name: String::new(),
+            person_name: None,
             birth_date: None,
             birth_location: None,
             death_date: None,
             death_location: None,
             burial_location: None,
             plot_details: None,
+            is_veteran: false,
+            is_famous: false,
+            is_cenotaph: false,
+            not_buried_here: false,
+            created_by: None,
+            maintained_by: None,
+            created_date: None,
+            sponsored_by: None,
+            bio: None,
+            plot_coordinates: None,
+            cemetery_coordinates: None,
         }
     }
 
@@ -63,15 +139,148 @@ impl Memorial {
         }
 
         if let Some(burial) = &self.burial_location {
-            citation.push_str(&format!("\nBurial: {}", burial));
+            // A cenotaph or "not buried here" memorial marks a place of
+            // remembrance, not the resting place, so it must not be cited as a burial
+            if self.is_cenotaph {
+                citation.push_str(&format!("\nCenotaph: {}", burial));
+            } else if self.not_buried_here {
+                citation.push_str(&format!("\nMemorial: {} (not buried here)", burial));
+            } else {
+                citation.push_str(&format!("\nBurial: {}", burial));
+            }
         }
 
         if let Some(plot) = &self.plot_details {
             citation.push_str(&format!("\nPlot: {}", plot));
         }
 
+        if self.is_veteran {
+            citation.push_str("\nVeteran");
+        }
+
+        if let Some(creator) = &self.created_by {
+            citation.push_str(&format!("\nCreated by: {}", creator));
+            if let Some(date) = &self.created_date {
+                citation.push_str(&format!(" ({})", date));
+            }
+        }
+
+        if let Some(maintainer) = &self.maintained_by {
+            citation.push_str(&format!("\nMaintained by: {}", maintainer));
+        }
+
+        citation
+    }
+
+    /// Address of the memorial on Find a Grave, if its ID is known
+    pub fn url(&self) -> Option<String> {
+        self.id
+            .map(|id| format!("https://www.findagrave.com/memorial/{}", id))
+    }
+
+    /// Citation followed by the memorial's address and the date it was accessed
+    pub fn to_sourced_citation(&self, accessed: Option<&str>) -> String {
+        let mut citation = self.to_citation();
+        if let (Some(id), Some(url)) = (self.id, self.url()) {
+            citation.push_str(&format!("\nFind a Grave memorial {}: {}", id, url));
+        }
+        if let Some(accessed) = accessed {
+            citation.push_str(&format!("\nAccessed: {}", accessed));
+        }
         citation
     }
+
+    /// Field values as `(field name, value)` pairs, used for comparisons
+    pub fn fields(&self) -> Vec<(&'static str, Option<String>)> {
+        vec![
+            ("id", self.id.map(|id| id.to_string())),
+            ("name", Some(self.name.clone())),
+            ("maiden_name", self.parsed_name().maiden),
+            ("birth_date", self.birth_date.clone()),
+            ("birth_location", self.birth_location.clone()),
+            ("death_date", self.death_date.clone()),
+            ("death_location", self.death_location.clone()),
+            ("burial_location", self.burial_location.clone()),
+            ("plot_details", self.plot_details.clone()),
+            ("is_veteran", Some(self.is_veteran.to_string())),
+            ("is_famous", Some(self.is_famous.to_string())),
+            ("is_cenotaph", Some(self.is_cenotaph.to_string())),
+            ("not_buried_here", Some(self.not_buried_here.to_string())),
+            ("created_by", self.created_by.clone()),
+            ("maintained_by", self.maintained_by.clone()),
+            ("created_date", self.created_date.clone()),
+            ("sponsored_by", self.sponsored_by.clone()),
+            ("bio", self.bio.clone()),
+            (
+                "plot_coordinates",
+                self.plot_coordinates.map(|c| c.to_string()),
+            ),
+            (
+                "cemetery_coordinates",
+                self.cemetery_coordinates.map(|c| c.to_string()),
+            ),
+        ]
+    }
+
+    /// Parts of the name, split from the full name if they were not parsed
+    pub fn parsed_name(&self) -> PersonName {
+        self.person_name
+            .clone()
+            .unwrap_or_else(|| PersonName::parse(&self.name))
+    }
+
+    /// Year of birth, if the birth date contains one
+    pub fn birth_year(&self) -> Option<i32> {
+        self.birth_date.as_deref().and_then(year_of)
+    }
+
+    /// Year of death, if the death date contains one
+    pub fn death_year(&self) -> Option<i32> {
+        self.death_date.as_deref().and_then(year_of)
+    }
+
+    /// Compare with a newer version of the memorial field by field
+    pub fn diff(&self, newer: &Memorial) -> Vec<FieldChange> {
+        self.fields()
+            .into_iter()
+            .zip(newer.fields())
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((field, old), (_, new))| FieldChange { field, old, new })
+            .collect()
+    }
+}
+
+/// A single field that differs between two versions of a memorial
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldChange {
+    /// Name of the changed field
+    pub field: &'static str,
+    /// Value before the change
+    pub old: Option<String>,
+    /// Value after the change
+    pub new: Option<String>,
+}
+
+impl std::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.field,
+            self.old.as_deref().unwrap_or("(none)"),
+            self.new.as_deref().unwrap_or("(none)")
+        )
+    }
+}
+
+/// Extract the year from a free-text date such as `12 Apr 1861` or `1861-04-12`
+///
+/// The last four-digit number in the text is taken as the year, so both
+/// day-first and ISO dates work.
+pub(crate) fn year_of(date: &str) -> Option<i32> {
+    date.split(|c: char| !c.is_ascii_digit())
+        .rfind(|part| part.len() == 4)
+        .and_then(|year| year.parse().ok())
 }
 
 impl Default for Memorial {
@@ -118,4 +327,51 @@ mod tests {
         assert!(citation.contains("January 1, 1900"));
         assert!(citation.contains("Springfield, IL"));
     }
+
+    #[test]
+    fn test_memorial_years() {
+        let mut memorial = Memorial::new();
+        memorial.birth_date = Some("12 Apr 1861".to_string());
+        memorial.death_date = Some("1923-07-04".to_string());
+        assert_eq!(memorial.birth_year(), Some(1861));
+        assert_eq!(memorial.death_year(), Some(1923));
+
+        memorial.death_date = Some("unknown".to_string());
+        assert_eq!(memorial.death_year(), None);
+    }
+
+    #[test]
+    fn test_memorial_diff() {
+        let mut old = Memorial::new();
+        old.name = "John Doe".to_string();
+        old.death_date = Some("1 Jan 1950".to_string());
+
+        let mut new = old.clone();
+        assert!(old.diff(&new).is_empty());
+
+        new.death_date = Some("2 Jan 1950".to_string());
+        new.is_veteran = true;
+        let changes = old.diff(&new);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].field, "death_date");
+        assert_eq!(
+            changes[0].to_string(),
+            "death_date: 1 Jan 1950 -> 2 Jan 1950"
+        );
+        assert_eq!(changes[1].field, "is_veteran");
+    }
+
+    #[test]
+    fn test_memorial_to_citation_cenotaph() {
+        let mut memorial = Memorial::new();
+        memorial.name = "John Doe".to_string();
+        memorial.burial_location = Some("Arlington National Cemetery".to_string());
+        memorial.is_cenotaph = true;
+        memorial.maintained_by = Some("Jane Roe".to_string());
+
+        let citation = memorial.to_citation();
+        assert!(citation.contains("Cenotaph: Arlington National Cemetery"));
+        assert!(!citation.contains("Burial:"));
+        assert!(citation.contains("Maintained by: Jane Roe"));
+    }
 }
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+/// Honorifics and ranks that can open a name
+const NAME_PREFIXES: &[&str] = &[
+    "dr", "rev", "mr", "mrs", "ms", "miss", "capt", "col", "gen", "maj", "sgt", "lt", "pvt", "cpl",
+    "hon", "sir", "judge", "elder", "deacon",
+];
+
+/// Generational and other suffixes that can close a name
+const NAME_SUFFIXES: &[&str] = &["jr", "sr", "ii", "iii", "iv", "v", "esq", "md", "phd"];
+
+/// Lowercase particles that belong to the surname, as in `van Buren`
+const SURNAME_PARTICLES: &[&str] = &[
+    "van", "von", "de", "der", "den", "du", "da", "di", "del", "della", "la", "le", "st", "ter",
+];
+
+/// A personal name split into its parts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersonName {
+    /// Honorific or rank before the name, such as `Dr` or `Capt.`
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Given names in order, first name first
+    #[serde(default)]
+    pub given: Vec<String>,
+    /// Nickname, written in quotes on the page
+    #[serde(default)]
+    pub nickname: Option<String>,
+    /// Surname, usually the married surname for women
+    #[serde(default)]
+    pub surname: Option<String>,
+    /// Maiden (birth) surname, written in italics on the page
+    #[serde(default)]
+    pub maiden: Option<String>,
+    /// Suffix after the name, such as `Jr.` or `III`
+    #[serde(default)]
+    pub suffix: Option<String>,
+}
+
+impl PersonName {
+    /// Split a plain-text name into its parts
+    ///
+    /// Without markup a maiden name cannot be told apart from a middle name,
+    /// so it is left among the given names. Text in parentheses, such as
+    /// `(Twin)`, is not part of the name and is skipped.
+    pub fn parse(name: &str) -> Self {
+        Self::parse_with_maiden(name, None)
+    }
+
+    /// Split a name whose maiden name is already known from the page markup
+    ///
+    /// The maiden name words are removed from the name before the remaining
+    /// words are divided into given names and surname.
+    pub fn parse_with_maiden(name: &str, maiden: Option<&str>) -> Self {
+        let mut nickname = None;
+        let mut rest = String::new();
+        let mut quoted: Option<String> = None;
+        let mut bracketed = false;
+        for c in name.chars() {
+            if bracketed {
+                bracketed = c != ')';
+                continue;
+            }
+            match (&mut quoted, c) {
+                (None, '"' | '“') => quoted = Some(String::new()),
+                (Some(text), '"' | '”') => {
+                    let text = text.trim();
+                    if nickname.is_none() && !text.is_empty() {
+                        nickname = Some(text.to_string());
+                    }
+                    quoted = None;
+                    rest.push(' ');
+                }
+                (Some(text), c) => text.push(c),
+                (None, '(') => {
+                    bracketed = true;
+                    rest.push(' ');
+                }
+                (None, ',') => rest.push(' '),
+                (None, c) => rest.push(c),
+            }
+        }
+        // A quote never closed was not a nickname, so keep its words
+        if let Some(text) = quoted {
+            rest.push(' ');
+            rest.push_str(&text.replace(',', " "));
+        }
+
+        let mut words: Vec<&str> = rest.split_whitespace().collect();
+
+        let maiden = maiden.map(str::trim).filter(|m| !m.is_empty());
+        if let Some(maiden) = maiden {
+            let maiden_words: Vec<&str> = maiden.split_whitespace().collect();
+            if let Some(start) = words
+                .windows(maiden_words.len())
+                .position(|window| window == maiden_words.as_slice())
+            {
+                words.drain(start..start + maiden_words.len());
+            }
+        }
+
+        let mut prefixes = Vec::new();
+        while words.len() > 1 && is_one_of(words[0], NAME_PREFIXES) {
+            prefixes.push(words.remove(0));
+        }
+        let mut suffixes = Vec::new();
+        while words.len() > 1 && is_one_of(words[words.len() - 1], NAME_SUFFIXES) {
+            suffixes.insert(0, words.pop().unwrap_or_default());
+        }
+
+        let surname = words.pop().map(|last| {
+            let mut surname = vec![last];
+            while words.len() > 1 && SURNAME_PARTICLES.contains(&words[words.len() - 1]) {
+                surname.insert(0, words.pop().unwrap_or_default());
+            }
+            surname.join(" ")
+        });
+
+        let join = |parts: Vec<&str>| (!parts.is_empty()).then(|| parts.join(" "));
+        Self {
+            prefix: join(prefixes),
+            given: words.into_iter().map(str::to_string).collect(),
+            nickname,
+            surname,
+            maiden: maiden.map(str::to_string),
+            suffix: join(suffixes),
+        }
+    }
+
+    /// Given names joined with spaces, such as `John Henry`
+    pub fn given_names(&self) -> String {
+        self.given.join(" ")
+    }
+
+    /// Surname a person was born with: the maiden name when known
+    pub fn birth_surname(&self) -> Option<&str> {
+        self.maiden.as_deref().or(self.surname.as_deref())
+    }
+
+    /// Render the name GEDCOM-style, with the surname between slashes
+    ///
+    /// GEDCOM records people under their birth surname, so the maiden name is
+    /// used when it is known: `Mary Ann /Jones/`.
+    pub fn to_gedcom(&self) -> String {
+        let mut parts = Vec::new();
+        parts.extend(self.prefix.clone());
+        parts.extend(self.given.iter().cloned());
+        parts.push(format!("/{}/", self.birth_surname().unwrap_or_default()));
+        parts.extend(self.suffix.clone());
+        parts.join(" ")
+    }
+}
+
+/// Compare a name word to a list, ignoring case and trailing full stops
+fn is_one_of(word: &str, list: &[&str]) -> bool {
+    let word = word.trim_end_matches('.').to_lowercase();
+    list.contains(&word.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_name() {
+        let name = PersonName::parse("Dr John \"Jack\" Henry Smith Jr.");
+        assert_eq!(name.prefix.as_deref(), Some("Dr"));
+        assert_eq!(name.given, ["John", "Henry"]);
+        assert_eq!(name.nickname.as_deref(), Some("Jack"));
+        assert_eq!(name.surname.as_deref(), Some("Smith"));
+        assert_eq!(name.suffix.as_deref(), Some("Jr."));
+        assert_eq!(name.to_gedcom(), "Dr John Henry /Smith/ Jr.");
+    }
+
+    #[test]
+    fn test_unclosed_quote_keeps_its_words() {
+        let name = PersonName::parse("John \"Jack Smith");
+        assert_eq!(name.given, ["John", "Jack"]);
+        assert_eq!(name.surname.as_deref(), Some("Smith"));
+        assert!(name.nickname.is_none());
+    }
+
+    #[test]
+    fn test_parse_with_maiden_name() {
+        let name = PersonName::parse_with_maiden("Mary Ann Jones Smith", Some("Jones"));
+        assert_eq!(name.given, ["Mary", "Ann"]);
+        assert_eq!(name.surname.as_deref(), Some("Smith"));
+        assert_eq!(name.maiden.as_deref(), Some("Jones"));
+        assert_eq!(name.to_gedcom(), "Mary Ann /Jones/");
+
+        // Without markup the maiden name reads as a middle name
+        let plain = PersonName::parse("Mary Ann Jones Smith");
+        assert_eq!(plain.given, ["Mary", "Ann", "Jones"]);
+        assert!(plain.maiden.is_none());
+    }
+
+    #[test]
+    fn test_parse_particles_and_comma_suffix() {
+        let name = PersonName::parse("Martin van Buren");
+        assert_eq!(name.given, ["Martin"]);
+        assert_eq!(name.surname.as_deref(), Some("van Buren"));
+
+        let name = PersonName::parse("Robert E. Lee, III");
+        assert_eq!(name.given, ["Robert", "E."]);
+        assert_eq!(name.surname.as_deref(), Some("Lee"));
+        assert_eq!(name.suffix.as_deref(), Some("III"));
+
+        let name = PersonName::parse("John (Twin) Smith (Rev.)");
+        assert_eq!(name.given, ["John"]);
+        assert_eq!(name.surname.as_deref(), Some("Smith"));
+
+        let single = PersonName::parse("Pocahontas");
+        assert!(single.given.is_empty());
+        assert_eq!(single.to_gedcom(), "/Pocahontas/");
+    }
+}
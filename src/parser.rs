@@ -1,4 +1,4 @@
-use crate::{Error, Memorial, Result};
+use crate::{Coordinates, Error, Memorial, PersonName, Result};
 use scraper::{Html, Selector};
 use std::collections::HashMap;
 
@@ -12,12 +12,18 @@ pub fn parse_page(html: &str) -> Result<Memorial> {
 
     // Extract basic information
     if let Some(element) = document.select(&selectors["name"]).next() {
-        memorial.name = element
-            .text()
-            .collect::<Vec<_>>()
-            .join(" ")
-            .trim()
-            .to_string();
+        memorial.name = collapse_whitespace(element.text());
+
+        // The maiden name is set in italics within the `bio-name` heading
+        let maiden = collapse_whitespace(
+            element
+                .select(&selectors["maiden_name"])
+                .flat_map(|italic| italic.text()),
+        );
+        memorial.person_name = Some(PersonName::parse_with_maiden(
+            &memorial.name,
+            Some(maiden.as_str()).filter(|m| !m.is_empty()),
+        ));
     } else {
         return Err(Error::ParseError("Could not find name field".into()));
     }
@@ -54,6 +60,47 @@ pub fn parse_page(html: &str) -> Result<Memorial> {
         &mut memorial.plot_details,
     );
 
+    // Extract memorial flags
+    memorial.is_veteran = has_match(&document, &selectors["veteran"]);
+    memorial.is_famous = has_match(&document, &selectors["famous"]);
+    memorial.is_cenotaph = has_match(&document, &selectors["cenotaph"]);
+    memorial.not_buried_here = has_match(&document, &selectors["not_buried_here"]);
+
+    // Extract memorial metadata
+    extract_optional_field(
+        &document,
+        &selectors["created_by"],
+        &mut memorial.created_by,
+    );
+    extract_optional_field(
+        &document,
+        &selectors["maintained_by"],
+        &mut memorial.maintained_by,
+    );
+    extract_optional_field(
+        &document,
+        &selectors["created_date"],
+        &mut memorial.created_date,
+    );
+    extract_optional_field(
+        &document,
+        &selectors["sponsored_by"],
+        &mut memorial.sponsored_by,
+    );
+    extract_optional_field(&document, &selectors["bio"], &mut memorial.bio);
+
+    // Extract grave and cemetery GPS positions
+    memorial.plot_coordinates = extract_coordinates(
+        &document,
+        &selectors["plot_latitude"],
+        &selectors["plot_longitude"],
+    );
+    memorial.cemetery_coordinates = extract_coordinates(
+        &document,
+        &selectors["cemetery_latitude"],
+        &selectors["cemetery_longitude"],
+    );
+
     // Extract memorial ID from the URL or page content
     if let Some(element) = document.select(&selectors["memorial_id"]).next() {
         if let Some(id_str) = element.value().attr("content") {
@@ -84,6 +131,36 @@ fn extract_optional_field(document: &Html, selector: &Selector, target: &mut Opt
     }
 }
 
+/// Extract a latitude and longitude given as element text or a `content` attribute
+fn extract_coordinates(
+    document: &Html,
+    latitude: &Selector,
+    longitude: &Selector,
+) -> Option<Coordinates> {
+    let degrees = |selector: &Selector| -> Option<f64> {
+        let element = document.select(selector).next()?;
+        let text = match element.value().attr("content") {
+            Some(content) => content.to_string(),
+            None => element.text().collect(),
+        };
+        text.trim().parse().ok()
+    };
+    Coordinates::new(degrees(latitude)?, degrees(longitude)?)
+}
+
+/// Join text fragments with single spaces between words
+fn collapse_whitespace<'a>(fragments: impl Iterator<Item = &'a str>) -> String {
+    fragments
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Check whether any element on the page matches the given selector
+fn has_match(document: &Html, selector: &Selector) -> bool {
+    document.select(selector).next().is_some()
+}
+
 /// Build a map of CSS selectors for different parts of the page
 fn build_selectors() -> HashMap<&'static str, Selector> {
     let mut selectors = HashMap::new();
@@ -93,6 +170,10 @@ fn build_selectors() -> HashMap<&'static str, Selector> {
         "name",
         Selector::parse("[itemprop='name'], h1.name, .bio-info .full-name").unwrap(),
     );
+    selectors.insert(
+        "maiden_name",
+        Selector::parse("h1#bio-name i, h1#bio-name em").unwrap(),
+    );
     selectors.insert(
         "birth_date",
         Selector::parse("[itemprop='birthDate'], .birth-info .date").unwrap(),
@@ -117,6 +198,60 @@ fn build_selectors() -> HashMap<&'static str, Selector> {
         "plot_details",
         Selector::parse(".plot-details, .grave-location").unwrap(),
     );
+    selectors.insert(
+        "veteran",
+        Selector::parse(".icon-vet, .veteran-badge, [data-veteran='true']").unwrap(),
+    );
+    selectors.insert(
+        "famous",
+        Selector::parse(".icon-famous, .famous-memorial, [data-famous='true']").unwrap(),
+    );
+    selectors.insert(
+        "cenotaph",
+        Selector::parse("#cenotaph, .cenotaph, [data-cenotaph='true']").unwrap(),
+    );
+    selectors.insert(
+        "not_buried_here",
+        Selector::parse("#notBuriedHere, .not-buried-here, [data-not-buried='true']").unwrap(),
+    );
+    selectors.insert(
+        "created_by",
+        Selector::parse("#createdBy [itemprop='name'], #createdBy a, .created-by .member-name")
+            .unwrap(),
+    );
+    selectors.insert(
+        "maintained_by",
+        Selector::parse(
+            "#maintainedBy [itemprop='name'], #maintainedBy a, .maintained-by .member-name",
+        )
+        .unwrap(),
+    );
+    selectors.insert(
+        "created_date",
+        Selector::parse("#addedDate, .added-date, [itemprop='dateCreated']").unwrap(),
+    );
+    selectors.insert(
+        "sponsored_by",
+        Selector::parse(
+            "#sponsoredBy [itemprop='name'], #sponsoredBy a, .sponsored-by .member-name",
+        )
+        .unwrap(),
+    );
+    selectors.insert(
+        "bio",
+        Selector::parse("#fullBio, #partBio, .memorial-bio, [itemprop='description']").unwrap(),
+    );
+    selectors.insert("plot_latitude", Selector::parse("#gpsLatitude").unwrap());
+    selectors.insert("plot_longitude", Selector::parse("#gpsLongitude").unwrap());
+    selectors.insert(
+        "cemetery_latitude",
+        Selector::parse("#cemeteryLatitude, [itemtype$='Cemetery'] [itemprop='latitude']").unwrap(),
+    );
+    selectors.insert(
+        "cemetery_longitude",
+        Selector::parse("#cemeteryLongitude, [itemtype$='Cemetery'] [itemprop='longitude']")
+            .unwrap(),
+    );
     selectors.insert(
         "memorial_id",
         Selector::parse("meta[property='og:url']").unwrap(),
@@ -183,6 +318,104 @@ mod tests {
         assert_eq!(memorial.name, "Jane Smith");
         assert_eq!(memorial.birth_date, None);
         assert_eq!(memorial.death_date, None);
+        assert!(!memorial.is_veteran);
+        assert!(!memorial.is_cenotaph);
+        assert_eq!(memorial.created_by, None);
+    }
+
+    #[test]
+    fn test_parse_flags_and_metadata() {
+        let html = r#"
+        <html>
+            <body>
+                <h1 itemprop="name">James Brown</h1>
+                <span class="icon-vet" title="Veteran"></span>
+                <span class="icon-famous" title="Famous memorial"></span>
+                <p id="cenotaph">Cenotaph</p>
+                <div class="burial-info">
+                    <span class="location" itemprop="burialPlace">Arlington National Cemetery</span>
+                </div>
+                <div id="createdBy"><a href="/user/profile/1">Find a Grave</a></div>
+                <div id="maintainedBy"><a href="/user/profile/2">Jane Roe</a></div>
+                <span id="addedDate">10 Mar 2001</span>
+                <div id="sponsoredBy"><a href="/user/profile/3">John Roe</a></div>
+                <p id="fullBio">Served with the 1st Virginia Cavalry.</p>
+            </body>
+        </html>
+        "#;
+
+        let memorial = parse_page(html).unwrap();
+
+        assert!(memorial.is_veteran);
+        assert!(memorial.is_famous);
+        assert!(memorial.is_cenotaph);
+        assert!(!memorial.not_buried_here);
+        assert_eq!(memorial.created_by, Some("Find a Grave".to_string()));
+        assert_eq!(memorial.maintained_by, Some("Jane Roe".to_string()));
+        assert_eq!(memorial.created_date, Some("10 Mar 2001".to_string()));
+        assert_eq!(memorial.sponsored_by, Some("John Roe".to_string()));
+        assert_eq!(
+            memorial.bio,
+            Some("Served with the 1st Virginia Cavalry.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_coordinates() {
+        let html = r#"<html><body>
+            <h1 itemprop="name">Ann Lee</h1>
+            <span id="gpsLatitude">42.3601</span><span id="gpsLongitude">-71.0589</span>
+            <div itemscope itemtype="https://schema.org/Cemetery">
+                <span itemprop="geo" itemscope itemtype="https://schema.org/GeoCoordinates">
+                    <meta itemprop="latitude" content="42.3597">
+                    <meta itemprop="longitude" content="-71.0603">
+                </span>
+            </div>
+        </body></html>"#;
+
+        let memorial = parse_page(html).unwrap();
+        assert_eq!(
+            memorial.plot_coordinates,
+            Coordinates::new(42.3601, -71.0589)
+        );
+        assert_eq!(
+            memorial.cemetery_coordinates,
+            Coordinates::new(42.3597, -71.0603)
+        );
+
+        // Out-of-range or half-given positions are ignored
+        let html = r#"<html><body><h1 itemprop="name">Ann Lee</h1>
+            <span id="gpsLatitude">142.0</span><span id="gpsLongitude">-71.0</span>
+            <span id="cemeteryLatitude">42.0</span>
+        </body></html>"#;
+        let memorial = parse_page(html).unwrap();
+        assert_eq!(memorial.plot_coordinates, None);
+        assert_eq!(memorial.cemetery_coordinates, None);
+    }
+
+    #[test]
+    fn test_parse_name_parts_from_markup() {
+        let html = r#"<html><body>
+            <h1 id="bio-name" itemprop="name">Mrs Mary &ldquo;Polly&rdquo; Ann <i>Jones</i> Smith</h1>
+        </body></html>"#;
+
+        let memorial = parse_page(html).unwrap();
+        assert_eq!(memorial.name, "Mrs Mary “Polly” Ann Jones Smith");
+
+        let name = memorial.person_name.unwrap();
+        assert_eq!(name.prefix.as_deref(), Some("Mrs"));
+        assert_eq!(name.given, ["Mary", "Ann"]);
+        assert_eq!(name.nickname.as_deref(), Some("Polly"));
+        assert_eq!(name.surname.as_deref(), Some("Smith"));
+        assert_eq!(name.maiden.as_deref(), Some("Jones"));
+
+        // Italics elsewhere, such as an emphasised word in a name block, are not a maiden name
+        let html = r#"<html><body>
+            <div class="bio-info"><span class="full-name">Mary <em>Ann</em> Smith</span></div>
+        </body></html>"#;
+        let name = parse_page(html).unwrap().person_name.unwrap();
+        assert!(name.maiden.is_none());
+        assert_eq!(name.given, ["Mary", "Ann"]);
     }
 
     #[test]
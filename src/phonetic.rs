@@ -0,0 +1,937 @@
+use crate::{Error, PersonName, Result};
+use std::str::FromStr;
+
+/// How a name search compares names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameMatch {
+    /// Names that sound alike by Soundex or Double Metaphone
+    Phonetic,
+    /// Names within this many single-letter edits of each other
+    Fuzzy(usize),
+}
+
+/// Which part of a name a key was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NamePart {
+    Given,
+    Surname,
+}
+
+impl NamePart {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            NamePart::Given => "given",
+            NamePart::Surname => "surname",
+        }
+    }
+}
+
+impl FromStr for NamePart {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "given" => Ok(NamePart::Given),
+            "surname" => Ok(NamePart::Surname),
+            other => Err(Error::InvalidInput(format!("Unknown name part: {}", other))),
+        }
+    }
+}
+
+/// Normalized and phonetic keys for a single word of a name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NameKey {
+    pub part: NamePart,
+    pub normalized: String,
+    pub soundex: String,
+    pub metaphone: String,
+    pub metaphone_alt: String,
+}
+
+impl NameKey {
+    /// Key a single name word, or `None` if it has no letters
+    pub(crate) fn new(part: NamePart, word: &str) -> Option<Self> {
+        let normalized = normalize_name(word);
+        if normalized.is_empty() {
+            return None;
+        }
+        let (metaphone, metaphone_alt) = double_metaphone(&normalized);
+        Some(Self {
+            part,
+            soundex: soundex(&normalized),
+            normalized,
+            metaphone,
+            metaphone_alt,
+        })
+    }
+
+    /// Similarity to another key in `0.0..=1.0`, or `None` if they do not match
+    fn similarity(&self, other: &NameKey, mode: NameMatch) -> Option<f64> {
+        let distance = levenshtein(&self.normalized, &other.normalized);
+        let matches = match mode {
+            NameMatch::Phonetic => self.soundex == other.soundex || self.metaphones_overlap(other),
+            NameMatch::Fuzzy(max_distance) => distance <= max_distance,
+        };
+        if !matches {
+            return None;
+        }
+
+        let longest = self
+            .normalized
+            .chars()
+            .count()
+            .max(other.normalized.chars().count());
+        Some(1.0 - distance as f64 / longest as f64)
+    }
+
+    fn metaphones_overlap(&self, other: &NameKey) -> bool {
+        let ours = [&self.metaphone, &self.metaphone_alt];
+        let theirs = [&other.metaphone, &other.metaphone_alt];
+        ours.iter()
+            .any(|code| !code.is_empty() && theirs.contains(code))
+    }
+}
+
+/// Normalized and phonetic keys for every part of a name
+///
+/// Given names and the nickname are keyed as given names; the surname and
+/// maiden name are both keyed as surnames.
+pub(crate) fn name_keys(name: &PersonName) -> Vec<NameKey> {
+    let given = name
+        .given
+        .iter()
+        .chain(&name.nickname)
+        .filter_map(|word| NameKey::new(NamePart::Given, word));
+    let surnames = name
+        .surname
+        .iter()
+        .chain(&name.maiden)
+        .filter_map(|word| NameKey::new(NamePart::Surname, word));
+    given.chain(surnames).collect()
+}
+
+/// Score how well a stored name matches a query name, higher is better
+///
+/// A one-word query matches any part of the stored name. Longer queries must
+/// match the surname and every given name word; the score is the average
+/// similarity of the matched words.
+pub(crate) fn score_name(query: &[NameKey], candidate: &[NameKey], mode: NameMatch) -> Option<f64> {
+    let best = |key: &NameKey, same_part: bool| {
+        candidate
+            .iter()
+            .filter(|other| !same_part || other.part == key.part)
+            .filter_map(|other| key.similarity(other, mode))
+            .fold(None, |best: Option<f64>, score| {
+                Some(best.map_or(score, |best| best.max(score)))
+            })
+    };
+
+    match query {
+        [] => None,
+        [single] => best(single, false),
+        keys => {
+            let mut total = 0.0;
+            for key in keys {
+                total += best(key, true)?;
+            }
+            Some(total / keys.len() as f64)
+        }
+    }
+}
+
+/// Fold a name to lowercase ASCII letters, dropping accents and punctuation
+pub fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        let folded = match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+            'ç' | 'ć' | 'č' => "c",
+            'ď' | 'đ' => "d",
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+            'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' => "i",
+            'ł' | 'ľ' | 'ĺ' => "l",
+            'ñ' | 'ń' | 'ň' => "n",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+            'ř' | 'ŕ' => "r",
+            'ś' | 'š' | 'ş' => "s",
+            'ť' | 'ţ' => "t",
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+            'ý' | 'ÿ' => "y",
+            'ź' | 'ż' | 'ž' => "z",
+            'ß' => "ss",
+            'æ' => "ae",
+            'œ' => "oe",
+            'ĳ' => "ij",
+            'ŉ' => "n",
+            c if c.is_ascii_lowercase() => {
+                normalized.push(c);
+                continue;
+            }
+            _ => continue,
+        };
+        normalized.push_str(folded);
+    }
+    normalized
+}
+
+/// American Soundex code, such as `R163` for both Robert and Rupert
+///
+/// Returns an empty string for input without letters.
+pub fn soundex(name: &str) -> String {
+    fn digit(c: char) -> Option<char> {
+        match c {
+            'b' | 'f' | 'p' | 'v' => Some('1'),
+            'c' | 'g' | 'j' | 'k' | 'q' | 's' | 'x' | 'z' => Some('2'),
+            'd' | 't' => Some('3'),
+            'l' => Some('4'),
+            'm' | 'n' => Some('5'),
+            'r' => Some('6'),
+            _ => None,
+        }
+    }
+
+    let normalized = normalize_name(name);
+    let mut letters = normalized.chars();
+    let first = match letters.next() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+
+    let mut code = first.to_ascii_uppercase().to_string();
+    let mut previous = digit(first);
+    for c in letters {
+        let current = digit(c);
+        if let Some(d) = current.filter(|_| current != previous) {
+            code.push(d);
+            if code.len() == 4 {
+                break;
+            }
+        }
+        // H and W do not separate letters with the same code; vowels do
+        if c != 'h' && c != 'w' {
+            previous = current;
+        }
+    }
+
+    while code.len() < 4 {
+        code.push('0');
+    }
+    code
+}
+
+/// Primary and alternate Double Metaphone codes, each up to four characters
+///
+/// The alternate code equals the primary one when the name has only one
+/// likely pronunciation.
+pub fn double_metaphone(name: &str) -> (String, String) {
+    DoubleMetaphone::new(name).encode()
+}
+
+/// Levenshtein edit distance between two strings, counted in characters
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// State for Lawrence Philips' Double Metaphone algorithm
+struct DoubleMetaphone {
+    /// Uppercase letters followed by space padding, so look-ahead never overruns
+    word: Vec<char>,
+    length: isize,
+    primary: String,
+    alternate: String,
+    slavo_germanic: bool,
+}
+
+impl DoubleMetaphone {
+    const MAX_LENGTH: usize = 4;
+
+    fn new(name: &str) -> Self {
+        let upper = normalize_name(name).to_ascii_uppercase();
+        let slavo_germanic = ["W", "K", "CZ", "WITZ"]
+            .iter()
+            .any(|pattern| upper.contains(pattern));
+        let length = upper.len() as isize;
+        let mut word: Vec<char> = upper.chars().collect();
+        word.extend([' '; 5]);
+        Self {
+            word,
+            length,
+            primary: String::new(),
+            alternate: String::new(),
+            slavo_germanic,
+        }
+    }
+
+    fn at(&self, pos: isize) -> char {
+        if pos < 0 || pos as usize >= self.word.len() {
+            '\0'
+        } else {
+            self.word[pos as usize]
+        }
+    }
+
+    fn is_vowel(&self, pos: isize) -> bool {
+        pos >= 0 && pos < self.length && matches!(self.at(pos), 'A' | 'E' | 'I' | 'O' | 'U' | 'Y')
+    }
+
+    /// Check whether the text at `start` is one of `options`, all of length `len`
+    fn string_at(&self, start: isize, len: usize, options: &[&str]) -> bool {
+        if start < 0 || start as usize + len > self.word.len() {
+            return false;
+        }
+        let start = start as usize;
+        let text: String = self.word[start..start + len].iter().collect();
+        options.contains(&text.as_str())
+    }
+
+    fn add(&mut self, code: &str) {
+        self.add_both(code, code);
+    }
+
+    fn add_both(&mut self, primary: &str, alternate: &str) {
+        self.primary.push_str(primary);
+        self.alternate.push_str(alternate);
+    }
+
+    fn encode(mut self) -> (String, String) {
+        let last = self.length - 1;
+        let mut current: isize = 0;
+
+        // Silent first letters
+        if self.string_at(0, 2, &["GN", "KN", "PN", "WR", "PS"]) {
+            current += 1;
+        }
+        // Initial X is pronounced Z, as in Xavier
+        if self.at(0) == 'X' {
+            self.add("S");
+            current += 1;
+        }
+
+        while (self.primary.len() < Self::MAX_LENGTH || self.alternate.len() < Self::MAX_LENGTH)
+            && current < self.length
+        {
+            current = match self.at(current) {
+                'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                    if current == 0 {
+                        self.add("A");
+                    }
+                    current + 1
+                }
+                'B' => {
+                    self.add("P");
+                    current + if self.at(current + 1) == 'B' { 2 } else { 1 }
+                }
+                'C' => self.encode_c(current),
+                'D' => {
+                    if self.string_at(current, 2, &["DG"]) {
+                        if self.string_at(current + 2, 1, &["I", "E", "Y"]) {
+                            self.add("J");
+                            current + 3
+                        } else {
+                            self.add("TK");
+                            current + 2
+                        }
+                    } else if self.string_at(current, 2, &["DT", "DD"]) {
+                        self.add("T");
+                        current + 2
+                    } else {
+                        self.add("T");
+                        current + 1
+                    }
+                }
+                'F' => {
+                    self.add("F");
+                    current + if self.at(current + 1) == 'F' { 2 } else { 1 }
+                }
+                'G' => self.encode_g(current),
+                'H' => {
+                    // Only keep H between vowels or at the start before a vowel
+                    if (current == 0 || self.is_vowel(current - 1)) && self.is_vowel(current + 1) {
+                        self.add("H");
+                        current + 2
+                    } else {
+                        current + 1
+                    }
+                }
+                'J' => self.encode_j(current, last),
+                'K' => {
+                    self.add("K");
+                    current + if self.at(current + 1) == 'K' { 2 } else { 1 }
+                }
+                'L' => {
+                    let next = if self.at(current + 1) == 'L' {
+                        // Spanish, as in Cabrillo or Gallegos
+                        if (current == self.length - 3
+                            && self.string_at(current - 1, 4, &["ILLO", "ILLA", "ALLE"]))
+                            || ((self.string_at(last - 1, 2, &["AS", "OS"])
+                                || self.string_at(last, 1, &["A", "O"]))
+                                && self.string_at(current - 1, 4, &["ALLE"]))
+                        {
+                            self.add_both("L", "");
+                            current += 2;
+                            continue;
+                        }
+                        current + 2
+                    } else {
+                        current + 1
+                    };
+                    self.add("L");
+                    next
+                }
+                'M' => {
+                    self.add("M");
+                    if (self.string_at(current - 1, 3, &["UMB"])
+                        && (current + 1 == last || self.string_at(current + 2, 2, &["ER"])))
+                        || self.at(current + 1) == 'M'
+                    {
+                        current + 2
+                    } else {
+                        current + 1
+                    }
+                }
+                'N' => {
+                    self.add("N");
+                    current + if self.at(current + 1) == 'N' { 2 } else { 1 }
+                }
+                'P' => {
+                    if self.at(current + 1) == 'H' {
+                        self.add("F");
+                        current + 2
+                    } else {
+                        self.add("P");
+                        current
+                            + if self.string_at(current + 1, 1, &["P", "B"]) {
+                                2
+                            } else {
+                                1
+                            }
+                    }
+                }
+                'Q' => {
+                    self.add("K");
+                    current + if self.at(current + 1) == 'Q' { 2 } else { 1 }
+                }
+                'R' => {
+                    // French, as in Rogier
+                    if current == last
+                        && !self.slavo_germanic
+                        && self.string_at(current - 2, 2, &["IE"])
+                        && !self.string_at(current - 4, 2, &["ME", "MA"])
+                    {
+                        self.add_both("", "R");
+                    } else {
+                        self.add("R");
+                    }
+                    current + if self.at(current + 1) == 'R' { 2 } else { 1 }
+                }
+                'S' => self.encode_s(current, last),
+                'T' => {
+                    if self.string_at(current, 4, &["TION"])
+                        || self.string_at(current, 3, &["TIA", "TCH"])
+                    {
+                        self.add("X");
+                        current + 3
+                    } else if self.string_at(current, 2, &["TH"])
+                        || self.string_at(current, 3, &["TTH"])
+                    {
+                        if self.string_at(current + 2, 2, &["OM", "AM"])
+                            || self.string_at(0, 4, &["VAN ", "VON "])
+                            || self.string_at(0, 3, &["SCH"])
+                        {
+                            self.add("T");
+                        } else {
+                            self.add_both("0", "T");
+                        }
+                        current + 2
+                    } else {
+                        self.add("T");
+                        current
+                            + if self.string_at(current + 1, 1, &["T", "D"]) {
+                                2
+                            } else {
+                                1
+                            }
+                    }
+                }
+                'V' => {
+                    self.add("F");
+                    current + if self.at(current + 1) == 'V' { 2 } else { 1 }
+                }
+                'W' => self.encode_w(current, last),
+                'X' => {
+                    // French, as in Breaux
+                    if !(current == last
+                        && (self.string_at(current - 3, 3, &["IAU", "EAU"])
+                            || self.string_at(current - 2, 2, &["AU", "OU"])))
+                    {
+                        self.add("KS");
+                    }
+                    current
+                        + if self.string_at(current + 1, 1, &["C", "X"]) {
+                            2
+                        } else {
+                            1
+                        }
+                }
+                'Z' => {
+                    if self.at(current + 1) == 'H' {
+                        // Chinese, as in Zhao
+                        self.add("J");
+                        current + 2
+                    } else {
+                        if self.string_at(current + 1, 2, &["ZO", "ZI", "ZA"])
+                            || (self.slavo_germanic && current > 0 && self.at(current - 1) != 'T')
+                        {
+                            self.add_both("S", "TS");
+                        } else {
+                            self.add("S");
+                        }
+                        current + if self.at(current + 1) == 'Z' { 2 } else { 1 }
+                    }
+                }
+                _ => current + 1,
+            };
+        }
+
+        self.primary.truncate(Self::MAX_LENGTH);
+        self.alternate.truncate(Self::MAX_LENGTH);
+        (self.primary, self.alternate)
+    }
+
+    fn encode_c(&mut self, current: isize) -> isize {
+        // Germanic, as in Bacher or Macher
+        if current > 1
+            && !self.is_vowel(current - 2)
+            && self.string_at(current - 1, 3, &["ACH"])
+            && self.at(current + 2) != 'I'
+            && (self.at(current + 2) != 'E'
+                || self.string_at(current - 2, 6, &["BACHER", "MACHER"]))
+        {
+            self.add("K");
+            return current + 2;
+        }
+        if current == 0 && self.string_at(current, 6, &["CAESAR"]) {
+            self.add("S");
+            return current + 2;
+        }
+        // Italian, as in Chianti
+        if self.string_at(current, 4, &["CHIA"]) {
+            self.add("K");
+            return current + 2;
+        }
+        if self.string_at(current, 2, &["CH"]) {
+            // Michael
+            if current > 0 && self.string_at(current, 4, &["CHAE"]) {
+                self.add_both("K", "X");
+                return current + 2;
+            }
+            // Greek roots at the start, as in Chemistry or Chorus
+            if current == 0
+                && (self.string_at(current + 1, 5, &["HARAC", "HARIS"])
+                    || self.string_at(current + 1, 3, &["HOR", "HYM", "HIA", "HEM"]))
+                && !self.string_at(0, 5, &["CHORE"])
+            {
+                self.add("K");
+                return current + 2;
+            }
+            // Germanic, Greek or otherwise pronounced K
+            if self.string_at(0, 4, &["VAN ", "VON "])
+                || self.string_at(0, 3, &["SCH"])
+                || self.string_at(current - 2, 6, &["ORCHES", "ARCHIT", "ORCHID"])
+                || self.string_at(current + 2, 1, &["T", "S"])
+                || ((self.string_at(current - 1, 1, &["A", "O", "U", "E"]) || current == 0)
+                    && self.string_at(
+                        current + 2,
+                        1,
+                        &["L", "R", "N", "M", "B", "H", "F", "V", "W", " "],
+                    ))
+            {
+                self.add("K");
+            } else if current > 0 {
+                if self.string_at(0, 2, &["MC"]) {
+                    self.add("K");
+                } else {
+                    self.add_both("X", "K");
+                }
+            } else {
+                self.add("X");
+            }
+            return current + 2;
+        }
+        // Polish, as in Czerny
+        if self.string_at(current, 2, &["CZ"]) && !self.string_at(current - 2, 4, &["WICZ"]) {
+            self.add_both("S", "X");
+            return current + 2;
+        }
+        // Italian, as in Focaccia
+        if self.string_at(current + 1, 3, &["CIA"]) {
+            self.add("X");
+            return current + 3;
+        }
+        // Double C, but not McClellan
+        if self.string_at(current, 2, &["CC"]) && !(current == 1 && self.at(0) == 'M') {
+            if self.string_at(current + 2, 1, &["I", "E", "H"])
+                && !self.string_at(current + 2, 2, &["HU"])
+            {
+                // Accident, Succeed, but Bacchus is a K
+                if (current == 1 && self.at(current - 1) == 'A')
+                    || self.string_at(current - 1, 5, &["UCCEE", "UCCES"])
+                {
+                    self.add("KS");
+                } else {
+                    self.add("X");
+                }
+                return current + 3;
+            }
+            self.add("K");
+            return current + 2;
+        }
+        if self.string_at(current, 2, &["CK", "CG", "CQ"]) {
+            self.add("K");
+            return current + 2;
+        }
+        if self.string_at(current, 2, &["CI", "CE", "CY"]) {
+            if self.string_at(current, 3, &["CIO", "CIE", "CIA"]) {
+                self.add_both("S", "X");
+            } else {
+                self.add("S");
+            }
+            return current + 2;
+        }
+
+        self.add("K");
+        if self.string_at(current + 1, 2, &[" C", " Q", " G"]) {
+            current + 3
+        } else if self.string_at(current + 1, 1, &["C", "K", "Q"])
+            && !self.string_at(current + 1, 2, &["CE", "CI"])
+        {
+            current + 2
+        } else {
+            current + 1
+        }
+    }
+
+    fn encode_g(&mut self, current: isize) -> isize {
+        if self.at(current + 1) == 'H' {
+            if current > 0 && !self.is_vowel(current - 1) {
+                self.add("K");
+                return current + 2;
+            }
+            // Ghislane, Ghiradelli
+            if current == 0 {
+                if self.at(current + 2) == 'I' {
+                    self.add("J");
+                } else {
+                    self.add("K");
+                }
+                return current + 2;
+            }
+            // Silent, as in Hugh or Bough
+            if (current > 1 && self.string_at(current - 2, 1, &["B", "H", "D"]))
+                || (current > 2 && self.string_at(current - 3, 1, &["B", "H", "D"]))
+                || (current > 3 && self.string_at(current - 4, 1, &["B", "H"]))
+            {
+                return current + 2;
+            }
+            // Laugh, McLaughlin, Cough, Rough
+            if current > 2
+                && self.at(current - 1) == 'U'
+                && self.string_at(current - 3, 1, &["C", "G", "L", "R", "T"])
+            {
+                self.add("F");
+            } else if current > 0 && self.at(current - 1) != 'I' {
+                self.add("K");
+            }
+            return current + 2;
+        }
+
+        if self.at(current + 1) == 'N' {
+            if current == 1 && self.is_vowel(0) && !self.slavo_germanic {
+                self.add_both("KN", "N");
+            } else if !self.string_at(current + 2, 2, &["EY"])
+                && self.at(current + 1) != 'Y'
+                && !self.slavo_germanic
+            {
+                self.add_both("N", "KN");
+            } else {
+                self.add("KN");
+            }
+            return current + 2;
+        }
+        // Tagliaro
+        if self.string_at(current + 1, 2, &["LI"]) && !self.slavo_germanic {
+            self.add_both("KL", "L");
+            return current + 2;
+        }
+        // Soft G at the start, as in Gerald or Gilbert
+        if current == 0
+            && (self.at(current + 1) == 'Y'
+                || self.string_at(
+                    current + 1,
+                    2,
+                    &[
+                        "ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER",
+                    ],
+                ))
+        {
+            self.add_both("K", "J");
+            return current + 2;
+        }
+        // -ger- and -gy-, but not Danger, Ranger or Manger
+        if (self.string_at(current + 1, 2, &["ER"]) || self.at(current + 1) == 'Y')
+            && !self.string_at(0, 6, &["DANGER", "RANGER", "MANGER"])
+            && !self.string_at(current - 1, 1, &["E", "I"])
+            && !self.string_at(current - 1, 3, &["RGY", "OGY"])
+        {
+            self.add_both("K", "J");
+            return current + 2;
+        }
+        // Italian, as in Biaggi
+        if self.string_at(current + 1, 1, &["E", "I", "Y"])
+            || self.string_at(current - 1, 4, &["AGGI", "OGGI"])
+        {
+            if self.string_at(0, 4, &["VAN ", "VON "])
+                || self.string_at(0, 3, &["SCH"])
+                || self.string_at(current + 1, 2, &["ET"])
+            {
+                self.add("K");
+            } else if self.string_at(current + 1, 4, &["IER "]) {
+                self.add("J");
+            } else {
+                self.add_both("J", "K");
+            }
+            return current + 2;
+        }
+
+        self.add("K");
+        current + if self.at(current + 1) == 'G' { 2 } else { 1 }
+    }
+
+    fn encode_j(&mut self, current: isize, last: isize) -> isize {
+        // Spanish, as in Jose or San Jacinto
+        if self.string_at(current, 4, &["JOSE"]) || self.string_at(0, 4, &["SAN "]) {
+            if (current == 0 && self.at(current + 4) == ' ') || self.string_at(0, 4, &["SAN "]) {
+                self.add("H");
+            } else {
+                self.add_both("J", "H");
+            }
+            return current + 1;
+        }
+
+        if current == 0 {
+            // Yankelovich or Jankelowicz
+            self.add_both("J", "A");
+        } else if self.is_vowel(current - 1)
+            && !self.slavo_germanic
+            && matches!(self.at(current + 1), 'A' | 'O')
+        {
+            self.add_both("J", "H");
+        } else if current == last {
+            self.add_both("J", "");
+        } else if !self.string_at(current + 1, 1, &["L", "T", "K", "S", "N", "M", "B", "Z"])
+            && !self.string_at(current - 1, 1, &["S", "K", "L"])
+        {
+            self.add("J");
+        }
+
+        current + if self.at(current + 1) == 'J' { 2 } else { 1 }
+    }
+
+    fn encode_s(&mut self, current: isize, last: isize) -> isize {
+        // Silent, as in Island or Carlisle
+        if self.string_at(current - 1, 3, &["ISL", "YSL"]) {
+            return current + 1;
+        }
+        if current == 0 && self.string_at(current, 5, &["SUGAR"]) {
+            self.add_both("X", "S");
+            return current + 1;
+        }
+        if self.string_at(current, 2, &["SH"]) {
+            // Germanic, as in Holmsheim
+            if self.string_at(current + 1, 4, &["HEIM", "HOEK", "HOLM", "HOLZ"]) {
+                self.add("S");
+            } else {
+                self.add("X");
+            }
+            return current + 2;
+        }
+        // Italian and Armenian
+        if self.string_at(current, 3, &["SIO", "SIA"]) || self.string_at(current, 4, &["SIAN"]) {
+            if self.slavo_germanic {
+                self.add("S");
+            } else {
+                self.add_both("S", "X");
+            }
+            return current + 3;
+        }
+        // German and anglicized forms, as in Smith matching Schmidt
+        if (current == 0 && self.string_at(current + 1, 1, &["M", "N", "L", "W"]))
+            || self.string_at(current + 1, 1, &["Z"])
+        {
+            self.add_both("S", "X");
+            return current
+                + if self.string_at(current + 1, 1, &["Z"]) {
+                    2
+                } else {
+                    1
+                };
+        }
+        if self.string_at(current, 2, &["SC"]) {
+            // Schlesinger's rule
+            if self.at(current + 2) == 'H' {
+                // Dutch, as in School or Schooner
+                if self.string_at(current + 3, 2, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+                    if self.string_at(current + 3, 2, &["ER", "EN"]) {
+                        self.add_both("X", "SK");
+                    } else {
+                        self.add("SK");
+                    }
+                } else if current == 0 && !self.is_vowel(3) && self.at(3) != 'W' {
+                    self.add_both("X", "S");
+                } else {
+                    self.add("X");
+                }
+                return current + 3;
+            }
+            if self.string_at(current + 2, 1, &["I", "E", "Y"]) {
+                self.add("S");
+            } else {
+                self.add("SK");
+            }
+            return current + 3;
+        }
+
+        // French, as in Resnais or Artois
+        if current == last && self.string_at(current - 2, 2, &["AI", "OI"]) {
+            self.add_both("", "S");
+        } else {
+            self.add("S");
+        }
+        current
+            + if self.string_at(current + 1, 1, &["S", "Z"]) {
+                2
+            } else {
+                1
+            }
+    }
+
+    fn encode_w(&mut self, current: isize, last: isize) -> isize {
+        if self.string_at(current, 2, &["WR"]) {
+            self.add("R");
+            return current + 2;
+        }
+        if current == 0 && (self.is_vowel(current + 1) || self.string_at(current, 2, &["WH"])) {
+            // Wasserman should match Vasserman
+            if self.is_vowel(current + 1) {
+                self.add_both("A", "F");
+            } else {
+                self.add("A");
+            }
+        }
+        // Polish, as in Filipowicz, or Arnow
+        if (current == last && self.is_vowel(current - 1))
+            || self.string_at(current - 1, 5, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"])
+            || self.string_at(0, 3, &["SCH"])
+        {
+            self.add_both("", "F");
+            return current + 1;
+        }
+        if self.string_at(current, 4, &["WICZ", "WITZ"]) {
+            self.add_both("TS", "FX");
+            return current + 4;
+        }
+        current + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soundex() {
+        assert_eq!(soundex("Robert"), "R163");
+        assert_eq!(soundex("Rupert"), "R163");
+        assert_eq!(soundex("Ashcraft"), "A261");
+        assert_eq!(soundex("Tymczak"), "T522");
+        assert_eq!(soundex("Pfister"), "P236");
+        assert_eq!(soundex("Lee"), "L000");
+        assert_eq!(soundex(""), "");
+    }
+
+    #[test]
+    fn test_double_metaphone() {
+        assert_eq!(double_metaphone("Smith"), ("SM0".into(), "XMT".into()));
+        assert_eq!(double_metaphone("Schmidt"), ("XMT".into(), "SMT".into()));
+        assert_eq!(
+            double_metaphone("Catherine"),
+            ("K0RN".into(), "KTRN".into())
+        );
+        assert_eq!(
+            double_metaphone("Katherine"),
+            ("K0RN".into(), "KTRN".into())
+        );
+        assert_eq!(double_metaphone("Xavier"), ("SF".into(), "SFR".into()));
+    }
+
+    #[test]
+    fn test_normalize_and_levenshtein() {
+        assert_eq!(normalize_name("Müller-Lüdenscheidt"), "mullerludenscheidt");
+        assert_eq!(normalize_name("O'Brien"), "obrien");
+        assert_eq!(normalize_name("Ĳsselmuiden"), "ijsselmuiden");
+        assert_eq!(normalize_name("Иван"), "");
+        assert_eq!(levenshtein("jonson", "johnson"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_name_keys_cover_every_part() {
+        let name =
+            PersonName::parse_with_maiden("Dr John \"Jack\" Henry Jones Smith Jr.", Some("Jones"));
+        let keys = name_keys(&name);
+        let words: Vec<(&str, NamePart)> = keys
+            .iter()
+            .map(|key| (key.normalized.as_str(), key.part))
+            .collect();
+        assert_eq!(
+            words,
+            [
+                ("john", NamePart::Given),
+                ("henry", NamePart::Given),
+                ("jack", NamePart::Given),
+                ("smith", NamePart::Surname),
+                ("jones", NamePart::Surname)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_score_name() {
+        let keys = |name: &str| name_keys(&PersonName::parse(name));
+        let stored = keys("Catherine Smith");
+
+        let phonetic = score_name(&keys("Katherine Schmidt"), &stored, NameMatch::Phonetic);
+        assert!(phonetic.is_some());
+        assert!(score_name(&keys("Mary Jones"), &stored, NameMatch::Phonetic).is_none());
+
+        // A single word may match any part of the name
+        let exact = score_name(&keys("smith"), &stored, NameMatch::Fuzzy(1)).unwrap();
+        let close = score_name(&keys("smyth"), &stored, NameMatch::Fuzzy(1)).unwrap();
+        assert_eq!(exact, 1.0);
+        assert!(close < exact);
+        assert!(score_name(&keys("smithers"), &stored, NameMatch::Fuzzy(1)).is_none());
+    }
+}
@@ -0,0 +1,417 @@
+use crate::db::SearchFilters;
+use crate::{Error, Result};
+use rusqlite::types::Value;
+use std::str::FromStr;
+
+/// Field used to order query results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortField {
+    /// Full name, alphabetically
+    #[default]
+    Name,
+    /// Year of birth
+    BirthYear,
+    /// Year of death
+    DeathYear,
+    /// FindAGrave memorial ID
+    Id,
+    /// Time the stored copy was last updated
+    UpdatedAt,
+}
+
+impl SortField {
+    /// Column expression used in the ORDER BY clause
+    fn column(&self) -> &'static str {
+        match self {
+            SortField::Name => "name",
+            SortField::BirthYear => "birth_year",
+            SortField::DeathYear => "death_year",
+            SortField::Id => "id",
+            SortField::UpdatedAt => "updated_at",
+        }
+    }
+}
+
+impl FromStr for SortField {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "name" => Ok(SortField::Name),
+            "born" | "birth" => Ok(SortField::BirthYear),
+            "died" | "death" => Ok(SortField::DeathYear),
+            "id" => Ok(SortField::Id),
+            "updated" => Ok(SortField::UpdatedAt),
+            other => Err(Error::InvalidInput(format!(
+                "Unknown sort field: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A structured, multi-criteria query over stored memorials
+///
+/// Every filter left unset matches all memorials. Text filters match
+/// case-insensitively anywhere in the field.
+///
+/// ```
+/// use findagrave_citation_parser::{MemorialQuery, SortField};
+///
+/// let query = MemorialQuery::new()
+///     .died_between(1860, 1865)
+///     .died_in("Virginia")
+///     .cemetery("Hollywood Cemetery")
+///     .sort_by(SortField::DeathYear, false)
+///     .limit(50);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemorialQuery {
+    name: Option<String>,
+    surname: Option<String>,
+    born: Option<(i32, i32)>,
+    died: Option<(i32, i32)>,
+    born_in: Option<String>,
+    died_in: Option<String>,
+    cemetery: Option<String>,
+    location: Option<String>,
+    plot: Option<String>,
+    tags: Vec<String>,
+    project: Option<String>,
+    flags: SearchFilters,
+    sort: SortField,
+    descending: bool,
+    limit: usize,
+    offset: usize,
+}
+
+impl Default for MemorialQuery {
+    fn default() -> Self {
+        Self {
+            name: None,
+            surname: None,
+            born: None,
+            died: None,
+            born_in: None,
+            died_in: None,
+            cemetery: None,
+            location: None,
+            plot: None,
+            tags: Vec::new(),
+            project: None,
+            flags: SearchFilters::default(),
+            sort: SortField::Name,
+            descending: false,
+            limit: 100,
+            offset: 0,
+        }
+    }
+}
+
+impl MemorialQuery {
+    /// Start a query that matches every memorial
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match names containing `name`
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Match memorials whose surname or maiden name is exactly `surname`
+    ///
+    /// Unlike the name filter this uses the surname index, ignoring case.
+    pub fn surname(mut self, surname: &str) -> Self {
+        self.surname = Some(surname.to_string());
+        self
+    }
+
+    /// Match memorials born between two years, inclusive
+    pub fn born_between(mut self, from: i32, to: i32) -> Self {
+        self.born = Some((from, to));
+        self
+    }
+
+    /// Match memorials that died between two years, inclusive
+    pub fn died_between(mut self, from: i32, to: i32) -> Self {
+        self.died = Some((from, to));
+        self
+    }
+
+    /// Match birth locations containing `place`
+    pub fn born_in(mut self, place: &str) -> Self {
+        self.born_in = Some(place.to_string());
+        self
+    }
+
+    /// Match death locations containing `place`
+    pub fn died_in(mut self, place: &str) -> Self {
+        self.died_in = Some(place.to_string());
+        self
+    }
+
+    /// Match burial locations (cemeteries) containing `cemetery`
+    pub fn cemetery(mut self, cemetery: &str) -> Self {
+        self.cemetery = Some(cemetery.to_string());
+        self
+    }
+
+    /// Match memorials with `place` in any of the birth, death or burial locations
+    pub fn location(mut self, place: &str) -> Self {
+        self.location = Some(place.to_string());
+        self
+    }
+
+    /// Match plot details containing `plot`
+    pub fn plot(mut self, plot: &str) -> Self {
+        self.plot = Some(plot.to_string());
+        self
+    }
+
+    /// Match memorials tagged `tag`, ignoring case
+    ///
+    /// Calling this more than once requires every tag to be present.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.trim().to_string());
+        self
+    }
+
+    /// Match memorials in the research project named `project`
+    pub fn project(mut self, project: &str) -> Self {
+        self.project = Some(project.to_string());
+        self
+    }
+
+    /// Restrict results with flag filters
+    pub fn flags(mut self, flags: SearchFilters) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Order results by a field
+    pub fn sort_by(mut self, field: SortField, descending: bool) -> Self {
+        self.sort = field;
+        self.descending = descending;
+        self
+    }
+
+    /// Return at most `limit` results
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Skip the first `offset` results
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Check whether the query uses any filter other than the name and flags
+    pub fn has_structured_filters(&self) -> bool {
+        self.surname.is_some()
+            || self.born.is_some()
+            || self.died.is_some()
+            || self.born_in.is_some()
+            || self.died_in.is_some()
+            || self.cemetery.is_some()
+            || self.location.is_some()
+            || self.plot.is_some()
+            || !self.tags.is_empty()
+            || self.project.is_some()
+    }
+
+    /// Name filter and flag filters, for backends without structured queries
+    pub fn name_and_flags(&self) -> (&str, &SearchFilters) {
+        (self.name.as_deref().unwrap_or(""), &self.flags)
+    }
+
+    /// Build the WHERE, ORDER BY and LIMIT clauses with their bound parameters
+    ///
+    /// Values are always bound as parameters, never spliced into the SQL.
+    pub(crate) fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut clauses = Vec::new();
+        let mut values = Vec::new();
+
+        let mut push = |clause: &str, params: Vec<Value>| {
+            let mut clause = clause.to_string();
+            for value in params {
+                values.push(value);
+                clause = clause.replacen("{}", &format!("?{}", values.len()), 1);
+            }
+            clauses.push(clause);
+        };
+
+        let like = |text: &str| Value::Text(format!("%{}%", escape_like(text)));
+
+        if let Some(name) = &self.name {
+            push("name LIKE {} ESCAPE '\\'", vec![like(name)]);
+        }
+        if let Some(surname) = &self.surname {
+            push(
+                "(surname = {} COLLATE NOCASE OR maiden_name = {} COLLATE NOCASE)",
+                vec![Value::Text(surname.clone()), Value::Text(surname.clone())],
+            );
+        }
+        if let Some((from, to)) = self.born {
+            push(
+                "birth_year BETWEEN {} AND {}",
+                vec![Value::Integer(from.into()), Value::Integer(to.into())],
+            );
+        }
+        if let Some((from, to)) = self.died {
+            push(
+                "death_year BETWEEN {} AND {}",
+                vec![Value::Integer(from.into()), Value::Integer(to.into())],
+            );
+        }
+        if let Some(place) = &self.born_in {
+            push("birth_location LIKE {} ESCAPE '\\'", vec![like(place)]);
+        }
+        if let Some(place) = &self.died_in {
+            push("death_location LIKE {} ESCAPE '\\'", vec![like(place)]);
+        }
+        if let Some(cemetery) = &self.cemetery {
+            push("burial_location LIKE {} ESCAPE '\\'", vec![like(cemetery)]);
+        }
+        if let Some(place) = &self.location {
+            push(
+                "(birth_location LIKE {} ESCAPE '\\' OR death_location LIKE {} ESCAPE '\\'
+                  OR burial_location LIKE {} ESCAPE '\\')",
+                vec![like(place), like(place), like(place)],
+            );
+        }
+        if let Some(plot) = &self.plot {
+            push("plot_details LIKE {} ESCAPE '\\'", vec![like(plot)]);
+        }
+        for tag in &self.tags {
+            push(
+                "id IN (SELECT memorial_id FROM tags WHERE tag = {})",
+                vec![Value::Text(tag.clone())],
+            );
+        }
+        if let Some(project) = &self.project {
+            push(
+                "id IN (SELECT pm.memorial_id FROM project_memorials pm
+                        JOIN projects p ON p.id = pm.project_id WHERE p.name = {})",
+                vec![Value::Text(project.clone())],
+            );
+        }
+
+        let flags = [
+            ("is_veteran", self.flags.veteran),
+            ("is_famous", self.flags.famous),
+            ("is_cenotaph", self.flags.cenotaph),
+            ("not_buried_here", self.flags.not_buried_here),
+        ];
+        for (column, flag) in flags {
+            if let Some(flag) = flag {
+                push(
+                    &format!("{} = {{}}", column),
+                    vec![Value::Integer(flag.into())],
+                );
+            }
+        }
+
+        let mut sql = String::new();
+        if !clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&clauses.join(" AND "));
+        }
+
+        // Memorials without the sort value go last in either direction
+        let column = self.sort.column();
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        sql.push_str(&format!(
+            " ORDER BY {} IS NULL, {} {}, name, id",
+            column, column, direction
+        ));
+
+        values.push(Value::Integer(self.limit as i64));
+        values.push(Value::Integer(self.offset as i64));
+        sql.push_str(&format!(
+            " LIMIT ?{} OFFSET ?{}",
+            values.len() - 1,
+            values.len()
+        ));
+
+        (sql, values)
+    }
+}
+
+/// Parse a year range such as `1860-1865`, `1860..1865` or a single `1863`
+pub fn parse_year_range(range: &str) -> Result<(i32, i32)> {
+    let invalid = || Error::InvalidInput(format!("Invalid year range: {}", range));
+    let parse = |year: &str| year.trim().parse::<i32>().map_err(|_| invalid());
+
+    let (from, to) = match range.split_once("..").or_else(|| range.split_once('-')) {
+        Some((from, to)) => (parse(from)?, parse(to)?),
+        None => {
+            let year = parse(range)?;
+            (year, year)
+        }
+    };
+
+    if from > to {
+        return Err(invalid());
+    }
+    Ok((from, to))
+}
+
+/// Escape `%`, `_` and the escape character itself for a `LIKE ... ESCAPE '\'` pattern
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_year_range() {
+        assert_eq!(parse_year_range("1860-1865").unwrap(), (1860, 1865));
+        assert_eq!(parse_year_range("1860..1865").unwrap(), (1860, 1865));
+        assert_eq!(parse_year_range("1863").unwrap(), (1863, 1863));
+        assert!(parse_year_range("1865-1860").is_err());
+        assert!(parse_year_range("civil war").is_err());
+    }
+
+    #[test]
+    fn test_query_binds_every_value() {
+        let (sql, values) = MemorialQuery::new()
+            .name("O'Brien")
+            .died_between(1860, 1865)
+            .location("Virginia")
+            .sort_by(SortField::DeathYear, true)
+            .limit(10)
+            .offset(20)
+            .to_sql();
+
+        assert!(!sql.contains("O'Brien"));
+        assert!(sql.contains("death_year BETWEEN ?2 AND ?3"));
+        assert!(sql.contains("ORDER BY death_year IS NULL, death_year DESC"));
+        assert!(sql.ends_with("LIMIT ?7 OFFSET ?8"));
+        assert_eq!(values.len(), 8);
+    }
+
+    #[test]
+    fn test_like_values_match_literally() {
+        let (sql, values) = MemorialQuery::new()
+            .plot("Row 5_A 100%")
+            .tag(" civil war ")
+            .to_sql();
+
+        assert!(sql.contains("plot_details LIKE ?1 ESCAPE '\\'"));
+        assert_eq!(values[0], Value::Text("%Row 5\\_A 100\\%%".to_string()));
+        assert_eq!(values[1], Value::Text("civil war".to_string()));
+    }
+}
@@ -0,0 +1,501 @@
+use crate::batch::{fetch_and_save, run_batch, SharedRepository};
+use crate::db::open_db;
+use crate::{BatchOptions, BatchSummary, Config, Error, Memorial, Result, ResultOrder};
+use futures::StreamExt;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Processing state of a queued memorial
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueState {
+    /// Waiting to be fetched
+    Pending,
+    /// Claimed by a running import
+    InFlight,
+    /// Fetched, parsed and stored
+    Done,
+    /// Last attempt failed
+    Failed,
+}
+
+impl QueueState {
+    /// Name of the state as stored in the database
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueueState::Pending => "pending",
+            QueueState::InFlight => "in_flight",
+            QueueState::Done => "done",
+            QueueState::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for QueueState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for QueueState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(QueueState::Pending),
+            "in_flight" => Ok(QueueState::InFlight),
+            "done" => Ok(QueueState::Done),
+            "failed" => Ok(QueueState::Failed),
+            other => Err(Error::DatabaseError(format!(
+                "Unknown queue state: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A memorial URL or ID waiting in the fetch queue
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueEntry {
+    /// Queue entry ID
+    pub id: i64,
+    /// URL or memorial ID as added
+    pub input: String,
+    /// Current processing state
+    pub state: QueueState,
+    /// Number of times the entry has been claimed
+    pub attempts: u32,
+    /// Error from the most recent failed attempt
+    pub last_error: Option<String>,
+    /// ID of the stored memorial once done
+    pub memorial_id: Option<u64>,
+}
+
+/// Number of queue entries in each state
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct QueueStatus {
+    /// Entries waiting to be fetched
+    pub pending: usize,
+    /// Entries claimed by a running import
+    pub in_flight: usize,
+    /// Entries fetched and stored
+    pub done: usize,
+    /// Entries whose last attempt failed
+    pub failed: usize,
+}
+
+/// Options controlling a queue run
+#[derive(Debug, Clone)]
+pub struct QueueRunOptions {
+    /// Maximum number of memorials fetched at the same time
+    pub concurrency: usize,
+    /// Failed entries are retried until they have been attempted this many times
+    pub max_attempts: u32,
+}
+
+impl Default for QueueRunOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            max_attempts: 3,
+        }
+    }
+}
+
+/// Add memorial URLs or IDs to the fetch queue
+///
+/// Inputs already in the queue are left untouched. Returns the number of
+/// newly added entries.
+pub fn enqueue(inputs: &[String], db_path: &str) -> Result<usize> {
+    let mut conn = open_db(db_path)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    let mut added = 0;
+    for input in inputs {
+        added += tx
+            .execute(
+                "INSERT OR IGNORE INTO fetch_queue (input) VALUES (?1)",
+                params![input],
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    }
+
+    tx.commit()
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    Ok(added)
+}
+
+/// Claim up to `limit` runnable entries and mark them in flight
+///
+/// Runnable entries are pending ones and failed ones with fewer than
+/// `max_attempts` attempts. Each claim counts as an attempt.
+pub fn claim_entries(limit: usize, max_attempts: u32, db_path: &str) -> Result<Vec<QueueEntry>> {
+    let mut conn = open_db(db_path)?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    let ids: Vec<i64> = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT id FROM fetch_queue
+             WHERE state = 'pending' OR (state = 'failed' AND attempts < ?1)
+             ORDER BY id LIMIT ?2",
+            )
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![max_attempts, limit as i64], |row| row.get(0))
+            .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| Error::DatabaseError(e.to_string()))?
+    };
+
+    let mut entries = Vec::with_capacity(ids.len());
+    for id in ids {
+        tx.execute(
+            "UPDATE fetch_queue
+             SET state = 'in_flight', attempts = attempts + 1, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+        entries.push(load_entry(&tx, id)?);
+    }
+
+    tx.commit()
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    Ok(entries)
+}
+
+/// Mark a queue entry as done
+pub fn mark_done(entry_id: i64, memorial_id: Option<u64>, db_path: &str) -> Result<()> {
+    let conn = open_db(db_path)?;
+    conn.execute(
+        "UPDATE fetch_queue
+         SET state = 'done', last_error = NULL, memorial_id = ?2, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1",
+        params![entry_id, memorial_id],
+    )
+    .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(())
+}
+
+/// Mark a queue entry as failed and record the error
+pub fn mark_failed(entry_id: i64, error: &str, db_path: &str) -> Result<()> {
+    let conn = open_db(db_path)?;
+    conn.execute(
+        "UPDATE fetch_queue
+         SET state = 'failed', last_error = ?2, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1",
+        params![entry_id, error],
+    )
+    .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    Ok(())
+}
+
+/// Return entries left in flight by an interrupted run to the pending state
+///
+/// Returns the number of recovered entries.
+pub fn recover_in_flight(db_path: &str) -> Result<usize> {
+    let conn = open_db(db_path)?;
+    conn.execute(
+        "UPDATE fetch_queue
+         SET state = 'pending', updated_at = CURRENT_TIMESTAMP
+         WHERE state = 'in_flight'",
+        [],
+    )
+    .map_err(|e| Error::DatabaseError(e.to_string()))
+}
+
+/// Count queue entries in each state
+pub fn queue_status(db_path: &str) -> Result<QueueStatus> {
+    let conn = open_db(db_path)?;
+    let mut stmt = conn
+        .prepare("SELECT state, COUNT(*) FROM fetch_queue GROUP BY state")
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    let mut status = QueueStatus::default();
+    for row in rows {
+        let (state, count) = row.map_err(|e| Error::DatabaseError(e.to_string()))?;
+        let count = count as usize;
+        match state.parse()? {
+            QueueState::Pending => status.pending = count,
+            QueueState::InFlight => status.in_flight = count,
+            QueueState::Done => status.done = count,
+            QueueState::Failed => status.failed = count,
+        }
+    }
+
+    Ok(status)
+}
+
+/// List queue entries, optionally only those in the given state
+pub fn list_entries(state: Option<QueueState>, db_path: &str) -> Result<Vec<QueueEntry>> {
+    let conn = open_db(db_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, input, state, attempts, last_error, memorial_id
+         FROM fetch_queue WHERE ?1 IS NULL OR state = ?1 ORDER BY id",
+        )
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+    let rows = stmt
+        .query_map(params![state.map(|s| s.as_str())], row_to_entry)
+        .map_err(|e| Error::DatabaseError(e.to_string()))?;
+
+    let mut entries = Vec::new();
+    for row in rows {
+        entries.push(row.map_err(|e| Error::DatabaseError(e.to_string()))?);
+    }
+    Ok(entries)
+}
+
+/// Work through the fetch queue until no runnable entries remain
+///
+/// Entries left in flight by an earlier interrupted run are resumed first.
+/// Fetched memorials are stored in `config.db_path`, the same database that
+/// holds the queue. `on_item` is called as each entry finishes.
+pub async fn run_queue<F>(
+    config: &Config,
+    options: &QueueRunOptions,
+    on_item: F,
+) -> Result<BatchSummary>
+where
+    F: FnMut(&QueueEntry, &Result<Memorial>),
+{
+    // Opened once for the whole run, not for every claimed batch
+    let repository = SharedRepository::default();
+    run_queue_with(&config.db_path, options, on_item, |input| {
+        fetch_and_save(input, config, true, Arc::clone(&repository))
+    })
+    .await
+}
+
+/// Work through the queue in `db_path`, fetching and storing each entry with `fetch`
+async fn run_queue_with<F, P, Fut>(
+    db_path: &str,
+    options: &QueueRunOptions,
+    mut on_item: F,
+    fetch: P,
+) -> Result<BatchSummary>
+where
+    F: FnMut(&QueueEntry, &Result<Memorial>),
+    P: Fn(String) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Memorial>> + Send,
+{
+    recover_in_flight(db_path)?;
+
+    let batch_options = BatchOptions {
+        concurrency: options.concurrency,
+        order: ResultOrder::Completion,
+        store_to_db: true,
+    };
+    let claim_size = options.concurrency.max(1);
+
+    let mut summary = BatchSummary::default();
+    loop {
+        let entries = claim_entries(claim_size, options.max_attempts, db_path)?;
+        if entries.is_empty() {
+            break;
+        }
+
+        let inputs: Vec<String> = entries.iter().map(|entry| entry.input.clone()).collect();
+        let mut results = run_batch(inputs, &batch_options, &fetch);
+        while let Some(item) = results.next().await {
+            let entry = &entries[item.index];
+            match &item.result {
+                Ok(memorial) => mark_done(entry.id, memorial.id, db_path)?,
+                Err(err) => mark_failed(entry.id, &err.to_string(), db_path)?,
+            }
+            summary.record(&item);
+            on_item(entry, &item.result);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Load a single queue entry by ID
+fn load_entry(conn: &Connection, id: i64) -> Result<QueueEntry> {
+    conn.query_row(
+        "SELECT id, input, state, attempts, last_error, memorial_id
+         FROM fetch_queue WHERE id = ?1",
+        params![id],
+        row_to_entry,
+    )
+    .map_err(|e| Error::DatabaseError(e.to_string()))
+}
+
+/// Map a queue row to an entry
+fn row_to_entry(row: &rusqlite::Row) -> rusqlite::Result<QueueEntry> {
+    let state: String = row.get(2)?;
+    let state = state.parse().map_err(|e: Error| {
+        rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(e))
+    })?;
+
+    Ok(QueueEntry {
+        id: row.get(0)?,
+        input: row.get(1)?,
+        state,
+        attempts: row.get(3)?,
+        last_error: row.get(4)?,
+        memorial_id: row.get(5)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn inputs(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    /// Numeric inputs succeed as that memorial; anything else fails
+    async fn fake_fetch(input: String) -> Result<Memorial> {
+        let id = input
+            .parse()
+            .map_err(|_| Error::InvalidInput(format!("Not a memorial: {}", input)))?;
+        Ok(Memorial {
+            id: Some(id),
+            ..Memorial::default()
+        })
+    }
+
+    #[test]
+    fn test_enqueue_ignores_duplicates() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("queue.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        assert_eq!(enqueue(&inputs(&["1", "2"]), db_path_str).unwrap(), 2);
+        assert_eq!(enqueue(&inputs(&["2", "3"]), db_path_str).unwrap(), 1);
+
+        let status = queue_status(db_path_str).unwrap();
+        assert_eq!(status.pending, 3);
+    }
+
+    #[test]
+    fn test_claim_and_mark() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("queue.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        enqueue(&inputs(&["1", "2", "3"]), db_path_str).unwrap();
+
+        let claimed = claim_entries(2, 3, db_path_str).unwrap();
+        assert_eq!(claimed.len(), 2);
+        assert_eq!(claimed[0].state, QueueState::InFlight);
+        assert_eq!(claimed[0].attempts, 1);
+
+        mark_done(claimed[0].id, Some(1), db_path_str).unwrap();
+        mark_failed(claimed[1].id, "timed out", db_path_str).unwrap();
+
+        let status = queue_status(db_path_str).unwrap();
+        assert_eq!(
+            status,
+            QueueStatus {
+                pending: 1,
+                in_flight: 0,
+                done: 1,
+                failed: 1
+            }
+        );
+
+        let failed = list_entries(Some(QueueState::Failed), db_path_str).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].input, "2");
+        assert_eq!(failed[0].last_error.as_deref(), Some("timed out"));
+    }
+
+    #[test]
+    fn test_failed_entries_retry_until_max_attempts() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("queue.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        enqueue(&inputs(&["1"]), db_path_str).unwrap();
+
+        for _ in 0..2 {
+            let claimed = claim_entries(10, 2, db_path_str).unwrap();
+            assert_eq!(claimed.len(), 1);
+            mark_failed(claimed[0].id, "boom", db_path_str).unwrap();
+        }
+
+        assert!(claim_entries(10, 2, db_path_str).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_queue_marks_entries_and_retries_failures() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("queue.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        enqueue(&inputs(&["1", "bad", "2"]), db_path_str).unwrap();
+        let options = QueueRunOptions {
+            concurrency: 2,
+            max_attempts: 3,
+        };
+        let mut attempts = Vec::new();
+        let summary = run_queue_with(
+            db_path_str,
+            &options,
+            |entry, result| attempts.push((entry.input.clone(), result.is_ok())),
+            fake_fetch,
+        )
+        .await
+        .unwrap();
+
+        // The bad entry is claimed again until it reaches max_attempts
+        assert_eq!(
+            (summary.total, summary.succeeded, summary.failed),
+            (5, 2, 3)
+        );
+        let bad = attempts.iter().filter(|(input, _)| input == "bad");
+        assert!(bad.clone().all(|(_, ok)| !ok));
+        assert_eq!(bad.count(), 3);
+
+        let done = list_entries(Some(QueueState::Done), db_path_str).unwrap();
+        let stored: Vec<_> = done.iter().map(|entry| entry.memorial_id).collect();
+        assert_eq!(stored, [Some(1), Some(2)]);
+        let failed = list_entries(Some(QueueState::Failed), db_path_str).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].attempts, 3);
+        assert!(failed[0].last_error.as_deref().unwrap().contains("bad"));
+
+        // Nothing is left to run
+        let again = run_queue_with(db_path_str, &options, |_, _| {}, fake_fetch)
+            .await
+            .unwrap();
+        assert_eq!(again.total, 0);
+    }
+
+    #[test]
+    fn test_recover_in_flight() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("queue.db");
+        let db_path_str = db_path.to_str().unwrap();
+
+        enqueue(&inputs(&["1", "2"]), db_path_str).unwrap();
+        claim_entries(10, 3, db_path_str).unwrap();
+
+        // Simulate a crash: both entries are stuck in flight
+        assert_eq!(queue_status(db_path_str).unwrap().in_flight, 2);
+        assert_eq!(recover_in_flight(db_path_str).unwrap(), 2);
+        assert_eq!(queue_status(db_path_str).unwrap().pending, 2);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::batch::run_batch;
+use crate::db::MemorialStore;
+use crate::{
+    fetch_memorial, BatchOptions, Config, Error, FieldChange, Memorial, Result, ResultOrder,
+};
+use futures::StreamExt;
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+
+/// Options controlling a refresh run
+#[derive(Debug, Clone)]
+pub struct RefreshOptions {
+    /// Refresh memorials not updated or checked for at least this long
+    pub max_age: Duration,
+    /// Maximum number of memorials fetched at the same time
+    pub concurrency: usize,
+    /// Report changes without writing them to the database
+    pub dry_run: bool,
+}
+
+/// Outcome of refreshing a single stored memorial
+#[derive(Debug, Serialize)]
+pub struct RefreshReport {
+    /// Memorial ID
+    pub memorial_id: u64,
+    /// Name of the memorial as stored before the refresh
+    pub name: String,
+    /// Fields that changed since the stored copy, empty if unchanged
+    pub changes: Vec<FieldChange>,
+    /// Error that stopped the refresh, if any
+    pub error: Option<String>,
+}
+
+/// Totals for a refresh run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RefreshSummary {
+    /// Number of stale memorials re-fetched
+    pub checked: usize,
+    /// Number of memorials with at least one changed field
+    pub changed: usize,
+    /// Number of memorials that could not be refreshed
+    pub failed: usize,
+}
+
+/// Parse an age such as `30d`, `12h`, `90m`, `45s` or `2w`
+///
+/// A bare number is read as days.
+pub fn parse_age(age: &str) -> Result<Duration> {
+    let age = age.trim();
+    let (number, unit) = match age.find(|c: char| !c.is_ascii_digit()) {
+        Some(pos) => age.split_at(pos),
+        None => (age, "d"),
+    };
+
+    let value: u64 = number
+        .parse()
+        .map_err(|_| Error::InvalidInput(format!("Invalid age: {}", age)))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(Error::InvalidInput(format!("Invalid age unit: {}", age))),
+    };
+
+    let seconds = value
+        .checked_mul(seconds)
+        .ok_or_else(|| Error::InvalidInput(format!("Age is too large: {}", age)))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Re-fetch stale memorials and write back only those that changed
+///
+/// Each re-fetched memorial is compared field by field with the stored row.
+/// Changed memorials are stored again; unchanged ones only have their check
+/// time recorded so they are not fetched again until they go stale. `on_report`
+/// is called as each memorial finishes.
+pub async fn refresh_memorials<F>(
+    config: &Config,
+    options: &RefreshOptions,
+    on_report: F,
+) -> Result<RefreshSummary>
+where
+    F: FnMut(&RefreshReport),
+{
+    refresh_with(&config.db_path, options, on_report, |input| async move {
+        fetch_memorial(&input, config)
+            .await
+            .map(|(memorial, _, _)| memorial)
+    })
+    .await
+}
+
+/// Refresh the stale memorials in `db_path`, re-fetching each with `fetch`
+async fn refresh_with<F, P, Fut>(
+    db_path: &str,
+    options: &RefreshOptions,
+    mut on_report: F,
+    fetch: P,
+) -> Result<RefreshSummary>
+where
+    F: FnMut(&RefreshReport),
+    P: Fn(String) -> Fut + Send,
+    Fut: Future<Output = Result<Memorial>> + Send,
+{
+    let mut store = MemorialStore::open(db_path)?;
+    let ids = store.stale_ids(options.max_age)?;
+
+    let batch_options = BatchOptions {
+        concurrency: options.concurrency,
+        order: ResultOrder::Completion,
+        store_to_db: false,
+    };
+
+    let inputs: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+    let mut results = run_batch(inputs, &batch_options, fetch);
+
+    let mut summary = RefreshSummary::default();
+    while let Some(item) = results.next().await {
+        let memorial_id = ids[item.index];
+        let stored = store.get(memorial_id)?.unwrap_or_default();
+
+        let mut report = RefreshReport {
+            memorial_id,
+            name: stored.name.clone(),
+            changes: Vec::new(),
+            error: None,
+        };
+
+        summary.checked += 1;
+        match item.result {
+            Ok(mut fetched) => {
+                // Keep the stored ID when the page does not expose one
+                fetched.id = fetched.id.or(Some(memorial_id));
+                report.changes = stored.diff(&fetched);
+
+                if !report.changes.is_empty() {
+                    summary.changed += 1;
+                }
+                if !options.dry_run {
+                    if report.changes.is_empty() {
+                        store.mark_checked(memorial_id)?;
+                    } else {
+                        store.store(&fetched)?;
+                    }
+                }
+            }
+            Err(err) => {
+                summary.failed += 1;
+                report.error = Some(err.to_string());
+            }
+        }
+
+        on_report(&report);
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::list_revisions;
+    use tempfile::tempdir;
+
+    fn memorial(id: u64, name: &str) -> Memorial {
+        Memorial {
+            id: Some(id),
+            name: name.to_string(),
+            ..Memorial::default()
+        }
+    }
+
+    /// Memorial 1 is unchanged on the site, memorial 2 was renamed
+    async fn fake_fetch(input: String) -> Result<Memorial> {
+        match input.as_str() {
+            "1" => Ok(memorial(1, "Jane Doe")),
+            "2" => Ok(memorial(2, "John Henry Smith")),
+            _ => Err(Error::InvalidInput(input)),
+        }
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_age("2h").unwrap(), Duration::from_secs(7_200));
+        assert_eq!(parse_age("30d").unwrap(), Duration::from_secs(2_592_000));
+        assert_eq!(parse_age("7").unwrap(), Duration::from_secs(604_800));
+        assert_eq!(parse_age("1w").unwrap(), Duration::from_secs(604_800));
+        assert!(parse_age("soon").is_err());
+        assert!(parse_age("3y").is_err());
+        assert!(parse_age("18446744073709551615w").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_marks_unchanged_and_stores_changed() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path = db_path.to_str().unwrap();
+
+        let mut store = MemorialStore::open(db_path).unwrap();
+        store.store(&memorial(1, "Jane Doe")).unwrap();
+        store.store(&memorial(2, "John Smith")).unwrap();
+        drop(store);
+        rusqlite::Connection::open(db_path)
+            .unwrap()
+            .execute(
+                "UPDATE memorials SET updated_at = datetime('now', '-2 days')",
+                [],
+            )
+            .unwrap();
+
+        let options = RefreshOptions {
+            max_age: Duration::from_secs(86_400),
+            concurrency: 2,
+            dry_run: false,
+        };
+        let mut reports = Vec::new();
+        let summary = refresh_with(
+            db_path,
+            &options,
+            |r| reports.push((r.memorial_id, r.changes.clone())),
+            fake_fetch,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            summary,
+            RefreshSummary {
+                checked: 2,
+                changed: 1,
+                failed: 0
+            }
+        );
+
+        reports.sort_by_key(|(id, _)| *id);
+        assert!(reports[0].1.is_empty());
+        assert_eq!(reports[1].1.len(), 1);
+        assert_eq!(reports[1].1[0].field, "name");
+
+        // Unchanged: only checked, no new revision
+        assert_eq!(list_revisions(1, db_path).unwrap().len(), 1);
+        // Changed: stored again as a new revision
+        assert_eq!(list_revisions(2, db_path).unwrap().len(), 2);
+        let store = MemorialStore::open(db_path).unwrap();
+        assert_eq!(store.get(2).unwrap().unwrap().name, "John Henry Smith");
+        assert!(store.stale_ids(options.max_age).unwrap().is_empty());
+    }
+}
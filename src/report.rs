@@ -0,0 +1,422 @@
+use crate::db::MemorialStore;
+use crate::geo::escape_xml;
+use crate::{Memorial, MemorialQuery, Result, SortField};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Heading for memorials whose surname or cemetery is not known
+const UNKNOWN: &str = "Unknown";
+
+const STYLE: &str = "body { font-family: Georgia, serif; max-width: 50em; margin: 2em auto; \
+padding: 0 1em; color: #222; line-height: 1.5; }
+nav { margin-bottom: 1.5em; font-family: sans-serif; font-size: 0.9em; }
+nav a { margin-right: 1em; }
+a { color: #2a5d8f; }
+h2 { border-bottom: 1px solid #ccc; }
+pre.citation { background: #f6f4ef; padding: 1em; white-space: pre-wrap; }
+table.details th { text-align: left; padding-right: 1em; vertical-align: top; }
+.letters a { margin-right: 0.4em; }
+.dates { color: #666; }
+#search { width: 100%; font-size: 1.1em; padding: 0.4em; box-sizing: border-box; }
+";
+
+const SEARCH_SCRIPT: &str = "(function () {
+  var input = document.getElementById('search');
+  var results = document.getElementById('results');
+  if (!input || !results || !window.SEARCH_INDEX) return;
+  input.addEventListener('input', function () {
+    var terms = input.value.toLowerCase().split(/\\s+/).filter(Boolean);
+    results.innerHTML = '';
+    if (!terms.length) return;
+    window.SEARCH_INDEX.filter(function (entry) {
+      var text = entry.text.toLowerCase();
+      return terms.every(function (term) { return text.indexOf(term) !== -1; });
+    }).slice(0, 100).forEach(function (entry) {
+      var item = document.createElement('li');
+      var link = document.createElement('a');
+      link.href = entry.url;
+      link.textContent = entry.name;
+      item.appendChild(link);
+      if (entry.dates) {
+        var dates = document.createElement('span');
+        dates.className = 'dates';
+        dates.textContent = ' ' + entry.dates;
+        item.appendChild(dates);
+      }
+      results.appendChild(item);
+    });
+  });
+})();
+";
+
+/// Options for generating an HTML report
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Title shown on every page
+    pub title: String,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            title: "Memorial research report".to_string(),
+        }
+    }
+}
+
+/// What a generated report contains
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReportSummary {
+    /// Number of memorial pages written
+    pub memorials: usize,
+    /// Number of distinct surnames in the index
+    pub surnames: usize,
+    /// Number of cemetery pages written
+    pub cemeteries: usize,
+}
+
+/// Render the stored memorials as a static HTML site in `out_dir`
+///
+/// The site has a surname index with a search box, one page per memorial
+/// with its citation, and a page per burial location. Styles, scripts and
+/// the search index are written alongside the pages, so the site works
+/// offline straight from disk.
+pub fn write_report(
+    store: &MemorialStore,
+    out_dir: &Path,
+    options: &ReportOptions,
+) -> Result<ReportSummary> {
+    let memorials = store.query_all(&MemorialQuery::new().sort_by(SortField::Id, false))?;
+
+    fs::create_dir_all(out_dir.join("memorials"))?;
+    fs::create_dir_all(out_dir.join("cemeteries"))?;
+    fs::write(out_dir.join("style.css"), STYLE)?;
+    fs::write(out_dir.join("search.js"), SEARCH_SCRIPT)?;
+
+    let cemeteries = group_by(&memorials, |memorial| {
+        memorial
+            .burial_location
+            .as_deref()
+            .map(str::trim)
+            .filter(|cemetery| !cemetery.is_empty())
+            .unwrap_or(UNKNOWN)
+            .to_string()
+    });
+    let slugs = cemetery_slugs(cemeteries.keys());
+
+    for memorial in &memorials {
+        let cemetery = memorial
+            .burial_location
+            .as_deref()
+            .map(str::trim)
+            .and_then(|cemetery| slugs.get(cemetery));
+        let accessed = store.last_fetched(memorial.id.unwrap_or_default())?;
+        fs::write(
+            out_dir.join(format!(
+                "memorials/{}.html",
+                memorial.id.unwrap_or_default()
+            )),
+            memorial_page(memorial, cemetery, accessed.as_deref(), options),
+        )?;
+    }
+
+    for (cemetery, members) in &cemeteries {
+        let body = format!(
+            "<h1>{}</h1>\n<p>{} memorials</p>\n<ul>\n{}</ul>\n",
+            escape_xml(cemetery),
+            members.len(),
+            memorial_list(members, "../")
+        );
+        fs::write(
+            out_dir.join(format!("cemeteries/{}.html", slugs[cemetery.as_str()])),
+            page(cemetery, &body, "../", options),
+        )?;
+    }
+
+    let mut body = String::from("<h1>Cemeteries</h1>\n<ul>\n");
+    for (cemetery, members) in &cemeteries {
+        body.push_str(&format!(
+            "<li><a href=\"cemeteries/{}.html\">{}</a> <span class=\"dates\">({})</span></li>\n",
+            slugs[cemetery.as_str()],
+            escape_xml(cemetery),
+            members.len()
+        ));
+    }
+    body.push_str("</ul>\n");
+    fs::write(
+        out_dir.join("cemeteries.html"),
+        page("Cemeteries", &body, "", options),
+    )?;
+
+    let surnames = group_by(&memorials, |memorial| {
+        memorial
+            .parsed_name()
+            .surname
+            .unwrap_or_else(|| UNKNOWN.to_string())
+    });
+    fs::write(out_dir.join("index.html"), index_page(&surnames, options))?;
+    fs::write(out_dir.join("search-index.js"), search_index(&memorials))?;
+
+    Ok(ReportSummary {
+        memorials: memorials.len(),
+        surnames: surnames.len(),
+        cemeteries: cemeteries.len(),
+    })
+}
+
+/// Group memorials under a heading, sorting headings and members by name
+fn group_by<F>(memorials: &[Memorial], key: F) -> BTreeMap<String, Vec<&Memorial>>
+where
+    F: Fn(&Memorial) -> String,
+{
+    let mut groups: BTreeMap<String, Vec<&Memorial>> = BTreeMap::new();
+    for memorial in memorials {
+        groups.entry(key(memorial)).or_default().push(memorial);
+    }
+    for members in groups.values_mut() {
+        members.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+    }
+    groups
+}
+
+/// File names for cemetery pages, made unique when names differ only in punctuation
+fn cemetery_slugs<'a>(cemeteries: impl Iterator<Item = &'a String>) -> HashMap<&'a str, String> {
+    let mut slugs = HashMap::new();
+    let mut used: HashMap<String, usize> = HashMap::new();
+    for cemetery in cemeteries {
+        let mut slug = cemetery
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        if slug.is_empty() {
+            slug = "cemetery".to_string();
+        }
+        let count = used.entry(slug.clone()).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            slug = format!("{}-{}", slug, count);
+        }
+        slugs.insert(cemetery.as_str(), slug);
+    }
+    slugs
+}
+
+/// Wrap page content in the shared layout; `root` leads back to the site root
+fn page(title: &str, body: &str, root: &str, options: &ReportOptions) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title} - {site}</title>\n<link rel=\"stylesheet\" href=\"{root}style.css\">\n\
+         </head>\n<body>\n<nav><a href=\"{root}index.html\">{site}</a>\
+         <a href=\"{root}cemeteries.html\">Cemeteries</a></nav>\n{body}</body>\n</html>\n",
+        title = escape_xml(title),
+        site = escape_xml(&options.title),
+        root = root,
+        body = body
+    )
+}
+
+/// Life dates shown next to a name, such as `1820 – 1890`
+fn life_dates(memorial: &Memorial) -> String {
+    match (&memorial.birth_date, &memorial.death_date) {
+        (None, None) => String::new(),
+        (birth, death) => format!(
+            "{} – {}",
+            birth.as_deref().unwrap_or("?"),
+            death.as_deref().unwrap_or("?")
+        ),
+    }
+}
+
+fn memorial_list(memorials: &[&Memorial], root: &str) -> String {
+    memorials
+        .iter()
+        .map(|memorial| {
+            format!(
+                "<li><a href=\"{}memorials/{}.html\">{}</a> <span class=\"dates\">{}</span></li>\n",
+                root,
+                memorial.id.unwrap_or_default(),
+                escape_xml(&memorial.name),
+                escape_xml(&life_dates(memorial))
+            )
+        })
+        .collect()
+}
+
+fn index_page(surnames: &BTreeMap<String, Vec<&Memorial>>, options: &ReportOptions) -> String {
+    let letters: BTreeSet<char> = surnames
+        .keys()
+        .filter_map(|surname| surname.chars().next())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    let mut body = format!(
+        "<h1>{}</h1>\n<input id=\"search\" type=\"search\" placeholder=\"Search names, dates \
+         and cemeteries\" autocomplete=\"off\">\n<ul id=\"results\"></ul>\n<p class=\"letters\">",
+        escape_xml(&options.title)
+    );
+    for letter in &letters {
+        body.push_str(&format!(
+            "<a href=\"#letter-{0}\">{0}</a>",
+            escape_xml(&letter.to_string())
+        ));
+    }
+    body.push_str("</p>\n");
+
+    let mut anchored = BTreeSet::new();
+    for (surname, members) in surnames {
+        // The first surname under each letter is the letter's jump target
+        let anchor = match surname.chars().next().map(|c| c.to_ascii_uppercase()) {
+            Some(letter) if anchored.insert(letter) => {
+                format!(" id=\"letter-{}\"", escape_xml(&letter.to_string()))
+            }
+            _ => String::new(),
+        };
+        body.push_str(&format!(
+            "<h2{}>{}</h2>\n<ul>\n{}</ul>\n",
+            anchor,
+            escape_xml(surname),
+            memorial_list(members, "")
+        ));
+    }
+    body.push_str(
+        "<script src=\"search-index.js\"></script>\n<script src=\"search.js\"></script>\n",
+    );
+
+    page("Surname index", &body, "", options)
+}
+
+fn memorial_page(
+    memorial: &Memorial,
+    cemetery_slug: Option<&String>,
+    accessed: Option<&str>,
+    options: &ReportOptions,
+) -> String {
+    let url = memorial.url().unwrap_or_default();
+    let citation = memorial.to_sourced_citation(accessed);
+
+    let mut body = format!(
+        "<h1>{}</h1>\n<p class=\"dates\">{}</p>\n<h2>Citation</h2>\n\
+         <pre class=\"citation\">{}</pre>\n<h2>Details</h2>\n<table class=\"details\">\n",
+        escape_xml(&memorial.name),
+        escape_xml(&life_dates(memorial)),
+        escape_xml(&citation)
+    );
+    for (field, value) in memorial.fields() {
+        let Some(value) = value.filter(|value| !value.is_empty() && value != "false") else {
+            continue;
+        };
+        let value = match (field, cemetery_slug) {
+            ("burial_location", Some(slug)) => format!(
+                "<a href=\"../cemeteries/{}.html\">{}</a>",
+                slug,
+                escape_xml(&value)
+            ),
+            _ => escape_xml(&value),
+        };
+        body.push_str(&format!(
+            "<tr><th>{}</th><td>{}</td></tr>\n",
+            escape_xml(&field.replace('_', " ")),
+            value
+        ));
+    }
+    body.push_str(&format!(
+        "</table>\n<p><a href=\"{0}\">{0}</a></p>\n",
+        escape_xml(&url)
+    ));
+
+    page(&memorial.name, &body, "../", options)
+}
+
+/// Script that loads every memorial's searchable text for the search box
+fn search_index(memorials: &[Memorial]) -> String {
+    let entries: Vec<_> = memorials
+        .iter()
+        .map(|memorial| {
+            let dates = life_dates(memorial);
+            let text = [
+                Some(memorial.name.as_str()),
+                Some(dates.as_str()),
+                memorial.birth_location.as_deref(),
+                memorial.death_location.as_deref(),
+                memorial.burial_location.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+            json!({
+                "name": memorial.name,
+                "dates": dates,
+                "url": format!("memorials/{}.html", memorial.id.unwrap_or_default()),
+                "text": text,
+            })
+        })
+        .collect();
+    format!(
+        "window.SEARCH_INDEX = {};\n",
+        serde_json::Value::Array(entries)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_report() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        for (id, name, cemetery) in [
+            (1, "John Smith", Some("Oak Hill")),
+            (2, "Mary <Polly> Smith", Some("Oak Hill")),
+            (3, "Ann Brown", Some("St. Mary's")),
+            (4, "Tom Jones", None),
+        ] {
+            store
+                .store(&Memorial {
+                    id: Some(id),
+                    name: name.to_string(),
+                    death_date: Some("1901".to_string()),
+                    burial_location: cemetery.map(str::to_string),
+                    ..Memorial::default()
+                })
+                .unwrap();
+        }
+
+        let dir = tempdir().unwrap();
+        let summary = write_report(&store, dir.path(), &ReportOptions::default()).unwrap();
+        assert_eq!(
+            summary,
+            ReportSummary {
+                memorials: 4,
+                surnames: 3,
+                cemeteries: 3,
+            }
+        );
+
+        let read = |path: &str| fs::read_to_string(dir.path().join(path)).unwrap();
+        let index = read("index.html");
+        assert!(index.find("Brown").unwrap() < index.find("Smith").unwrap());
+        assert!(index.contains("Mary &lt;Polly&gt; Smith"));
+        assert!(read("search-index.js").contains("\"url\":\"memorials/2.html\""));
+
+        let page = read("memorials/3.html");
+        assert!(page.contains("Burial: St. Mary&apos;s"));
+        assert!(page.contains("<a href=\"../cemeteries/st-mary-s.html\">"));
+        assert!(page.contains("Accessed: "));
+        assert!(read("cemeteries/oak-hill.html").contains("memorials/2.html"));
+        assert!(read("cemeteries/unknown.html").contains("Tom Jones"));
+
+        // Everything the pages load is written alongside them
+        for file in ["index.html", "memorials/1.html", "cemeteries.html"] {
+            let html = read(file);
+            assert!(!html.contains("src=\"http") && !html.contains("stylesheet\" href=\"http"));
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use crate::db::{MemorialStore, SearchFilters};
+use crate::{Error, Memorial, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Maximum number of results returned by a name search
+const SEARCH_LIMIT: usize = 100;
+
+/// Storage for parsed memorials, independent of the backing format
+pub trait MemorialRepository {
+    /// Insert or update a memorial and return its ID
+    ///
+    /// Memorials without an ID are assigned one by the repository.
+    fn store(&mut self, memorial: &Memorial) -> Result<u64>;
+
+    /// Retrieve a memorial by ID
+    fn get(&self, id: u64) -> Result<Option<Memorial>>;
+
+    /// Search memorials by name, restricted by flag filters
+    fn search(&self, name: &str, filters: &SearchFilters) -> Result<Vec<Memorial>>;
+
+    /// List stored memorials ordered by ID
+    fn list(&self, limit: usize, offset: usize) -> Result<Vec<Memorial>>;
+
+    /// Delete a memorial, returning `false` if no memorial had that ID
+    fn delete(&mut self, id: u64) -> Result<bool>;
+}
+
+/// Storage backend selected in [`Config`](crate::Config)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageBackend {
+    /// SQLite database file
+    #[default]
+    Sqlite,
+    /// Append-only JSON-lines file
+    JsonLines,
+}
+
+impl FromStr for StorageBackend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sqlite" => Ok(StorageBackend::Sqlite),
+            "jsonl" | "json-lines" => Ok(StorageBackend::JsonLines),
+            other => Err(Error::InvalidInput(format!(
+                "Unknown storage backend: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Open the repository for a backend at the given path
+pub fn open_repository(
+    backend: StorageBackend,
+    path: &str,
+) -> Result<Box<dyn MemorialRepository + Send>> {
+    Ok(match backend {
+        StorageBackend::Sqlite => Box::new(MemorialStore::open(path)?),
+        StorageBackend::JsonLines => Box::new(JsonLinesRepository::open(path)?),
+    })
+}
+
+impl MemorialRepository for MemorialStore {
+    fn store(&mut self, memorial: &Memorial) -> Result<u64> {
+        MemorialStore::store(self, memorial)
+    }
+
+    fn get(&self, id: u64) -> Result<Option<Memorial>> {
+        MemorialStore::get(self, id)
+    }
+
+    fn search(&self, name: &str, filters: &SearchFilters) -> Result<Vec<Memorial>> {
+        MemorialStore::search(self, name, filters)
+    }
+
+    fn list(&self, limit: usize, offset: usize) -> Result<Vec<Memorial>> {
+        MemorialStore::list(self, limit, offset)
+    }
+
+    fn delete(&mut self, id: u64) -> Result<bool> {
+        MemorialStore::delete(self, id)
+    }
+}
+
+/// Repository that keeps memorials in memory only, mainly for tests
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryRepository {
+    memorials: BTreeMap<u64, Memorial>,
+}
+
+impl InMemoryRepository {
+    /// Create an empty repository
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MemorialRepository for InMemoryRepository {
+    fn store(&mut self, memorial: &Memorial) -> Result<u64> {
+        Ok(insert_memorial(&mut self.memorials, memorial))
+    }
+
+    fn get(&self, id: u64) -> Result<Option<Memorial>> {
+        Ok(self.memorials.get(&id).cloned())
+    }
+
+    fn search(&self, name: &str, filters: &SearchFilters) -> Result<Vec<Memorial>> {
+        Ok(search_map(&self.memorials, name, filters))
+    }
+
+    fn list(&self, limit: usize, offset: usize) -> Result<Vec<Memorial>> {
+        Ok(self
+            .memorials
+            .values()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    fn delete(&mut self, id: u64) -> Result<bool> {
+        Ok(self.memorials.remove(&id).is_some())
+    }
+}
+
+/// A single line in a JSON-lines repository file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JsonLinesRecord {
+    /// A memorial was stored or updated
+    Store { memorial: Box<Memorial> },
+    /// A memorial was deleted
+    Delete { id: u64 },
+}
+
+/// Repository backed by an append-only JSON-lines file
+///
+/// Every store or delete appends one line, so the file diffs cleanly under
+/// version control. The latest line for an ID wins when the file is replayed
+/// on open.
+#[derive(Debug)]
+pub struct JsonLinesRepository {
+    path: PathBuf,
+    memorials: BTreeMap<u64, Memorial>,
+}
+
+impl JsonLinesRepository {
+    /// Open a JSON-lines file, replaying its history, or start a new one
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut memorials = BTreeMap::new();
+
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path)?);
+            for (number, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record: JsonLinesRecord = serde_json::from_str(&line).map_err(|e| {
+                    Error::DatabaseError(format!(
+                        "{}:{}: invalid record: {}",
+                        path.display(),
+                        number + 1,
+                        e
+                    ))
+                })?;
+                match record {
+                    JsonLinesRecord::Store { memorial } => {
+                        insert_memorial(&mut memorials, &memorial);
+                    }
+                    JsonLinesRecord::Delete { id } => {
+                        memorials.remove(&id);
+                    }
+                }
+            }
+        }
+
+        Ok(Self { path, memorials })
+    }
+
+    /// Append a record to the end of the file
+    fn append(&self, record: &JsonLinesRecord) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        Ok(())
+    }
+}
+
+impl MemorialRepository for JsonLinesRepository {
+    fn store(&mut self, memorial: &Memorial) -> Result<u64> {
+        let id = insert_memorial(&mut self.memorials, memorial);
+        self.append(&JsonLinesRecord::Store {
+            memorial: Box::new(self.memorials[&id].clone()),
+        })?;
+        Ok(id)
+    }
+
+    fn get(&self, id: u64) -> Result<Option<Memorial>> {
+        Ok(self.memorials.get(&id).cloned())
+    }
+
+    fn search(&self, name: &str, filters: &SearchFilters) -> Result<Vec<Memorial>> {
+        Ok(search_map(&self.memorials, name, filters))
+    }
+
+    fn list(&self, limit: usize, offset: usize) -> Result<Vec<Memorial>> {
+        Ok(self
+            .memorials
+            .values()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    fn delete(&mut self, id: u64) -> Result<bool> {
+        if self.memorials.remove(&id).is_none() {
+            return Ok(false);
+        }
+        self.append(&JsonLinesRecord::Delete { id })?;
+        Ok(true)
+    }
+}
+
+/// Insert a memorial into a map, assigning the next free ID when it has none
+fn insert_memorial(memorials: &mut BTreeMap<u64, Memorial>, memorial: &Memorial) -> u64 {
+    let id = memorial
+        .id
+        .unwrap_or_else(|| memorials.keys().next_back().map_or(1, |last| last + 1));
+    let mut memorial = memorial.clone();
+    memorial.id = Some(id);
+    memorials.insert(id, memorial);
+    id
+}
+
+/// Case-insensitive substring search over a map, matching the SQLite behaviour
+fn search_map(
+    memorials: &BTreeMap<u64, Memorial>,
+    name: &str,
+    filters: &SearchFilters,
+) -> Vec<Memorial> {
+    let needle = name.to_lowercase();
+    let mut results: Vec<Memorial> = memorials
+        .values()
+        .filter(|m| m.name.to_lowercase().contains(&needle) && filters.matches(m))
+        .cloned()
+        .collect();
+    results.sort_by(|a, b| a.name.cmp(&b.name));
+    results.truncate(SEARCH_LIMIT);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn memorial(id: Option<u64>, name: &str) -> Memorial {
+        Memorial {
+            id,
+            name: name.to_string(),
+            ..Memorial::default()
+        }
+    }
+
+    /// Exercise the full trait contract against any repository
+    fn check_repository(repo: &mut dyn MemorialRepository) {
+        assert_eq!(repo.store(&memorial(Some(10), "John Smith")).unwrap(), 10);
+        let mut veteran = memorial(Some(20), "Jane Smith");
+        veteran.is_veteran = true;
+        repo.store(&veteran).unwrap();
+        let assigned = repo.store(&memorial(None, "Mary Jones")).unwrap();
+        assert!(assigned > 20);
+
+        assert_eq!(repo.get(10).unwrap().unwrap().name, "John Smith");
+        assert!(repo.get(99).unwrap().is_none());
+
+        let smiths = repo.search("smith", &SearchFilters::default()).unwrap();
+        assert_eq!(smiths.len(), 2);
+        assert_eq!(smiths[0].name, "Jane Smith");
+
+        let veterans = SearchFilters {
+            veteran: Some(true),
+            ..SearchFilters::default()
+        };
+        assert_eq!(repo.search("Smith", &veterans).unwrap().len(), 1);
+
+        assert_eq!(repo.list(10, 0).unwrap().len(), 3);
+        assert_eq!(repo.list(10, 1).unwrap()[0].id, Some(20));
+
+        assert!(repo.delete(10).unwrap());
+        assert!(!repo.delete(10).unwrap());
+        assert!(repo.get(10).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_sqlite_repository() {
+        let mut repo = MemorialStore::open_in_memory().unwrap();
+        check_repository(&mut repo);
+    }
+
+    #[test]
+    fn test_in_memory_repository() {
+        check_repository(&mut InMemoryRepository::new());
+    }
+
+    #[test]
+    fn test_json_lines_repository_replays_history() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("memorials.jsonl");
+
+        let mut repo = JsonLinesRepository::open(&path).unwrap();
+        check_repository(&mut repo);
+
+        // Reopening replays the stores and the delete
+        let reopened = JsonLinesRepository::open(&path).unwrap();
+        assert!(reopened.get(10).unwrap().is_none());
+        assert_eq!(reopened.get(20).unwrap().unwrap().name, "Jane Smith");
+        assert_eq!(reopened.list(10, 0).unwrap().len(), 2);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 4);
+        assert!(contents
+            .lines()
+            .last()
+            .unwrap()
+            .contains(r#""op":"delete""#));
+    }
+
+    #[test]
+    fn test_storage_backend_from_str() {
+        assert_eq!(
+            "sqlite".parse::<StorageBackend>().unwrap(),
+            StorageBackend::Sqlite
+        );
+        assert_eq!(
+            "jsonl".parse::<StorageBackend>().unwrap(),
+            StorageBackend::JsonLines
+        );
+        assert!("csv".parse::<StorageBackend>().is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::db::MemorialStore;
+use crate::{parse_page, Error, FieldChange, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+/// Raw HTML of a memorial page as it was fetched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snapshot {
+    /// Snapshot ID
+    pub id: i64,
+    /// ID of the memorial the page belongs to
+    pub memorial_id: u64,
+    /// URL the page was fetched from
+    pub url: String,
+    /// SHA-256 of the HTML, as lowercase hex
+    pub content_hash: String,
+    /// UTC time the page was fetched (`YYYY-MM-DD HH:MM:SS`)
+    pub fetched_at: String,
+    /// The page HTML, decompressed
+    #[serde(skip)]
+    pub html: String,
+}
+
+/// Options controlling a reparse run
+#[derive(Debug, Clone, Default)]
+pub struct ReparseOptions {
+    /// Report changes without writing them to the database
+    pub dry_run: bool,
+}
+
+/// Outcome of re-parsing the latest snapshot of one memorial
+#[derive(Debug, Serialize)]
+pub struct ReparseReport {
+    /// Memorial ID
+    pub memorial_id: u64,
+    /// Name of the memorial as stored before the reparse
+    pub name: String,
+    /// Snapshot that was parsed
+    pub snapshot_id: i64,
+    /// Fields that changed since the stored copy, empty if unchanged
+    pub changes: Vec<FieldChange>,
+    /// Error that stopped the reparse, if any
+    pub error: Option<String>,
+}
+
+/// Totals for a reparse run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReparseSummary {
+    /// Number of snapshots parsed
+    pub reparsed: usize,
+    /// Number of memorials with at least one changed field
+    pub changed: usize,
+    /// Number of snapshots that could not be parsed
+    pub failed: usize,
+}
+
+/// Run the current parser over the latest snapshot of every memorial
+///
+/// Only the stored HTML is used, so no network access is needed. Memorials
+/// whose parsed fields changed are stored again, recording a new revision
+/// dated when the page was fetched.
+/// `on_report` is called as each memorial finishes.
+pub fn reparse_snapshots<F>(
+    db_path: &str,
+    options: &ReparseOptions,
+    mut on_report: F,
+) -> Result<ReparseSummary>
+where
+    F: FnMut(&ReparseReport),
+{
+    let mut store = MemorialStore::open(db_path)?;
+    let mut summary = ReparseSummary::default();
+
+    for memorial_id in store.snapshot_memorial_ids()? {
+        // Pages of merged-away duplicates describe a memorial that is gone
+        if store.merged_into(memorial_id)?.is_some() {
+            continue;
+        }
+        let Some(snapshot) = store.latest_snapshot(memorial_id)? else {
+            continue;
+        };
+        let stored = store.get(memorial_id)?.unwrap_or_default();
+
+        let mut report = ReparseReport {
+            memorial_id,
+            name: stored.name.clone(),
+            snapshot_id: snapshot.id,
+            changes: Vec::new(),
+            error: None,
+        };
+
+        summary.reparsed += 1;
+        match parse_page(&snapshot.html) {
+            Ok(parsed) if parsed.id.is_some_and(|id| id != memorial_id) => {
+                // Never store one memorial's page over another
+                summary.failed += 1;
+                report.error = Some(format!(
+                    "Snapshot is the page of memorial {}, not {}",
+                    parsed.id.unwrap_or_default(),
+                    memorial_id
+                ));
+            }
+            Ok(mut parsed) => {
+                // Keep the stored ID when the page does not expose one
+                parsed.id = parsed.id.or(Some(memorial_id));
+                report.changes = stored.diff(&parsed);
+
+                if !report.changes.is_empty() {
+                    summary.changed += 1;
+                    if !options.dry_run {
+                        store.store_fetched(&parsed, &snapshot.fetched_at)?;
+                    }
+                }
+            }
+            Err(err) => {
+                summary.failed += 1;
+                report.error = Some(err.to_string());
+            }
+        }
+
+        on_report(&report);
+    }
+
+    Ok(summary)
+}
+
+/// SHA-256 of a page, as lowercase hex
+pub(crate) fn content_hash(html: &str) -> String {
+    Sha256::digest(html.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Gzip a page for storage
+pub(crate) fn compress(html: &str) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(html.as_bytes())?;
+    Ok(encoder.finish()?)
+}
+
+/// Restore a page stored with `compress`
+pub(crate) fn decompress(data: &[u8]) -> Result<String> {
+    let mut html = String::new();
+    GzDecoder::new(data)
+        .read_to_string(&mut html)
+        .map_err(|e| Error::DatabaseError(format!("Corrupt snapshot: {}", e)))?;
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{save_memorial, Config, Memorial};
+    use tempfile::tempdir;
+
+    const PAGE: &str = r#"<html><body>
+        <h1 itemprop="name">Jane Doe</h1>
+        <span itemprop="birthDate">1 Jan 1850</span>
+    </body></html>"#;
+
+    #[test]
+    fn test_compress_round_trip() {
+        let compressed = compress(PAGE).unwrap();
+        assert_eq!(decompress(&compressed).unwrap(), PAGE);
+        assert_eq!(content_hash(PAGE).len(), 64);
+        assert!(decompress(b"not gzip").is_err());
+    }
+
+    #[test]
+    fn test_reparse_updates_changed_rows() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let db_path = db_path.to_str().unwrap();
+
+        let mut store = MemorialStore::open(db_path).unwrap();
+        // Stored as an older parser would have read the page
+        store
+            .store(&Memorial {
+                id: Some(7),
+                name: "Jane".to_string(),
+                ..Memorial::default()
+            })
+            .unwrap();
+        let url = "https://www.findagrave.com/memorial/7";
+        assert!(store.store_snapshot(7, url, PAGE).unwrap());
+        assert!(!store.store_snapshot(7, url, PAGE).unwrap());
+
+        let snapshot = store.latest_snapshot(7).unwrap().unwrap();
+        assert_eq!(snapshot.html, PAGE);
+        assert_eq!(snapshot.content_hash, content_hash(PAGE));
+        drop(store);
+        rusqlite::Connection::open(db_path)
+            .unwrap()
+            .execute_batch(
+                "UPDATE snapshots SET fetched_at = '2015-03-01 12:00:00';
+                 UPDATE memorial_revisions SET fetched_at = '2015-03-01 12:00:00';",
+            )
+            .unwrap();
+
+        let mut reports = Vec::new();
+        let dry_run = ReparseOptions { dry_run: true };
+        let summary =
+            reparse_snapshots(db_path, &dry_run, |r| reports.push(r.name.clone())).unwrap();
+        assert_eq!(summary.changed, 1);
+        assert_eq!(reports, ["Jane"]);
+
+        reparse_snapshots(db_path, &ReparseOptions::default(), |_| {}).unwrap();
+        let store = MemorialStore::open(db_path).unwrap();
+        assert_eq!(store.get(7).unwrap().unwrap().name, "Jane Doe");
+        // Reparsing is not a fetch, so the citation's access date stays put
+        assert_eq!(
+            store.last_fetched(7).unwrap().as_deref(),
+            Some("2015-03-01 12:00:00")
+        );
+
+        let again = reparse_snapshots(db_path, &ReparseOptions::default(), |_| {}).unwrap();
+        assert_eq!((again.reparsed, again.changed), (1, 0));
+    }
+
+    #[test]
+    fn test_merged_duplicate_page_is_not_snapshotted() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test.db");
+        let config = Config {
+            db_path: db_path.to_str().unwrap().to_string(),
+            snapshots: true,
+            ..Config::default()
+        };
+
+        let mut store = MemorialStore::open(&config.db_path).unwrap();
+        let survivor = Memorial {
+            id: Some(1),
+            name: "John Smith".to_string(),
+            ..Memorial::default()
+        };
+        store
+            .store(&Memorial {
+                id: Some(2),
+                name: "J. Smith".to_string(),
+                ..Memorial::default()
+            })
+            .unwrap();
+        store.merge(&survivor, &[2]).unwrap();
+
+        // Saving the duplicate's page files it under the survivor, without its page
+        let page = Memorial {
+            id: Some(2),
+            name: "John Smith".to_string(),
+            ..Memorial::default()
+        };
+        let url = "https://www.findagrave.com/memorial/2";
+        assert_eq!(
+            save_memorial(&page, url, PAGE, &config, &mut store).unwrap(),
+            1
+        );
+        assert!(store.latest_snapshot(1).unwrap().is_none());
+
+        // A snapshot that is another memorial's page is refused by reparse
+        let other = PAGE.replace(
+            "<body>",
+            "<head><meta property=\"og:url\" \
+             content=\"https://www.findagrave.com/memorial/2\"></head><body>",
+        );
+        store.store_snapshot(1, url, &other).unwrap();
+        let mut errors = Vec::new();
+        let summary = reparse_snapshots(&config.db_path, &ReparseOptions::default(), |r| {
+            errors.push(r.error.clone())
+        })
+        .unwrap();
+        assert_eq!((summary.reparsed, summary.failed), (1, 1));
+        assert!(errors[0].as_deref().unwrap().contains("memorial 2, not 1"));
+        assert_eq!(store.get(1).unwrap().unwrap().name, "John Smith");
+    }
+}
@@ -0,0 +1,725 @@
+use crate::db::MemorialStore;
+use crate::{Coordinates, Error, Memorial, MemorialQuery, PartialDate, Result, SortField};
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use encoding_rs::Encoding;
+use serde::Serialize;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Delimited text formats used for spreadsheet import and export
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TableFormat {
+    /// Comma-separated values
+    #[default]
+    Csv,
+    /// Tab-separated values
+    Tsv,
+}
+
+impl TableFormat {
+    /// Guess the format from a file extension, defaulting to CSV
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("tsv") || ext.eq_ignore_ascii_case("tab") => {
+                TableFormat::Tsv
+            }
+            _ => TableFormat::Csv,
+        }
+    }
+
+    fn delimiter(self) -> u8 {
+        match self {
+            TableFormat::Csv => b',',
+            TableFormat::Tsv => b'\t',
+        }
+    }
+}
+
+impl FromStr for TableFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(TableFormat::Csv),
+            "tsv" => Ok(TableFormat::Tsv),
+            other => Err(Error::InvalidInput(format!(
+                "Unknown table format: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A column of the `memorials` table that can be imported or exported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Id,
+    Name,
+    BirthDate,
+    BirthLocation,
+    DeathDate,
+    DeathLocation,
+    BurialLocation,
+    PlotDetails,
+    IsVeteran,
+    IsFamous,
+    IsCenotaph,
+    NotBuriedHere,
+    CreatedBy,
+    MaintainedBy,
+    CreatedDate,
+    SponsoredBy,
+    Bio,
+    PlotLatitude,
+    PlotLongitude,
+    CemeteryLatitude,
+    CemeteryLongitude,
+}
+
+impl Column {
+    /// Every column, in table order
+    pub const ALL: &'static [Column] = &[
+        Column::Id,
+        Column::Name,
+        Column::BirthDate,
+        Column::BirthLocation,
+        Column::DeathDate,
+        Column::DeathLocation,
+        Column::BurialLocation,
+        Column::PlotDetails,
+        Column::IsVeteran,
+        Column::IsFamous,
+        Column::IsCenotaph,
+        Column::NotBuriedHere,
+        Column::CreatedBy,
+        Column::MaintainedBy,
+        Column::CreatedDate,
+        Column::SponsoredBy,
+        Column::Bio,
+        Column::PlotLatitude,
+        Column::PlotLongitude,
+        Column::CemeteryLatitude,
+        Column::CemeteryLongitude,
+    ];
+
+    /// Column name as used in headers and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Name => "name",
+            Column::BirthDate => "birth_date",
+            Column::BirthLocation => "birth_location",
+            Column::DeathDate => "death_date",
+            Column::DeathLocation => "death_location",
+            Column::BurialLocation => "burial_location",
+            Column::PlotDetails => "plot_details",
+            Column::IsVeteran => "is_veteran",
+            Column::IsFamous => "is_famous",
+            Column::IsCenotaph => "is_cenotaph",
+            Column::NotBuriedHere => "not_buried_here",
+            Column::CreatedBy => "created_by",
+            Column::MaintainedBy => "maintained_by",
+            Column::CreatedDate => "created_date",
+            Column::SponsoredBy => "sponsored_by",
+            Column::Bio => "bio",
+            Column::PlotLatitude => "plot_latitude",
+            Column::PlotLongitude => "plot_longitude",
+            Column::CemeteryLatitude => "cemetery_latitude",
+            Column::CemeteryLongitude => "cemetery_longitude",
+        }
+    }
+
+    /// Parse a comma-separated list of column names, keeping their order
+    pub fn parse_list(list: &str) -> Result<Vec<Column>> {
+        list.split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// The column's value in a memorial, as written to a cell
+    fn value(self, memorial: &Memorial) -> String {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let degrees = |position: Option<Coordinates>, part: fn(Coordinates) -> f64| {
+            position.map(|c| part(c).to_string()).unwrap_or_default()
+        };
+        match self {
+            Column::Id => memorial.id.map(|id| id.to_string()).unwrap_or_default(),
+            Column::Name => memorial.name.clone(),
+            Column::BirthDate => text(&memorial.birth_date),
+            Column::BirthLocation => text(&memorial.birth_location),
+            Column::DeathDate => text(&memorial.death_date),
+            Column::DeathLocation => text(&memorial.death_location),
+            Column::BurialLocation => text(&memorial.burial_location),
+            Column::PlotDetails => text(&memorial.plot_details),
+            Column::IsVeteran => memorial.is_veteran.to_string(),
+            Column::IsFamous => memorial.is_famous.to_string(),
+            Column::IsCenotaph => memorial.is_cenotaph.to_string(),
+            Column::NotBuriedHere => memorial.not_buried_here.to_string(),
+            Column::CreatedBy => text(&memorial.created_by),
+            Column::MaintainedBy => text(&memorial.maintained_by),
+            Column::CreatedDate => text(&memorial.created_date),
+            Column::SponsoredBy => text(&memorial.sponsored_by),
+            Column::Bio => text(&memorial.bio),
+            Column::PlotLatitude => degrees(memorial.plot_coordinates, |c| c.latitude),
+            Column::PlotLongitude => degrees(memorial.plot_coordinates, |c| c.longitude),
+            Column::CemeteryLatitude => degrees(memorial.cemetery_coordinates, |c| c.latitude),
+            Column::CemeteryLongitude => degrees(memorial.cemetery_coordinates, |c| c.longitude),
+        }
+    }
+
+    /// Set the column in a memorial from a cell, checking that it is valid
+    fn set(self, memorial: &mut Memorial, cell: &str) -> std::result::Result<(), String> {
+        let cell = cell.trim();
+        let text = Some(cell.to_string()).filter(|text| !text.is_empty());
+        let date = || match &text {
+            Some(date) if !date.eq_ignore_ascii_case("unknown") => date
+                .parse::<PartialDate>()
+                .map(|_| text.clone())
+                .map_err(|_| format!("{}: unrecognized date '{}'", self, date)),
+            _ => Ok(text.clone()),
+        };
+        // One half of a position; the other half comes from its own column
+        let degrees = |limit: f64| match &text {
+            Some(value) => value
+                .parse::<f64>()
+                .ok()
+                .filter(|degrees| (-limit..=limit).contains(degrees))
+                .map(Some)
+                .ok_or_else(|| format!("{}: not a coordinate '{}'", self, value)),
+            None => Ok(None),
+        };
+        let flag = || match cell.to_ascii_lowercase().as_str() {
+            "" | "false" | "no" | "n" | "0" => Ok(false),
+            "true" | "yes" | "y" | "1" => Ok(true),
+            other => Err(format!(
+                "{}: expected true or false, found '{}'",
+                self, other
+            )),
+        };
+
+        match self {
+            Column::Id => {
+                memorial.id = match &text {
+                    Some(id) => Some(
+                        id.parse()
+                            .map_err(|_| format!("id: not a memorial ID '{}'", id))?,
+                    ),
+                    None => None,
+                }
+            }
+            Column::Name => {
+                // Re-derive the name parts from the new name
+                if memorial.name != cell {
+                    memorial.person_name = None;
+                }
+                memorial.name = cell.to_string();
+            }
+            Column::BirthDate => memorial.birth_date = date()?,
+            Column::BirthLocation => memorial.birth_location = text,
+            Column::DeathDate => memorial.death_date = date()?,
+            Column::DeathLocation => memorial.death_location = text,
+            Column::BurialLocation => memorial.burial_location = text,
+            Column::PlotDetails => memorial.plot_details = text,
+            Column::IsVeteran => memorial.is_veteran = flag()?,
+            Column::IsFamous => memorial.is_famous = flag()?,
+            Column::IsCenotaph => memorial.is_cenotaph = flag()?,
+            Column::NotBuriedHere => memorial.not_buried_here = flag()?,
+            Column::CreatedBy => memorial.created_by = text,
+            Column::MaintainedBy => memorial.maintained_by = text,
+            Column::CreatedDate => memorial.created_date = date()?,
+            Column::SponsoredBy => memorial.sponsored_by = text,
+            Column::Bio => memorial.bio = text,
+            Column::PlotLatitude => set_latitude(&mut memorial.plot_coordinates, degrees(90.0)?),
+            Column::PlotLongitude => set_longitude(&mut memorial.plot_coordinates, degrees(180.0)?),
+            Column::CemeteryLatitude => {
+                set_latitude(&mut memorial.cemetery_coordinates, degrees(90.0)?)
+            }
+            Column::CemeteryLongitude => {
+                set_longitude(&mut memorial.cemetery_coordinates, degrees(180.0)?)
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replace the latitude of a position, keeping its longitude
+///
+/// A missing half is NaN until the row is complete; `row_to_memorial` then
+/// drops a position with both halves missing and rejects one with only one.
+fn set_latitude(position: &mut Option<Coordinates>, latitude: Option<f64>) {
+    *position = Some(Coordinates {
+        latitude: latitude.unwrap_or(f64::NAN),
+        longitude: position.map_or(f64::NAN, |c| c.longitude),
+    });
+}
+
+/// Replace the longitude of a position, keeping its latitude
+fn set_longitude(position: &mut Option<Coordinates>, longitude: Option<f64>) {
+    *position = Some(Coordinates {
+        latitude: position.map_or(f64::NAN, |c| c.latitude),
+        longitude: longitude.unwrap_or(f64::NAN),
+    });
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Column {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim();
+        Column::ALL
+            .iter()
+            .copied()
+            .find(|column| column.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown column: {}", name)))
+    }
+}
+
+/// Options for exporting the memorial table
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Delimited format to write
+    pub format: TableFormat,
+    /// Columns to write, in order
+    pub columns: Vec<Column>,
+    /// Character encoding label, such as `utf-8` or `windows-1252`
+    pub encoding: String,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: TableFormat::Csv,
+            columns: Column::ALL.to_vec(),
+            encoding: "utf-8".to_string(),
+        }
+    }
+}
+
+/// Options for importing a spreadsheet into the memorial table
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Delimited format to read
+    pub format: TableFormat,
+    /// Character encoding label, such as `utf-8` or `windows-1252`
+    ///
+    /// A byte-order mark in the input takes precedence.
+    pub encoding: String,
+    /// Validate every row without writing to the database
+    pub dry_run: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            format: TableFormat::Csv,
+            encoding: "utf-8".to_string(),
+            dry_run: false,
+        }
+    }
+}
+
+/// A row that could not be imported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RejectedRow {
+    /// Line of the row in the input, counting the header as line 1
+    pub line: u64,
+    /// Cells of the row as read
+    pub cells: Vec<String>,
+    /// Why the row was rejected
+    pub reason: String,
+}
+
+/// Outcome of a spreadsheet import
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    /// Columns found in the header, in order
+    #[serde(skip)]
+    pub columns: Vec<Column>,
+    /// Number of rows stored, or that would be stored in a dry run
+    pub imported: usize,
+    /// Rows that failed validation
+    pub rejected: Vec<RejectedRow>,
+}
+
+/// Write matching memorials as CSV or TSV, returning the number of rows
+///
+/// The query's filters select the memorials, which are written in ID order;
+/// its sort order and paging are ignored. Values are quoted where needed, so
+/// cells may safely contain delimiters, quotes and line breaks. Exporting
+/// fails rather than writing a value the chosen encoding cannot represent.
+pub fn export_table<W: Write>(
+    store: &MemorialStore,
+    query: &MemorialQuery,
+    options: &ExportOptions,
+    out: &mut W,
+) -> Result<usize> {
+    let encoding = encoding(&options.encoding)?;
+    if encoding.output_encoding() != encoding {
+        return Err(Error::InvalidInput(format!(
+            "Cannot export as {}; use utf-8",
+            encoding.name()
+        )));
+    }
+    if options.columns.is_empty() {
+        return Err(Error::InvalidInput("No columns to export".to_string()));
+    }
+
+    let mut writer = WriterBuilder::new()
+        .delimiter(options.format.delimiter())
+        .from_writer(Vec::new());
+    writer
+        .write_record(options.columns.iter().map(|column| column.name()))
+        .map_err(csv_error)?;
+
+    let memorials = store.query_all(&query.clone().sort_by(SortField::Id, false))?;
+    for memorial in &memorials {
+        let mut record = Vec::with_capacity(options.columns.len());
+        for column in &options.columns {
+            let value = column.value(memorial);
+            if encoding != encoding_rs::UTF_8 && encoding.encode(&value).2 {
+                return Err(Error::InvalidInput(format!(
+                    "Memorial {}: {} cannot be written as {}",
+                    memorial.id.unwrap_or_default(),
+                    column,
+                    encoding.name()
+                )));
+            }
+            record.push(value);
+        }
+        writer.write_record(&record).map_err(csv_error)?;
+    }
+
+    let text = writer
+        .into_inner()
+        .map_err(|e| Error::Other(e.to_string()))?;
+    let text = String::from_utf8(text).map_err(|e| Error::Other(e.to_string()))?;
+    out.write_all(&encoding.encode(&text).0)?;
+    Ok(memorials.len())
+}
+
+/// Validate CSV or TSV rows into memorials and upsert them
+///
+/// The first row names the columns, in any order, and must include `id`:
+/// every row needs the memorial ID it describes. Columns that are left out
+/// keep their stored values when a row updates an existing memorial. Rows
+/// that fail validation are collected in the report and do not stop the
+/// import; valid rows are stored together in a single transaction.
+pub fn import_table(
+    store: &mut MemorialStore,
+    input: &[u8],
+    options: &ImportOptions,
+) -> Result<ImportReport> {
+    let encoding = encoding(&options.encoding)?;
+    let (text, used, had_errors) = encoding.decode(input);
+    if had_errors {
+        return Err(Error::InvalidInput(format!(
+            "Input is not valid {}",
+            used.name()
+        )));
+    }
+
+    let mut reader = ReaderBuilder::new()
+        .delimiter(options.format.delimiter())
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let columns = reader
+        .headers()
+        .map_err(csv_error)?
+        .iter()
+        .map(str::parse)
+        .collect::<Result<Vec<Column>>>()?;
+    if !columns.contains(&Column::Id) {
+        return Err(Error::InvalidInput(
+            "The header must include an id column".to_string(),
+        ));
+    }
+
+    let mut report = ImportReport {
+        columns,
+        ..ImportReport::default()
+    };
+    let mut memorials = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        match row_to_memorial(store, &report.columns, &record) {
+            Ok(memorial) => memorials.push(memorial),
+            Err(reason) => report.rejected.push(RejectedRow {
+                line: record.position().map_or(0, |position| position.line()),
+                cells: record.iter().map(str::to_string).collect(),
+                reason,
+            }),
+        }
+    }
+
+    report.imported = memorials.len();
+    if !options.dry_run {
+        store.store_many(&memorials)?;
+    }
+    Ok(report)
+}
+
+/// Write rejected rows back out with their reasons, ready to fix and re-import
+///
+/// The rows keep the input's header, with a trailing `reason` column added.
+pub fn write_rejected<W: Write>(report: &ImportReport, format: TableFormat, out: W) -> Result<()> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(format.delimiter())
+        .flexible(true)
+        .from_writer(out);
+    writer
+        .write_record(
+            report
+                .columns
+                .iter()
+                .map(|column| column.name())
+                .chain(["reason"]),
+        )
+        .map_err(csv_error)?;
+    for row in &report.rejected {
+        writer
+            .write_record(
+                row.cells
+                    .iter()
+                    .map(String::as_str)
+                    .chain([row.reason.as_str()]),
+            )
+            .map_err(csv_error)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Build a memorial from one row, starting from the stored copy if there is one
+fn row_to_memorial(
+    store: &MemorialStore,
+    columns: &[Column],
+    record: &StringRecord,
+) -> std::result::Result<Memorial, String> {
+    if record.len() != columns.len() {
+        return Err(format!(
+            "expected {} cells, found {}",
+            columns.len(),
+            record.len()
+        ));
+    }
+
+    let mut memorial = Memorial::default();
+    if let Some(cell) = columns
+        .iter()
+        .position(|column| *column == Column::Id)
+        .and_then(|i| record.get(i))
+    {
+        Column::Id.set(&mut memorial, cell)?;
+    }
+    let id = memorial.id.ok_or_else(|| "id: missing".to_string())?;
+    if let Some(stored) = store.get(id).map_err(|e| e.to_string())? {
+        memorial = stored;
+    }
+
+    for (column, cell) in columns.iter().zip(record.iter()) {
+        column.set(&mut memorial, cell)?;
+    }
+    if memorial.name.trim().is_empty() {
+        return Err("name: missing".to_string());
+    }
+    for (position, field) in [
+        (&mut memorial.plot_coordinates, "plot"),
+        (&mut memorial.cemetery_coordinates, "cemetery"),
+    ] {
+        match *position {
+            Some(c) if c.latitude.is_nan() && c.longitude.is_nan() => *position = None,
+            Some(c) if c.latitude.is_nan() || c.longitude.is_nan() => {
+                return Err(format!(
+                    "{}_latitude, {}_longitude: give both or neither",
+                    field, field
+                ))
+            }
+            _ => {}
+        }
+    }
+    Ok(memorial)
+}
+
+/// Look up a character encoding by its WHATWG label
+fn encoding(label: &str) -> Result<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown encoding: {}", label)))
+}
+
+fn csv_error(err: csv::Error) -> Error {
+    Error::ParseError(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> MemorialStore {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        store
+            .store(&Memorial {
+                id: Some(1),
+                name: "José Núñez".to_string(),
+                birth_date: Some("3 May 1850".to_string()),
+                bio: Some("Farmer, \"Pepe\"\nto friends".to_string()),
+                is_veteran: true,
+                ..Memorial::default()
+            })
+            .unwrap();
+        store
+    }
+
+    #[test]
+    fn test_export_columns_and_encoding() {
+        let store = store();
+        let options = ExportOptions {
+            columns: Column::parse_list("name,id,bio,is_veteran").unwrap(),
+            encoding: "windows-1252".to_string(),
+            ..ExportOptions::default()
+        };
+        let mut out = Vec::new();
+        assert_eq!(
+            export_table(&store, &MemorialQuery::new(), &options, &mut out).unwrap(),
+            1
+        );
+        let (text, _, _) = encoding_rs::WINDOWS_1252.decode(&out);
+        assert_eq!(
+            text,
+            "name,id,bio,is_veteran\nJosé Núñez,1,\"Farmer, \"\"Pepe\"\"\nto friends\",true\n"
+        );
+
+        let tsv = ExportOptions {
+            format: TableFormat::Tsv,
+            columns: vec![Column::Id, Column::BirthDate],
+            ..ExportOptions::default()
+        };
+        let mut out = Vec::new();
+        export_table(&store, &MemorialQuery::new(), &tsv, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id\tbirth_date\n1\t3 May 1850\n"
+        );
+
+        assert!("nickname".parse::<Column>().is_err());
+        // Latin-2 has no ñ
+        let latin2 = ExportOptions {
+            encoding: "iso-8859-2".to_string(),
+            ..options
+        };
+        assert!(export_table(&store, &MemorialQuery::new(), &latin2, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_import_validates_and_upserts() {
+        let mut store = store();
+        let input = "ID,death_date,is_famous,name\n\
+            1,12 Jun 1901,yes,José Núñez\n\
+            2,31 Feb 1900,no,Ann Lee\n\
+            3,,maybe,Tom Lee\n\
+            4,1 Jan 1920,no\n\
+            x,,no,Bad Id\n\
+            7,,no,\n\
+            ,,no,No Id\n\
+            5,unknown,,Eve Lee\n";
+        let dry_run = ImportOptions {
+            dry_run: true,
+            ..ImportOptions::default()
+        };
+        let report = import_table(&mut store, input.as_bytes(), &dry_run).unwrap();
+        assert_eq!(report.imported, 2);
+        assert_eq!(store.count().unwrap(), 1);
+
+        let report = import_table(&mut store, input.as_bytes(), &ImportOptions::default()).unwrap();
+        let rejected: Vec<_> = report
+            .rejected
+            .iter()
+            .map(|row| (row.line, row.reason.as_str()))
+            .collect();
+        assert_eq!(
+            rejected,
+            [
+                (3, "death_date: unrecognized date '31 Feb 1900'"),
+                (4, "is_famous: expected true or false, found 'maybe'"),
+                (5, "expected 4 cells, found 3"),
+                (6, "id: not a memorial ID 'x'"),
+                (7, "name: missing"),
+                (8, "id: missing"),
+            ]
+        );
+        let mut out = Vec::new();
+        write_rejected(&report, TableFormat::Csv, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("id,death_date,is_famous,name,reason\n2,31 Feb 1900,no,Ann Lee,"));
+
+        // Columns left out of the file keep their stored values
+        let updated = store.get(1).unwrap().unwrap();
+        assert_eq!(updated.name, "José Núñez");
+        assert_eq!(updated.death_date.as_deref(), Some("12 Jun 1901"));
+        assert!(updated.is_famous && updated.is_veteran);
+        assert_eq!(updated.bio.as_deref(), Some("Farmer, \"Pepe\"\nto friends"));
+        assert_eq!(store.get(5).unwrap().unwrap().name, "Eve Lee");
+
+        let tsv = ImportOptions {
+            format: TableFormat::Tsv,
+            encoding: "windows-1252".to_string(),
+            ..ImportOptions::default()
+        };
+        let (latin1, _, _) = encoding_rs::WINDOWS_1252.encode("id\tname\n6\tRenée Roy\n");
+        let report = import_table(&mut store, &latin1, &tsv).unwrap();
+        assert_eq!(report.imported, 1);
+        assert_eq!(store.get(6).unwrap().unwrap().name, "Renée Roy");
+        assert!(import_table(&mut store, b"nickname\nx\n", &tsv).is_err());
+        assert!(import_table(&mut store, b"name\nNo Id\n", &tsv).is_err());
+    }
+
+    #[test]
+    fn test_coordinate_columns() {
+        let mut store = store();
+        let input = "id,plot_latitude,plot_longitude,cemetery_longitude,cemetery_latitude\n\
+            1,39.94,-83.02,-83.03,39.95\n\
+            1,39.94,,,\n\
+            1,95,-83.02,,\n";
+        let report = import_table(&mut store, input.as_bytes(), &ImportOptions::default()).unwrap();
+        let rejected: Vec<_> = report
+            .rejected
+            .iter()
+            .map(|row| (row.line, row.reason.as_str()))
+            .collect();
+        assert_eq!(
+            rejected,
+            [
+                (3, "plot_latitude, plot_longitude: give both or neither"),
+                (4, "plot_latitude: not a coordinate '95'"),
+            ]
+        );
+        let stored = store.get(1).unwrap().unwrap();
+        assert_eq!(stored.plot_coordinates, Coordinates::new(39.94, -83.02));
+        assert_eq!(stored.cemetery_coordinates, Coordinates::new(39.95, -83.03));
+
+        let options = ExportOptions {
+            columns: Column::parse_list("id,plot_latitude,plot_longitude,cemetery_latitude")
+                .unwrap(),
+            ..ExportOptions::default()
+        };
+        let mut out = Vec::new();
+        export_table(&store, &MemorialQuery::new(), &options, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "id,plot_latitude,plot_longitude,cemetery_latitude\n1,39.94,-83.02,39.95\n"
+        );
+
+        // Empty cells in both halves clear the position
+        let input = "id,plot_latitude,plot_longitude\n1,,\n";
+        import_table(&mut store, input.as_bytes(), &ImportOptions::default()).unwrap();
+        assert_eq!(store.get(1).unwrap().unwrap().plot_coordinates, None);
+    }
+}
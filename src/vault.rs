@@ -0,0 +1,508 @@
+use crate::db::MemorialStore;
+use crate::{Memorial, MemorialQuery, Result, SortField};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Markers around the part of a note that is regenerated on every export
+const BEGIN_MARKER: &str = "<!-- findagrave:begin -->";
+const END_MARKER: &str = "<!-- findagrave:end -->";
+
+/// Front-matter keys and values, in the order they are written
+type FrontMatter = Vec<(String, Value)>;
+
+/// Characters that cannot appear in note names or wikilinks
+const RESERVED: &[char] = &[
+    '[', ']', '#', '^', '|', '\\', '/', ':', '*', '?', '"', '<', '>',
+];
+
+/// What a vault export wrote
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VaultSummary {
+    /// Memorial notes written for the first time
+    pub created: usize,
+    /// Existing memorial notes whose generated part changed
+    pub updated: usize,
+    /// Existing memorial notes that were already up to date
+    pub unchanged: usize,
+    /// Cemetery notes written or updated
+    pub cemeteries: usize,
+}
+
+/// Write matching memorials as notes in a Markdown (Obsidian) vault
+///
+/// Each memorial gets a note in `memorials/` whose YAML front-matter holds
+/// every memorial field, followed by its citation. Memorials sharing a burial
+/// location link to a note for that cemetery in `cemeteries/`, which links
+/// back to them. Notes are found again by the memorial ID in their name, so
+/// renamed notes keep being updated; only the front-matter keys and the
+/// block between the `findagrave` markers are rewritten, and anything else
+/// in the note is left as it is.
+pub fn export_vault(
+    store: &MemorialStore,
+    query: &MemorialQuery,
+    dir: &Path,
+) -> Result<VaultSummary> {
+    let memorials_dir = dir.join("memorials");
+    let cemeteries_dir = dir.join("cemeteries");
+    fs::create_dir_all(&memorials_dir)?;
+    fs::create_dir_all(&cemeteries_dir)?;
+    let existing = existing_notes(&memorials_dir)?;
+    // Named from every stored cemetery, so a filtered export links to the
+    // same notes as a full one
+    let cemetery_names: BTreeSet<String> = store
+        .cemetery_names()?
+        .iter()
+        .map(|cemetery| cemetery.trim().to_string())
+        .collect();
+    let notes = cemetery_notes(&cemetery_names);
+
+    let mut summary = VaultSummary::default();
+    let mut cemeteries = BTreeSet::new();
+    for memorial in store.query_all(&query.clone().sort_by(SortField::Id, false))? {
+        let Some(id) = memorial.id else {
+            continue;
+        };
+        let path = note_path(&existing, &memorials_dir, &memorial);
+        let accessed = store.last_fetched(id)?;
+
+        let (front_matter, body) = memorial_note(&memorial, accessed.as_deref(), &notes);
+        match write_note(&path, &front_matter, &body)? {
+            NoteWrite::Created => summary.created += 1,
+            NoteWrite::Updated => summary.updated += 1,
+            NoteWrite::Unchanged => summary.unchanged += 1,
+        }
+
+        if let Some(cemetery) = cemetery_name(&memorial).filter(|c| notes.contains_key(c)) {
+            cemeteries.insert(cemetery.to_string());
+        }
+    }
+
+    // A cemetery note lists everyone buried there, not only the memorials
+    // this export selected, so a filtered export keeps the other backlinks
+    for cemetery in &cemeteries {
+        let members: Vec<(String, Memorial)> = store
+            .query_all(
+                &MemorialQuery::new()
+                    .cemetery(cemetery)
+                    .sort_by(SortField::Name, false),
+            )?
+            .into_iter()
+            .filter(|memorial| memorial.id.is_some())
+            .filter(|memorial| cemetery_name(memorial) == Some(cemetery.as_str()))
+            .map(|memorial| {
+                let path = note_path(&existing, &memorials_dir, &memorial);
+                let note = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or_default()
+                    .to_string();
+                (note, memorial)
+            })
+            .collect();
+        let path = cemeteries_dir.join(format!("{}.md", notes[cemetery.as_str()]));
+        let (front_matter, body) = cemetery_note(cemetery, &members);
+        if write_note(&path, &front_matter, &body)? != NoteWrite::Unchanged {
+            summary.cemeteries += 1;
+        }
+    }
+
+    Ok(summary)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum NoteWrite {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// Memorial notes already in the vault, by the memorial ID at the end of their name
+fn existing_notes(dir: &Path) -> Result<HashMap<u64, PathBuf>> {
+    let mut notes = HashMap::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+            continue;
+        }
+        let id = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_suffix(')'))
+            .and_then(|stem| stem.rsplit_once('('))
+            .and_then(|(_, id)| id.parse().ok());
+        if let Some(id) = id {
+            notes.insert(id, path);
+        }
+    }
+    Ok(notes)
+}
+
+/// Path of a memorial's note: the existing note for its ID, or a new one
+fn note_path(existing: &HashMap<u64, PathBuf>, dir: &Path, memorial: &Memorial) -> PathBuf {
+    memorial
+        .id
+        .and_then(|id| existing.get(&id).cloned())
+        .unwrap_or_else(|| dir.join(format!("{}.md", note_name(memorial))))
+}
+
+/// Note name for a memorial, such as `John Smith (12345)`
+fn note_name(memorial: &Memorial) -> String {
+    let name = sanitize(&memorial.name);
+    let name = if name.is_empty() { "Memorial" } else { &name };
+    format!("{} ({})", name, memorial.id.unwrap_or_default())
+}
+
+/// Note names for cemeteries, made unique when names differ only in punctuation or case
+fn cemetery_notes(cemeteries: &BTreeSet<String>) -> HashMap<&str, String> {
+    let mut notes = HashMap::new();
+    let mut used: HashMap<String, usize> = HashMap::new();
+    for cemetery in cemeteries {
+        let mut note = sanitize(cemetery);
+        if note.is_empty() {
+            continue;
+        }
+        let count = used.entry(note.to_lowercase()).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            note = format!("{} {}", note, count);
+        }
+        notes.insert(cemetery.as_str(), note);
+    }
+    notes
+}
+
+fn cemetery_name(memorial: &Memorial) -> Option<&str> {
+    memorial
+        .burial_location
+        .as_deref()
+        .map(str::trim)
+        .filter(|cemetery| !sanitize(cemetery).is_empty())
+}
+
+/// Make text safe to use as a note name and wikilink target
+fn sanitize(text: &str) -> String {
+    text.replace(RESERVED, " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches('.')
+        .to_string()
+}
+
+/// A wikilink to a note, showing `label` if it differs from the note name
+fn wikilink(note: &str, label: &str) -> String {
+    if note == label {
+        format!("[[{}]]", note)
+    } else {
+        format!("[[{}|{}]]", note, label.replace(['[', ']', '|'], " "))
+    }
+}
+
+/// Generated front-matter and body of a memorial note
+fn memorial_note(
+    memorial: &Memorial,
+    accessed: Option<&str>,
+    cemetery_notes: &HashMap<&str, String>,
+) -> (FrontMatter, String) {
+    let mut full = memorial.clone();
+    full.person_name = Some(memorial.parsed_name());
+    let mut fields = match serde_json::to_value(&full) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    };
+    // Keep the memorial's field order rather than alphabetical order
+    let order: Vec<&str> = memorial.fields().iter().map(|(field, _)| *field).collect();
+    let mut keys: Vec<String> = fields.keys().cloned().collect();
+    keys.sort_by_key(|key| {
+        order
+            .iter()
+            .position(|field| field == key)
+            .unwrap_or(order.len())
+    });
+    let front_matter = keys
+        .into_iter()
+        .filter_map(|key| fields.remove(&key).map(|value| (key, value)))
+        .collect();
+
+    let mut body = format!("# {}\n\n", memorial.name);
+    for line in memorial.to_sourced_citation(accessed).lines() {
+        body.push_str(&format!("> {}\n", line));
+    }
+    if let Some(cemetery) = cemetery_name(memorial) {
+        if let Some(note) = cemetery_notes.get(cemetery) {
+            body.push_str(&format!("\nCemetery: {}\n", wikilink(note, cemetery)));
+        }
+    }
+    (front_matter, body)
+}
+
+/// Generated front-matter and body of a cemetery note
+fn cemetery_note(cemetery: &str, members: &[(String, Memorial)]) -> (FrontMatter, String) {
+    let front_matter = vec![
+        ("cemetery".to_string(), Value::from(cemetery)),
+        ("memorials".to_string(), Value::from(members.len())),
+    ];
+
+    let mut body = format!("# {}\n\n", cemetery);
+    for (note, memorial) in members {
+        let dates = match (&memorial.birth_date, &memorial.death_date) {
+            (None, None) => String::new(),
+            (birth, death) => format!(
+                " ({} – {})",
+                birth.as_deref().unwrap_or("?"),
+                death.as_deref().unwrap_or("?")
+            ),
+        };
+        body.push_str(&format!("- {}{}\n", wikilink(note, &memorial.name), dates));
+    }
+    (front_matter, body)
+}
+
+/// Write a note, or update the generated parts of an existing one
+fn write_note(path: &Path, front_matter: &[(String, Value)], body: &str) -> Result<NoteWrite> {
+    let block = format!("{}\n{}{}", BEGIN_MARKER, body, END_MARKER);
+
+    let (note, outcome) = match fs::read_to_string(path) {
+        Ok(existing) => {
+            let (user_front_matter, rest) = split_front_matter(&existing);
+            // Keep keys the user added to the front-matter
+            let kept: Vec<&str> = user_front_matter
+                .into_iter()
+                .filter(|(key, _)| !front_matter.iter().any(|(generated, _)| generated == key))
+                .map(|(_, lines)| lines)
+                .collect();
+
+            let rest = match (rest.find(BEGIN_MARKER), rest.find(END_MARKER)) {
+                (Some(start), Some(end)) if start < end => format!(
+                    "{}{}{}",
+                    &rest[..start],
+                    block,
+                    &rest[end + END_MARKER.len()..]
+                ),
+                // A note written by hand: put the generated block above the user's text
+                _ => format!("{}\n\n{}", block, rest.trim_start()),
+            };
+            let note = format!("{}{}", yaml(front_matter, &kept), rest);
+            let outcome = if note == existing {
+                NoteWrite::Unchanged
+            } else {
+                NoteWrite::Updated
+            };
+            (note, outcome)
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => (
+            format!("{}{}\n", yaml(front_matter, &[]), block),
+            NoteWrite::Created,
+        ),
+        Err(err) => return Err(err.into()),
+    };
+
+    if outcome != NoteWrite::Unchanged {
+        fs::write(path, note)?;
+    }
+    Ok(outcome)
+}
+
+/// Split a note into its front-matter entries, keyed by top-level key, and the rest
+///
+/// Each entry keeps its original lines, including indented continuation lines.
+fn split_front_matter(note: &str) -> (Vec<(String, &str)>, &str) {
+    let Some(inner) = note.strip_prefix("---\n") else {
+        return (Vec::new(), note);
+    };
+    let Some(end) = inner
+        .find("\n---\n")
+        .or_else(|| inner.strip_suffix("\n---").map(str::len))
+    else {
+        return (Vec::new(), note);
+    };
+    let rest = inner.get(end + 5..).unwrap_or("");
+    let front_matter = &inner[..end + 1];
+
+    let mut entries: Vec<(String, usize, usize)> = Vec::new();
+    let mut offset = 0;
+    for line in front_matter.split_inclusive('\n') {
+        let is_key = !line.starts_with([' ', '\t', '-', '#']) && line.contains(':');
+        match entries.last_mut() {
+            Some((_, _, end)) if !is_key => *end = offset + line.len(),
+            _ => {
+                let key = line.split(':').next().unwrap_or_default();
+                let key = key.trim().trim_matches(['"', '\'']).to_string();
+                entries.push((key, offset, offset + line.len()));
+            }
+        }
+        offset += line.len();
+    }
+
+    let entries = entries
+        .into_iter()
+        .map(|(key, start, end)| (key, &front_matter[start..end]))
+        .collect();
+    (entries, rest)
+}
+
+/// YAML front-matter for generated fields, followed by lines kept from the note
+///
+/// Values are written in JSON syntax, which YAML reads as flow scalars,
+/// sequences and mappings; nested objects become indented block mappings.
+fn yaml(fields: &[(String, Value)], kept: &[&str]) -> String {
+    fn write<'a>(
+        fields: impl IntoIterator<Item = (&'a String, &'a Value)>,
+        indent: usize,
+        out: &mut String,
+    ) {
+        for (key, value) in fields {
+            match value {
+                Value::Object(inner) if !inner.is_empty() => {
+                    out.push_str(&format!("{:indent$}{}:\n", "", key, indent = indent));
+                    write(inner, indent + 2, out);
+                }
+                other => out.push_str(&format!(
+                    "{:indent$}{}: {}\n",
+                    "",
+                    key,
+                    other,
+                    indent = indent
+                )),
+            }
+        }
+    }
+
+    let mut out = String::from("---\n");
+    write(fields.iter().map(|(key, value)| (key, value)), 0, &mut out);
+    for lines in kept {
+        out.push_str(lines);
+    }
+    out.push_str("---\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn store() -> MemorialStore {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        for (id, name, cemetery) in [
+            (1, "John Smith", "Oak Hill: Section A"),
+            (2, "Mary Smith", "Oak Hill: Section A"),
+            (3, "Ann Brown", "Elm Grove"),
+        ] {
+            store
+                .store(&Memorial {
+                    id: Some(id),
+                    name: name.to_string(),
+                    death_date: Some("1901".to_string()),
+                    burial_location: Some(cemetery.to_string()),
+                    ..Memorial::default()
+                })
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn test_export_vault_notes_and_links() {
+        let store = store();
+        let dir = tempdir().unwrap();
+        let summary = export_vault(&store, &MemorialQuery::new(), dir.path()).unwrap();
+        assert_eq!((summary.created, summary.cemeteries), (3, 2));
+
+        let note = fs::read_to_string(dir.path().join("memorials/John Smith (1).md")).unwrap();
+        assert!(note.starts_with("---\nid: 1\nname: \"John Smith\"\n"));
+        assert!(note.contains("person_name:\n  given: [\"John\"]\n"));
+        assert!(note.contains("plot_coordinates: null\n"));
+        assert!(note.contains("> Burial: Oak Hill: Section A\n"));
+        assert!(note.contains("Cemetery: [[Oak Hill Section A|Oak Hill: Section A]]"));
+
+        let cemetery =
+            fs::read_to_string(dir.path().join("cemeteries/Oak Hill Section A.md")).unwrap();
+        assert!(cemetery.contains("- [[John Smith (1)|John Smith]] (? – 1901)\n"));
+        assert!(cemetery.contains("[[Mary Smith (2)|Mary Smith]]"));
+    }
+
+    #[test]
+    fn test_filtered_export_keeps_cemetery_backlinks() {
+        let store = store();
+        let dir = tempdir().unwrap();
+        export_vault(&store, &MemorialQuery::new(), dir.path()).unwrap();
+
+        let path = dir.path().join("cemeteries/Oak Hill Section A.md");
+        let full = fs::read_to_string(&path).unwrap();
+        let summary = export_vault(&store, &MemorialQuery::new().name("John"), dir.path()).unwrap();
+        assert_eq!((summary.unchanged, summary.cemeteries), (1, 0));
+
+        let filtered = fs::read_to_string(&path).unwrap();
+        assert_eq!(filtered, full);
+        assert!(filtered.contains("memorials: 2\n"));
+        assert!(filtered.contains("[[Mary Smith (2)|Mary Smith]]"));
+    }
+
+    #[test]
+    fn test_cemeteries_differing_in_punctuation_get_separate_notes() {
+        let mut store = store();
+        store
+            .store(&Memorial {
+                id: Some(4),
+                name: "Ruth Green".to_string(),
+                burial_location: Some("Oak Hill Section A".to_string()),
+                ..Memorial::default()
+            })
+            .unwrap();
+        let dir = tempdir().unwrap();
+        let summary = export_vault(&store, &MemorialQuery::new(), dir.path()).unwrap();
+        assert_eq!(summary.cemeteries, 3);
+
+        let note = fs::read_to_string(dir.path().join("memorials/Ruth Green (4).md")).unwrap();
+        assert!(note.contains("Cemetery: [[Oak Hill Section A]]"));
+        let note = fs::read_to_string(dir.path().join("memorials/John Smith (1).md")).unwrap();
+        assert!(note.contains("Cemetery: [[Oak Hill Section A 2|Oak Hill: Section A]]"));
+
+        let cemetery =
+            fs::read_to_string(dir.path().join("cemeteries/Oak Hill Section A.md")).unwrap();
+        assert!(cemetery.contains("[[Ruth Green (4)|Ruth Green]]"));
+        assert!(!cemetery.contains("John Smith"));
+        let cemetery =
+            fs::read_to_string(dir.path().join("cemeteries/Oak Hill Section A 2.md")).unwrap();
+        assert!(cemetery.contains("[[John Smith (1)|John Smith]]"));
+        assert!(!cemetery.contains("Ruth Green"));
+    }
+
+    #[test]
+    fn test_export_vault_keeps_user_sections() {
+        let mut store = store();
+        let dir = tempdir().unwrap();
+        export_vault(&store, &MemorialQuery::new().name("John"), dir.path()).unwrap();
+
+        // The user renames the note, adds a tag and writes research notes around the block
+        let old = dir.path().join("memorials/John Smith (1).md");
+        let path = dir.path().join("memorials/Grandpa John (1).md");
+        let note = fs::read_to_string(&old).unwrap();
+        let note = note.replacen("\n---\n", "\ntags:\n  - family\n---\n", 1)
+            + "\n## My research\n\nSee the 1880 census.\n";
+        fs::write(&path, note.replacen("<!--", "Intro\n\n<!--", 1)).unwrap();
+        fs::remove_file(old).unwrap();
+
+        let again = export_vault(&store, &MemorialQuery::new().name("John"), dir.path()).unwrap();
+        assert_eq!((again.created, again.unchanged), (0, 1));
+
+        let mut john = store.get(1).unwrap().unwrap();
+        john.death_date = Some("3 Mar 1902".to_string());
+        store.store(&john).unwrap();
+        let again = export_vault(&store, &MemorialQuery::new().name("John"), dir.path()).unwrap();
+        assert_eq!(again.updated, 1);
+
+        let note = fs::read_to_string(&path).unwrap();
+        assert!(note.contains("death_date: \"3 Mar 1902\"\n"));
+        assert!(note.contains("> Death: 3 Mar 1902\n"));
+        assert!(note.contains("tags:\n  - family\n---\nIntro\n\n<!-- findagrave:begin -->"));
+        assert!(
+            note.ends_with("<!-- findagrave:end -->\n\n## My research\n\nSee the 1880 census.\n")
+        );
+        assert!(!note.contains("1901"));
+    }
+}
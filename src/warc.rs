@@ -0,0 +1,552 @@
+use crate::db::MemorialStore;
+use crate::files::memorial_id_from_url;
+use crate::{parse_page, Error, Result};
+use chrono::{DateTime, Utc};
+use flate2::read::{GzDecoder, MultiGzDecoder};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// A Find a Grave memorial page captured in a WARC file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarcCapture {
+    /// URL the page was captured from
+    pub url: String,
+    /// Memorial ID taken from the URL
+    pub memorial_id: u64,
+    /// UTC capture time (`YYYY-MM-DD HH:MM:SS`)
+    pub captured_at: String,
+    /// The page HTML
+    pub html: String,
+}
+
+/// Outcome of importing one captured memorial page
+///
+/// A record that could not be read has no memorial ID or capture time, and
+/// its `url` is the path of the WARC file.
+#[derive(Debug, Serialize)]
+pub struct CaptureReport {
+    /// URL the page was captured from
+    pub url: String,
+    /// Memorial ID taken from the URL
+    pub memorial_id: Option<u64>,
+    /// UTC capture time (`YYYY-MM-DD HH:MM:SS`)
+    pub captured_at: Option<String>,
+    /// Name parsed from the page, if it could be parsed
+    pub name: Option<String>,
+    /// Whether a new revision was stored; `false` if it was already imported
+    pub stored: bool,
+    /// Error that stopped the import, if any
+    pub error: Option<String>,
+}
+
+/// Totals for a WARC import
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WarcImportSummary {
+    /// Number of memorial pages found
+    pub captures: usize,
+    /// Number of captures stored as new revisions
+    pub stored: usize,
+    /// Number of captures already stored by an earlier import
+    pub duplicates: usize,
+    /// Number of records that could not be read, parsed or stored
+    pub failed: usize,
+}
+
+/// Iterator over the memorial page captures in a WARC file
+///
+/// Only successful (`200`) HTTP responses for memorial URLs are yielded;
+/// requests, metadata, redirects and other pages are skipped. A record that
+/// cannot be read is yielded as an error, and reading carries on with the
+/// next record unless the file itself cannot be read any further.
+pub struct WarcCaptures {
+    reader: Box<dyn BufRead>,
+    /// Version line of the next record, found while skipping unreadable data
+    next_version: Option<String>,
+    /// Set once the file cannot be read any further
+    done: bool,
+}
+
+impl Iterator for WarcCaptures {
+    type Item = Result<WarcCapture>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.done {
+                return None;
+            }
+            let record = match read_record(&mut self.reader, &mut self.next_version) {
+                Ok(Some(record)) => record,
+                Ok(None) => return None,
+                Err(err) => {
+                    // Decompression and read errors leave nothing to resynchronise on
+                    self.done = matches!(err, Error::IoError(_));
+                    return Some(Err(err));
+                }
+            };
+            match memorial_capture(&record) {
+                Ok(Some(capture)) => return Some(Ok(capture)),
+                Ok(None) => continue,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Open a `.warc` or gzipped `.warc.gz` file and read its memorial captures
+pub fn read_warc(path: &Path) -> Result<WarcCaptures> {
+    let mut file = BufReader::new(File::open(path)?);
+    let gzipped = file.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+    let reader: Box<dyn BufRead> = if gzipped {
+        // Each record is usually its own gzip member
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(file)
+    };
+    Ok(WarcCaptures {
+        reader,
+        next_version: None,
+        done: false,
+    })
+}
+
+/// Import every memorial capture in a WARC file as a dated revision
+///
+/// Each capture is parsed with `parse_page` and stored as a revision dated
+/// with its capture time, which becomes the access date when the revision is
+/// cited. Records that cannot be read are reported and counted as failed
+/// without stopping the import. `on_report` is called as each capture
+/// finishes.
+pub fn import_warc<F>(path: &Path, db_path: &str, mut on_report: F) -> Result<WarcImportSummary>
+where
+    F: FnMut(&CaptureReport),
+{
+    let mut store = MemorialStore::open(db_path)?;
+    let mut summary = WarcImportSummary::default();
+
+    for capture in read_warc(path)? {
+        let capture = match capture {
+            Ok(capture) => capture,
+            Err(err) => {
+                summary.failed += 1;
+                on_report(&CaptureReport {
+                    url: path.display().to_string(),
+                    memorial_id: None,
+                    captured_at: None,
+                    name: None,
+                    stored: false,
+                    error: Some(err.to_string()),
+                });
+                continue;
+            }
+        };
+        let mut report = CaptureReport {
+            url: capture.url.clone(),
+            memorial_id: Some(capture.memorial_id),
+            captured_at: Some(capture.captured_at.clone()),
+            name: None,
+            stored: false,
+            error: None,
+        };
+
+        summary.captures += 1;
+        let result = parse_page(&capture.html).and_then(|mut memorial| {
+            // The URL identifies the memorial even when the old page layout does not
+            memorial.id = memorial.id.or(Some(capture.memorial_id));
+            report.name = Some(memorial.name.clone());
+            store.store_capture(&memorial, &capture.captured_at)
+        });
+        match result {
+            Ok(true) => {
+                summary.stored += 1;
+                report.stored = true;
+            }
+            Ok(false) => summary.duplicates += 1,
+            Err(err) => {
+                summary.failed += 1;
+                report.error = Some(err.to_string());
+            }
+        }
+
+        on_report(&report);
+    }
+
+    Ok(summary)
+}
+
+/// Largest record block read into memory; memorial pages are far smaller
+const MAX_RECORD_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A single WARC record: its named headers and content block
+struct WarcRecord {
+    headers: Vec<(String, String)>,
+    block: Vec<u8>,
+}
+
+impl WarcRecord {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Read the next record, or `None` at the end of the file
+///
+/// After an error the reader is left at the start of the next record where
+/// possible: an oversized block is skipped, and data that is not a record is
+/// skipped up to the next `WARC/` version line, which is kept in
+/// `next_version`.
+fn read_record(
+    reader: &mut dyn BufRead,
+    next_version: &mut Option<String>,
+) -> Result<Option<WarcRecord>> {
+    let mut line = next_version.take().unwrap_or_default();
+    // Records are separated by blank lines
+    while line.trim().is_empty() {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+    }
+    if !line.starts_with("WARC/") {
+        let found = line.trim().to_string();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            if line.starts_with("WARC/") {
+                *next_version = Some(line);
+                break;
+            }
+        }
+        return Err(Error::ParseError(format!(
+            "Expected a WARC record, found: {}",
+            found
+        )));
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let record = WarcRecord {
+        headers,
+        block: Vec::new(),
+    };
+    let length: u64 = record
+        .header("Content-Length")
+        .and_then(|length| length.parse().ok())
+        .ok_or_else(|| Error::ParseError("WARC record without Content-Length".to_string()))?;
+    if length > MAX_RECORD_SIZE {
+        std::io::copy(&mut reader.take(length), &mut std::io::sink())?;
+        return Err(Error::ParseError(format!(
+            "WARC record of {} bytes is larger than the {} byte limit",
+            length, MAX_RECORD_SIZE
+        )));
+    }
+
+    // Read no more than the header promises, so a bad length cannot make us
+    // allocate more than the file holds
+    let mut block = Vec::new();
+    reader.take(length).read_to_end(&mut block)?;
+    if (block.len() as u64) < length {
+        return Err(Error::ParseError(format!(
+            "WARC record truncated: expected {} bytes, found {}",
+            length,
+            block.len()
+        )));
+    }
+    Ok(Some(WarcRecord { block, ..record }))
+}
+
+/// Turn a response record for a memorial page into a capture
+fn memorial_capture(record: &WarcRecord) -> Result<Option<WarcCapture>> {
+    if !record
+        .header("WARC-Type")
+        .is_some_and(|kind| kind.eq_ignore_ascii_case("response"))
+    {
+        return Ok(None);
+    }
+    let Some(url) = record.header("WARC-Target-URI") else {
+        return Ok(None);
+    };
+    let url = url.trim_matches(['<', '>']);
+    let Some(memorial_id) = memorial_id_from_url(url) else {
+        return Ok(None);
+    };
+    let Some(html) = http_body(&record.block) else {
+        return Ok(None);
+    };
+
+    let date = record
+        .header("WARC-Date")
+        .ok_or_else(|| Error::ParseError(format!("No WARC-Date for {}", url)))?;
+    let captured_at = DateTime::parse_from_rfc3339(date)
+        .map_err(|e| Error::ParseError(format!("Invalid WARC-Date {}: {}", date, e)))?
+        .with_timezone(&Utc)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+
+    Ok(Some(WarcCapture {
+        url: url.to_string(),
+        memorial_id,
+        captured_at,
+        html,
+    }))
+}
+
+/// Body of a successful HTTP response, undoing chunked and gzip encodings
+fn http_body(response: &[u8]) -> Option<String> {
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|pos| (pos, pos + 4))
+        .or_else(|| {
+            response
+                .windows(2)
+                .position(|window| window == b"\n\n")
+                .map(|pos| (pos, pos + 2))
+        })?;
+    let head = String::from_utf8_lossy(&response[..split.0]);
+    let mut body = response[split.1..].to_vec();
+
+    let mut lines = head.lines();
+    let status = lines.next()?.split_whitespace().nth(1)?;
+    if status != "200" {
+        return None;
+    }
+
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| {
+            (
+                name.trim().to_ascii_lowercase(),
+                value.trim().to_ascii_lowercase(),
+            )
+        })
+        .collect();
+    let has = |name: &str, value: &str| {
+        headers
+            .iter()
+            .any(|(header, v)| header == name && v.contains(value))
+    };
+
+    if has("transfer-encoding", "chunked") {
+        body = dechunk(&body)?;
+    }
+    if has("content-encoding", "gzip") {
+        let mut decoded = Vec::new();
+        GzDecoder::new(body.as_slice())
+            .read_to_end(&mut decoded)
+            .ok()?;
+        body = decoded;
+    }
+
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Reassemble a body sent with chunked transfer encoding
+fn dechunk(mut data: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data.windows(2).position(|window| window == b"\r\n")?;
+        let size = std::str::from_utf8(&data[..line_end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Some(body);
+        }
+        body.extend_from_slice(data.get(..size)?);
+        data = data.get(size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::list_revisions;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn record(kind: &str, url: &str, date: &str, block: &[u8]) -> Vec<u8> {
+        let mut record = format!(
+            "WARC/1.0\r\nWARC-Type: {}\r\nWARC-Target-URI: {}\r\nWARC-Date: {}\r\n\
+             Content-Length: {}\r\n\r\n",
+            kind,
+            url,
+            date,
+            block.len()
+        )
+        .into_bytes();
+        record.extend_from_slice(block);
+        record.extend_from_slice(b"\r\n\r\n");
+        record
+    }
+
+    fn page(name: &str) -> Vec<u8> {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\r\n\
+             <html><body><h1 itemprop=\"name\">{}</h1></body></html>",
+            name
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_import_dated_revisions() {
+        let dir = tempdir().unwrap();
+        let url = "https://www.findagrave.com/memorial/555/jane-doe";
+
+        let mut chunked = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for chunk in [
+            "<html><body><h1 itemprop=\"name",
+            "\">Jane Doe</h1></body>",
+            "",
+        ] {
+            chunked.extend(format!("{:x}\r\n{}\r\n", chunk.len(), chunk).into_bytes());
+        }
+        let mut warc = Vec::new();
+        warc.extend(record(
+            "request",
+            url,
+            "2012-05-01T10:00:00Z",
+            b"GET / HTTP/1.1\r\n\r\n",
+        ));
+        warc.extend(record(
+            "response",
+            url,
+            "2012-05-01T10:00:00Z",
+            &page("Jane Smith"),
+        ));
+        warc.extend(record("response", url, "2016-07-04T08:30:00Z", &chunked));
+        warc.extend(record(
+            "response",
+            "https://www.findagrave.com/cemetery/1",
+            "2016-07-04T08:30:00Z",
+            &page("Oak Hill"),
+        ));
+        warc.extend(record(
+            "response",
+            "http://www.findagrave.com/cgi-bin/fg.cgi?page=gr&GRid=556",
+            "2009-01-01T00:00:00Z",
+            b"HTTP/1.1 404 Not Found\r\n\r\n",
+        ));
+
+        // Gzip each record separately, as crawlers do
+        let path = dir.path().join("crawl.warc.gz");
+        let mut gz = Vec::new();
+        for member in [
+            &warc[..],
+            &record("response", url, "2014-01-01T00:00:00Z", &page("Jane Doe")),
+        ] {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(member).unwrap();
+            gz.extend(encoder.finish().unwrap());
+        }
+        std::fs::write(&path, gz).unwrap();
+
+        let db_path = dir.path().join("test.db");
+        let db_path = db_path.to_str().unwrap();
+        let mut names = Vec::new();
+        let summary = import_warc(&path, db_path, |report| {
+            names.push(report.name.clone().unwrap_or_default())
+        })
+        .unwrap();
+        assert_eq!(names, ["Jane Smith", "Jane Doe", "Jane Doe"]);
+        assert_eq!((summary.captures, summary.stored), (3, 3));
+
+        // Revisions are dated by capture, and the row holds the newest capture
+        let dates: Vec<_> = list_revisions(555, db_path)
+            .unwrap()
+            .into_iter()
+            .map(|revision| revision.fetched_at)
+            .collect();
+        assert_eq!(
+            dates,
+            [
+                "2012-05-01 10:00:00",
+                "2014-01-01 00:00:00",
+                "2016-07-04 08:30:00"
+            ]
+        );
+        let store = MemorialStore::open(db_path).unwrap();
+        assert_eq!(store.get(555).unwrap().unwrap().name, "Jane Doe");
+
+        // An old capture is not a fresh fetch, so refresh still picks it up
+        let day = std::time::Duration::from_secs(86_400);
+        assert_eq!(store.stale_ids(day).unwrap(), vec![555]);
+
+        let again = import_warc(&path, db_path, |_| {}).unwrap();
+        assert_eq!((again.stored, again.duplicates), (0, 3));
+    }
+
+    #[test]
+    fn test_rejects_non_warc_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("page.html");
+        std::fs::write(&path, "<html></html>").unwrap();
+        assert!(read_warc(&path).unwrap().next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_content_lengths() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("bad.warc");
+        let url = "https://www.findagrave.com/memorial/555";
+
+        let mut truncated = record("response", url, "2012-05-01T10:00:00Z", &page("Jane Doe"));
+        truncated.truncate(truncated.len() - 20);
+        std::fs::write(&path, truncated).unwrap();
+        let err = read_warc(&path).unwrap().next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("truncated"));
+
+        let huge = format!(
+            "WARC/1.0\r\nWARC-Type: response\r\nContent-Length: {}\r\n\r\n",
+            u64::MAX
+        );
+        std::fs::write(&path, huge).unwrap();
+        let err = read_warc(&path).unwrap().next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("limit"));
+    }
+
+    #[test]
+    fn test_import_continues_after_bad_records() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("crawl.warc");
+        let url = "https://www.findagrave.com/memorial/555";
+
+        let mut warc = record("response", url, "not a date", &page("Jane Smith"));
+        warc.extend(b"garbage between records\r\n");
+        warc.extend(record(
+            "response",
+            url,
+            "2014-01-01T00:00:00Z",
+            &page("Jane Doe"),
+        ));
+        std::fs::write(&path, warc).unwrap();
+
+        let mut errors = Vec::new();
+        let summary = import_warc(
+            &path,
+            dir.path().join("test.db").to_str().unwrap(),
+            |report| errors.extend(report.error.clone()),
+        )
+        .unwrap();
+        assert_eq!((summary.stored, summary.failed), (1, 2));
+        assert!(errors[0].contains("Invalid WARC-Date"));
+        assert!(errors[1].contains("garbage between records"));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::db::MemorialStore;
+use crate::{DateQualifier, Memorial, MemorialQuery, PartialDate, Result, SortField};
+use chrono::NaiveDate;
+use std::io::Write;
+
+/// WikiTree profile text for a memorial: a biography and its Find a Grave source
+///
+/// The biography is built from the birth, death and burial details, with the
+/// burial sentence cited by an inline `<ref>` holding the `{{FindAGrave}}`
+/// source template. `accessed` is the time the page was fetched
+/// (`YYYY-MM-DD HH:MM:SS`), written into the citation as the access date.
+pub fn to_wikitree(memorial: &Memorial, accessed: Option<&str>) -> String {
+    let name = memorial.parsed_name();
+    // Later sentences use the first given name, as WikiTree biographies do
+    let short = name
+        .given
+        .first()
+        .cloned()
+        .unwrap_or_else(|| memorial.name.clone());
+
+    let mut sentences = Vec::new();
+    let mut first = format!("'''{}'''", memorial.name);
+    if let Some(birth) = event(&memorial.birth_date, &memorial.birth_location) {
+        sentences.push(format!("{} was born{}.", first, birth));
+        first = short.clone();
+    }
+    if let Some(death) = event(&memorial.death_date, &memorial.death_location) {
+        sentences.push(format!("{} died{}.", first, death));
+        first = short.clone();
+    }
+
+    let reference = format!(
+        "<ref name=\"findagrave\">{}</ref>",
+        source(memorial, accessed)
+    );
+    match &memorial.burial_location {
+        Some(burial) if memorial.is_cenotaph => sentences.push(format!(
+            "{} is remembered by a cenotaph in {}.{}",
+            first, burial, reference
+        )),
+        Some(burial) if memorial.not_buried_here => sentences.push(format!(
+            "{} is remembered in {}, but is not buried there.{}",
+            first, burial, reference
+        )),
+        Some(burial) => sentences.push(format!("{} was buried in {}.{}", first, burial, reference)),
+        None => sentences.push(format!(
+            "{} has a memorial on Find a Grave.{}",
+            first, reference
+        )),
+    }
+    if memorial.is_veteran {
+        sentences.push(format!("{} was a veteran.", short));
+    }
+
+    format!(
+        "== Biography ==\n{}\n\n== Sources ==\n<references />\n",
+        sentences.join(" ")
+    )
+}
+
+/// Write WikiTree profile text for every matching memorial, in name order
+///
+/// Profiles are separated by a blank line, and each citation's access date
+/// is the time its newest stored revision was fetched. Returns the number of
+/// memorials written.
+pub fn export_wikitree<W: Write>(
+    store: &MemorialStore,
+    query: &MemorialQuery,
+    out: &mut W,
+) -> Result<usize> {
+    let memorials = store.query_all(&query.clone().sort_by(SortField::Name, false))?;
+    for (index, memorial) in memorials.iter().enumerate() {
+        let accessed = match memorial.id {
+            Some(id) => store.last_fetched(id)?,
+            None => None,
+        };
+        if index > 0 {
+            writeln!(out)?;
+        }
+        write!(out, "{}", to_wikitree(memorial, accessed.as_deref()))?;
+    }
+    Ok(memorials.len())
+}
+
+/// When and where a life event happened, such as ` on 3 Mar 1850 in Ohio`
+///
+/// Qualified dates read as prose, such as ` about 1820` or ` before 1900`;
+/// dates recorded as unknown are left out.
+fn event(date: &Option<String>, place: &Option<String>) -> Option<String> {
+    let date = date
+        .as_deref()
+        .map(str::trim)
+        .filter(|date| !date.is_empty() && !date.eq_ignore_ascii_case("unknown"))
+        .map(|date| match date.parse::<PartialDate>() {
+            Ok(parsed) if parsed.qualifier != DateQualifier::Exact => {
+                // Drop the qualifier word as written, such as `abt` or `c.`
+                let bare = date
+                    .split_once(char::is_whitespace)
+                    .map_or(date, |(_, rest)| rest);
+                let word = match parsed.qualifier {
+                    DateQualifier::Before => "before",
+                    DateQualifier::After => "after",
+                    _ => "about",
+                };
+                format!(" {} {}", word, bare.trim_start())
+            }
+            Ok(parsed) if parsed.day.is_some() => format!(" on {}", date),
+            _ => format!(" in {}", date),
+        });
+    let place = place.as_deref().map(|place| format!(" in {}", place));
+    if date.is_none() && place.is_none() {
+        return None;
+    }
+    Some(format!(
+        "{}{}",
+        date.unwrap_or_default(),
+        place.unwrap_or_default()
+    ))
+}
+
+/// Source citation in the style of WikiTree's Find a Grave citations
+fn source(memorial: &Memorial, accessed: Option<&str>) -> String {
+    let mut source = String::from("Find a Grave, database and images (https://www.findagrave.com");
+    if let Some(accessed) = accessed {
+        source.push_str(&format!(" : accessed {}", accessed_date(accessed)));
+    }
+    source.push_str(&format!("), memorial page for {}", memorial.name));
+
+    let years = match (memorial.birth_year(), memorial.death_year()) {
+        (None, None) => None,
+        (birth, death) => Some(format!(
+            "{}–{}",
+            birth.map(|year| year.to_string()).unwrap_or_default(),
+            death.map(|year| year.to_string()).unwrap_or_default()
+        )),
+    };
+    if let Some(years) = years {
+        source.push_str(&format!(" ({})", years));
+    }
+    if let Some(id) = memorial.id {
+        source.push_str(&format!(
+            ", {{{{FindAGrave|{}}}}}, Find a Grave Memorial ID {}",
+            id, id
+        ));
+    }
+    if let Some(burial) = &memorial.burial_location {
+        source.push_str(&format!(", citing {}", burial));
+    }
+    if let Some(maintainer) = &memorial.maintained_by {
+        source.push_str(&format!("; Maintained by {}", maintainer));
+    }
+    source.push('.');
+    source
+}
+
+/// Access date written out in full, such as `19 October 2026`
+fn accessed_date(accessed: &str) -> String {
+    accessed
+        .get(..10)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .map(|date| date.format("%-d %B %Y").to_string())
+        .unwrap_or_else(|| accessed.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memorial() -> Memorial {
+        Memorial {
+            id: Some(1234),
+            name: "John Henry Smith".to_string(),
+            birth_date: Some("3 Mar 1840".to_string()),
+            birth_location: Some("Columbus, Ohio".to_string()),
+            death_date: Some("1901".to_string()),
+            burial_location: Some("Green Lawn Cemetery".to_string()),
+            maintained_by: Some("Jane Doe".to_string()),
+            is_veteran: true,
+            ..Memorial::default()
+        }
+    }
+
+    #[test]
+    fn test_wikitree_biography_and_source() {
+        let text = to_wikitree(&memorial(), Some("2026-10-19 08:30:00"));
+        assert_eq!(
+            text,
+            "== Biography ==\n\
+             '''John Henry Smith''' was born on 3 Mar 1840 in Columbus, Ohio. \
+             John died in 1901. \
+             John was buried in Green Lawn Cemetery.<ref name=\"findagrave\">\
+             Find a Grave, database and images (https://www.findagrave.com : accessed 19 October 2026), \
+             memorial page for John Henry Smith (1840–1901), {{FindAGrave|1234}}, \
+             Find a Grave Memorial ID 1234, citing Green Lawn Cemetery; Maintained by Jane Doe.</ref> \
+             John was a veteran.\n\n\
+             == Sources ==\n<references />\n"
+        );
+
+        let cenotaph = Memorial {
+            birth_date: None,
+            birth_location: None,
+            death_date: None,
+            is_cenotaph: true,
+            ..memorial()
+        };
+        let text = to_wikitree(&cenotaph, None);
+        assert!(text.contains(
+            "'''John Henry Smith''' is remembered by a cenotaph in Green Lawn Cemetery."
+        ));
+        assert!(text.contains("(https://www.findagrave.com), memorial page"));
+
+        let vague = Memorial {
+            birth_date: Some("abt 1820".to_string()),
+            birth_location: None,
+            death_date: Some("bef. 3 Mar 1900".to_string()),
+            ..memorial()
+        };
+        let text = to_wikitree(&vague, None);
+        assert!(text
+            .contains("'''John Henry Smith''' was born about 1820. John died before 3 Mar 1900."));
+
+        let unknown = Memorial {
+            death_date: Some("Unknown".to_string()),
+            death_location: Some("Ohio".to_string()),
+            ..memorial()
+        };
+        let text = to_wikitree(&unknown, None);
+        assert!(text.contains("John died in Ohio."));
+        assert!(!text.contains("Unknown"));
+    }
+
+    #[test]
+    fn test_export_wikitree() {
+        let mut store = MemorialStore::open_in_memory().unwrap();
+        store.store(&memorial()).unwrap();
+        store
+            .store(&Memorial {
+                id: Some(99),
+                name: "Ann Brown".to_string(),
+                ..Memorial::default()
+            })
+            .unwrap();
+
+        let mut out = Vec::new();
+        let count = export_wikitree(&store, &MemorialQuery::new(), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(count, 2);
+        assert!(text.starts_with("== Biography ==\n'''Ann Brown''' has a memorial"));
+        assert!(text.contains("<references />\n\n== Biography ==\n'''John Henry Smith'''"));
+        assert!(text.contains(" : accessed "));
+    }
+}